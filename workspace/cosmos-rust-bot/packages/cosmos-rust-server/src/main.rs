@@ -22,10 +22,13 @@ use actix_web_httpauth::extractors::bearer::BearerAuth;
 use serde_json::json;
 
 
+use bot_library::shared::config::{init_config, CONFIG};
+use bot_library::shared::settings::client_send_settings_request;
 use cosmos_rust_interface::utils::entry::*;
 use cosmos_rust_interface::utils::entry::db::query::socket::client_send_query_request;
 
-const QUERY_SOCKET: &str = "./tmp/cosmos_rust_bot_query_socket";
+/// Comma separated user hashes that may change the settings.
+const ADMIN_USER_HASHES_ENV: &str = "CRB_ADMIN_USER_HASHES";
 
 
 async fn validator(
@@ -52,7 +55,7 @@ async fn validator(
                 }
             };
 
-            if let Ok(CosmosRustServerValue::Notification(n)) = client_send_query_request(QUERY_SOCKET, request) {
+            if let Ok(CosmosRustServerValue::Notification(n)) = client_send_query_request(&CONFIG.query_socket(), request) {
                 log::info!("Notification: {:?}", &n);
 
                 let mut is_authorized = false;
@@ -90,6 +93,48 @@ async fn verify(_req: HttpRequest, _basic_auth: BasicAuth) -> actix_web::Result<
     Ok(HttpResponse::Ok().finish())
 }
 
+fn is_admin(basic_auth: &BasicAuth) -> bool {
+    std::env::var(ADMIN_USER_HASHES_ENV)
+        .map(|admins| admins.split(',').any(|x| x.trim() == basic_auth.user_id()))
+        .unwrap_or(false)
+}
+
+fn settings_request(request: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    client_send_settings_request(&CONFIG.query_socket(), request)
+}
+
+fn settings_response(request: serde_json::Value) -> HttpResponse {
+    match settings_request(request) {
+        Ok(response) if response.get("Error").is_some() => HttpResponse::BadRequest().json(response),
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(err) => HttpResponse::ServiceUnavailable().json(json!({ "Error": err.to_string() })),
+    }
+}
+
+/// GET /settings returns all settings, GET /settings/<name> a single one.
+async fn get_settings(req: HttpRequest, basic_auth: BasicAuth) -> actix_web::Result<HttpResponse> {
+    if !is_admin(&basic_auth) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    let pointer = req.match_info().get("name").map(|name| format!("/{}", name));
+    Ok(settings_response(json!({ "Get": pointer })))
+}
+
+/// PATCH /settings with a JSON merge patch, e.g. {"pause_requested": true}
+async fn patch_settings(basic_auth: BasicAuth, patch: web::Json<serde_json::Value>) -> actix_web::Result<HttpResponse> {
+    if !is_admin(&basic_auth) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    Ok(settings_response(json!({ "Patch": [patch.into_inner(), format!("http:{}", basic_auth.user_id())] })))
+}
+
+async fn settings_history(basic_auth: BasicAuth) -> actix_web::Result<HttpResponse> {
+    if !is_admin(&basic_auth) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+    Ok(settings_response(json!({ "History": 100 })))
+}
+
 async fn translate(_req: HttpRequest, info: web::Path<(String, String, String)>) -> actix_web::Result<HttpResponse> {
     let (lang, blockchain, id) = info.into_inner();
 
@@ -139,7 +184,7 @@ async fn file(req: HttpRequest) -> actix_web::Result<NamedFile> {
 
     // Check if the file extension is allowed
     if ext == "json" || ext == "html" {
-        Ok(NamedFile::open(format!("./tmp/public/{}.{}", filepath, ext))?)
+        Ok(NamedFile::open(format!("{}{}.{}", CONFIG.public_path(), filepath, ext))?)
     } else {
         Err(UrlGenerationError::ResourceNotFound.into())
     }
@@ -147,6 +192,8 @@ async fn file(req: HttpRequest) -> actix_web::Result<NamedFile> {
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // paths and sockets, see bot_library::shared::config
+    init_config().map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err.to_string()))?;
     HttpServer::new(move || {
         let auth = HttpAuthentication::basic(validator);
         App::new()
@@ -157,6 +204,22 @@ async fn main() -> std::io::Result<()> {
                     .wrap(auth.clone())
                     .route(web::get().to(verify)),
             )
+            .service(
+                web::resource("/settings")
+                    .wrap(auth.clone())
+                    .route(web::get().to(get_settings))
+                    .route(web::patch().to(patch_settings)),
+            )
+            .service(
+                web::resource("/settings/history")
+                    .wrap(auth.clone())
+                    .route(web::get().to(settings_history)),
+            )
+            .service(
+                web::resource("/settings/{name}")
+                    .wrap(auth.clone())
+                    .route(web::get().to(get_settings)),
+            )
             .service(
                 web::resource("/translate/{lang}/{blockchain}/{id}")
                     //.wrap(auth)
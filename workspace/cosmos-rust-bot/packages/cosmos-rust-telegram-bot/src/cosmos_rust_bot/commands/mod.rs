@@ -1,3 +1,8 @@
+mod settings;
+mod vote;
+pub use settings::*;
+pub use vote::*;
+
 use cosmos_rust_interface::utils::entry::{db::{notification::notify_sled_db, query::socket::*}, EntriesQueryPart, SettingsPart, QueryPart, SubscriptionsQueryPart};
 use regex::{Match, Regex};
 
@@ -6,27 +11,66 @@ use std::collections::HashMap;
 use chrono::TimeZone;
 
 use cosmos_rust_interface::utils::entry::*;
+use bot_library::shared::config::CONFIG;
+use bot_library::shared::vote_preferences::{against_kind, remove_preference, votes_kind, write_preference, VotePreference, VOTE_PREFERENCES_DIR};
 
 
 lazy_static! {
     static ref LIST_BLOCKCHAINS: Vec<String> = {
-        let data = std::fs::read_to_string("./tmp/supported_blockchains.json").expect("Unable to read file");
+        let data = std::fs::read_to_string(CONFIG.data_path("supported_blockchains.json")).expect("Unable to read file");
         let supported_blockchains: HashMap<String, serde_json::Value> = serde_json::from_str(&data).expect("Unable to parse JSON");
         supported_blockchains.into_keys().collect()
     };
 }
 
+/// Sends a query to cosmos-rust-bot, traced within the span of the user command that caused it.
+#[tracing::instrument(skip_all, fields(query = ?request.query_part))]
+fn send_query(request: UserQuery) -> anyhow::Result<CosmosRustServerValue> {
+    match client_send_query_request(&CONFIG.query_socket(), request) {
+        Ok(response) => {
+            tracing::info!("query answered");
+            Ok(response)
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "query failed");
+            Err(err.into())
+        }
+    }
+}
+
 const SUB_UNSUB: &str = "(subscribe|unsubscribe)";
 
+/// `(?: (a|b|c))`, one of the words, captured without the separating space. Longer words are tried first.
+pub fn one_of<T: AsRef<str>>(words: &[T]) -> String {
+    let mut words: Vec<String> = words.iter().map(|x| regex::escape(x.as_ref())).collect();
+    words.sort_by_key(|x| std::cmp::Reverse(x.len()));
+    format!("(?: ({}))", words.join("|"))
+}
+
+/// Commands that query MetaData entries: `<command>[ <blockchain>][ <kind>][ <limit>][ subscribe|unsubscribe]`,
+/// captures: 1 blockchain, 2 kind, 3 limit, 4 subscribe/unsubscribe.
+fn meta_data_regex(command: &str, kinds: &[&str], blockchains: &[String]) -> Regex {
+    let kinds = if kinds.is_empty() { "()".to_string() } else { format!("{}?", one_of(kinds)) };
+    Regex::new(&format!(
+        "{}{}?{}(?: ([0-9]+))?(?: {})?(?:\\s|$)",
+        command,
+        one_of(blockchains),
+        kinds,
+        SUB_UNSUB
+    ))
+    .unwrap()
+}
+
 const LIST_TASK_STATES: [&str;6] = ["pending","resolved","upcoming","failed","unknown","reserved"];
 
 const LIST_PROPOSAL_STATUS: [&str;6] = ["nil","passed","failed","rejected","deposit period","voting period"];
 const LIST_PROPOSAL_TYPE: [&str;8] = ["text","community pool spend","parameter change","software upgrade","client update","update pool incentives","store code","unknown"];
 const LIST_PROPOSAL_TIME: [&str;5] = ["latest","submit","deposit end","voting start","voting end"];
 
-const LIST_GOV_PRPSL_VIEWS: [&str;13] = ["status","briefing0","briefing1","briefing2","briefing3","briefing4","briefing5","briefing6","briefing7","briefing8","briefing9","briefing10","content"];
+const LIST_GOV_PRPSL_VIEWS: [&str;14] = ["status","briefing0","briefing1","briefing2","briefing3","briefing4","briefing5","briefing6","briefing7","briefing8","briefing9","briefing10","content","votes"];
+
+const LIST_VOTE_OPTIONS: [(&str,&str);4] = [("yes","Yes"),("no with veto","NoWithVeto"),("no","No"),("abstain","Abstain")];
 
-const QUERY_SOCKET: &str = "./tmp/cosmos_rust_bot_query_socket";
 
 use lazy_static::lazy_static;
 
@@ -60,6 +104,34 @@ lazy_static!{
 
     pub static ref VERIFY_REGEX: Regex = Regex::new(r"verify ([0-9]+)").unwrap();
 
+    pub static ref PARAM_CHANGES_REGEX: Regex = meta_data_regex("param changes", &["diff", "applied"], &LIST_BLOCKCHAINS);
+
+    pub static ref MY_VOTE_REGEX: Regex = Regex::new(
+            format!(
+                "my vote{} id([0-9]+){}(?: {})?(?:\\s|$)",
+                one_of(&LIST_BLOCKCHAINS),
+                one_of(&LIST_VOTE_OPTIONS.map(|x| x.0)),
+                SUB_UNSUB
+            )
+                .as_str(),
+        ).unwrap();
+
+    pub static ref VALIDATOR_SCORECARD_REGEX: Regex = meta_data_regex("validator scorecard", &[], &LIST_BLOCKCHAINS);
+
+    pub static ref PARAMS_HISTORY_REGEX: Regex = meta_data_regex("params history", &[], &LIST_BLOCKCHAINS);
+
+    pub static ref COMMUNITY_POOL_REGEX: Regex = meta_data_regex("community pool", &["balance", "spend"], &LIST_BLOCKCHAINS);
+
+    pub static ref AUDIT_JOURNAL_REGEX: Regex = meta_data_regex("audit journal", &["accepted", "rejected", "simulated", "broadcast"], &LIST_BLOCKCHAINS);
+
+    pub static ref AUTHZ_GRANTS_REGEX: Regex = meta_data_regex("authz grants", &["active", "expiring", "expired", "missing"], &LIST_BLOCKCHAINS);
+
+    pub static ref STAKING_REMINDERS_REGEX: Regex = meta_data_regex("staking reminders", &["pending", "reminder", "completed"], &LIST_BLOCKCHAINS);
+
+    pub static ref BALANCE_ALERTS_REGEX: Regex = meta_data_regex("balance alerts", &["incoming transfer", "balance change"], &LIST_BLOCKCHAINS);
+
+    pub static ref VOTE_MIRROR_REGEX: Regex = meta_data_regex("vote mirror", &["pending", "vetoed", "excluded", "skipped", "simulated", "cast", "rejected", "failed"], &LIST_BLOCKCHAINS);
+
 }
 
 
@@ -112,7 +184,7 @@ pub fn handle_tasks_count_list_history(user_hash: u64, msg: &str, msg_for_query:
                 user_hash: Some(user_hash)
             } };
 
-            let response = client_send_query_request(QUERY_SOCKET,request)?;
+            let response = send_query(request)?;
             notify_sled_db(db, response);
             return Ok(());
         }
@@ -155,9 +227,8 @@ pub fn handle_tasks_logs_errors_debug(user_hash: u64, msg: &str, msg_for_query:
                 register: None,
                 user_hash: Some(user_hash)
             } };
-            println!("{:?}",request);
 
-            let response = client_send_query_request(QUERY_SOCKET,request)?;
+            let response = send_query(request)?;
             notify_sled_db(db, response);
             return Ok(());
         }
@@ -181,7 +252,7 @@ pub fn handle_subscribe_unsubscribe(user_hash: u64, msg: &str, msg_for_query: &s
         user_hash: Some(user_hash)
     } };
 
-    let response = client_send_query_request(QUERY_SOCKET,request)?;
+    let response = send_query(request)?;
     notify_sled_db(db, response);
     Ok(())
 }
@@ -201,7 +272,7 @@ pub fn handle_register(user_hash: u64, msg: &str, db: &sled::Db) -> anyhow::Resu
         user_hash: Some(user_hash)
     } };
 
-    let response = client_send_query_request(QUERY_SOCKET,request)?;
+    let response = send_query(request)?;
     notify_sled_db(db, response);
     Ok(())
 }
@@ -218,7 +289,7 @@ pub fn handle_verify(user_hash: u64, msg: &str, db: &sled::Db) -> anyhow::Result
             user_hash: Some(user_hash)
         } };
 
-        let response = client_send_query_request(QUERY_SOCKET,request)?;
+        let response = send_query(request)?;
         notify_sled_db(db, response);
         return Ok(());
     } else {
@@ -227,8 +298,209 @@ pub fn handle_verify(user_hash: u64, msg: &str, db: &sled::Db) -> anyhow::Result
 
 }
 
+// Queries entries that use the MetaData layout (kind, state = blockchain),
+// the regex captures: 1 blockchains, 2 kind, 3 limit, 4 subscribe/unsubscribe.
+fn handle_meta_data_query(regex: &Regex, index: &str, user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    if regex.is_match(&msg) {
+        let caps = regex.captures(&msg).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let mut filter: Vec<(String, String)> = Vec::new();
+        filter.push((
+            "kind".to_string(),
+            caps.get(2)
+                // kinds use underscores, the command separators were turned into spaces.
+                .map(|t| t.as_str().trim().replace(' ', "_"))
+                .filter(|t| !t.is_empty())
+                .unwrap_or("any".to_string()),
+        ));
+        let mut filter_list: Vec<Vec<(String, String)>> = Vec::new();
+        filter_list.push(filter);
+
+        for filter in filter_list.iter_mut() {
+            filter.push((
+                "state".to_string(),
+                caps.get(1).map(|t| t.as_str().to_upper_camel_case()).unwrap_or("any".to_string()),
+            ));
+        }
+
+        let limit = caps.get(3).map(|x| x.as_str().parse::<usize>().unwrap_or(1usize)).unwrap_or(1usize);
+
+        let subscribe = caps
+            .get(4)
+            .map(|x| x.as_str() == "subscribe")
+            .unwrap_or(false);
+        let unsubscribe = caps
+            .get(4)
+            .map(|x| x.as_str() == "unsubscribe")
+            .unwrap_or(false);
+
+        let request: UserQuery = UserQuery{ query_part: QueryPart::EntriesQueryPart(EntriesQueryPart{
+            message: msg_for_query.to_string(),
+            display: "default".to_string(),
+            indices: vec![index.to_string()],
+            filter: filter_list,
+            order_by: "index".to_string(),
+            limit,
+        }), settings_part: SettingsPart {
+            subscribe: Some(subscribe),
+            unsubscribe: Some(unsubscribe),
+            register: None,
+            user_hash: Some(user_hash)
+        } };
+
+        let response = send_query(request)?;
+        notify_sled_db(db, response);
+        return Ok(());
+    }
+    Err(anyhow::anyhow!("Error: Unknown Command!"))
+}
+
+pub fn handle_param_changes(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&PARAM_CHANGES_REGEX, "param_changes", user_hash, msg, msg_for_query, db)
+}
+
+pub fn handle_validator_scorecard(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&VALIDATOR_SCORECARD_REGEX, "validator_scorecard", user_hash, msg, msg_for_query, db)
+}
+
+pub fn handle_params_history(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&PARAMS_HISTORY_REGEX, "params_history", user_hash, msg, msg_for_query, db)
+}
+
+pub fn handle_community_pool(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&COMMUNITY_POOL_REGEX, "community_pool", user_hash, msg, msg_for_query, db)
+}
+
+pub fn handle_authz_grants(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&AUTHZ_GRANTS_REGEX, "authz_grants", user_hash, msg, msg_for_query, db)
+}
+
+pub fn handle_staking_reminders(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&STAKING_REMINDERS_REGEX, "staking_reminders", user_hash, msg, msg_for_query, db)
+}
+
+pub fn handle_balance_alerts(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&BALANCE_ALERTS_REGEX, "balance_alerts", user_hash, msg, msg_for_query, db)
+}
+
+pub fn handle_vote_mirror(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&VOTE_MIRROR_REGEX, "vote_mirror", user_hash, msg, msg_for_query, db)
+}
+
+pub fn handle_audit_journal(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    handle_meta_data_query(&AUDIT_JOURNAL_REGEX, "audit_journal", user_hash, msg, msg_for_query, db)
+}
+
+/// Subscribes to the votes of the validators the registered address delegates to that differ from the
+/// preferred vote option. The preference is handed to cosmos-rust-bot, which looks up the delegations.
+pub fn handle_my_vote(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    if MY_VOTE_REGEX.is_match(&msg) {
+        let caps = MY_VOTE_REGEX.captures(&msg).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let blockchain = caps.get(1).map(|t| t.as_str()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let proposal_id = caps.get(2).map(|t| t.as_str()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?.parse::<u64>()?;
+        let preference = caps.get(3).map(|t| t.as_str()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let option = LIST_VOTE_OPTIONS
+            .iter()
+            .find(|(x, _)| *x == preference)
+            .map(|(_, option)| option.to_string())
+            .ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+
+        let delegator = match registered_address(db, user_hash, blockchain)? {
+            Some(address) => address,
+            None => {
+                notify_sled_db(
+                    db,
+                    CosmosRustServerValue::Notify(Notify {
+                        timestamp: chrono::Utc::now().timestamp(),
+                        msg: vec![format!("No address registered for {}, use /my_address_{}_<address> first.", blockchain, blockchain)],
+                        buttons: vec![],
+                        user_hash,
+                    }),
+                );
+                return Ok(());
+            }
+        };
+
+        let unsubscribe = caps
+            .get(4)
+            .map(|x| x.as_str() == "unsubscribe")
+            .unwrap_or(false);
+
+        let preferences_dir = CONFIG.data_path(VOTE_PREFERENCES_DIR);
+        if unsubscribe {
+            remove_preference(&preferences_dir, blockchain, proposal_id, &delegator)?;
+        } else {
+            write_preference(&preferences_dir, &VotePreference {
+                blockchain: blockchain.to_string(),
+                proposal_id,
+                delegator: delegator.to_owned(),
+                option,
+            })?;
+        }
+
+        let mut filter_list: Vec<Vec<(String, String)>> = Vec::new();
+        filter_list.push(vec![
+            ("kind".to_string(), against_kind(proposal_id, &delegator)),
+            ("state".to_string(), blockchain.to_upper_camel_case()),
+        ]);
+
+        let request: UserQuery = UserQuery{ query_part: QueryPart::EntriesQueryPart(EntriesQueryPart{
+            message: msg_for_query.to_string(),
+            display: "default".to_string(),
+            indices: vec!["validator_votes".to_string()],
+            filter: filter_list,
+            order_by: "index".to_string(),
+            limit: 1,
+        }), settings_part: SettingsPart {
+            subscribe: Some(!unsubscribe),
+            unsubscribe: Some(unsubscribe),
+            register: None,
+            user_hash: Some(user_hash)
+        } };
+
+        let response = send_query(request)?;
+        notify_sled_db(db, response);
+        return Ok(());
+    }
+    Err(anyhow::anyhow!("Error: Unknown Command!"))
+}
+
+/// The votes view lists how the top validators voted on the proposal.
+fn handle_gov_prpsl_votes(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
+    let caps = LOOKUP_PROPOSALS_REGEX.captures(&msg).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+    let proposal_id = caps.get(3)
+        .and_then(|t| t.as_str().parse::<u64>().ok())
+        .ok_or(anyhow::anyhow!("Error: The votes view needs a proposal id, e.g. /gov_prpsl_votes_osmosis_id1"))?;
+    let mut filter: Vec<(String, String)> = Vec::new();
+    filter.push(("kind".to_string(), votes_kind(proposal_id)));
+    let mut filter_list: Vec<Vec<(String, String)>> = Vec::new();
+    filter_list.push(filter);
+
+    filter_list = add_filter(filter_list,msg.to_string(),LIST_BLOCKCHAINS.iter().map(|s| s.as_str()).collect(),"state",("",""));
+
+    let request: UserQuery = UserQuery{ query_part: QueryPart::EntriesQueryPart(EntriesQueryPart{
+        message: msg_for_query.to_string(),
+        display: "default".to_string(),
+        indices: vec!["validator_votes".to_string()],
+        filter: filter_list,
+        order_by: "index".to_string(),
+        limit: 1,
+    }), settings_part: SettingsPart {
+        subscribe: Some(false),
+        unsubscribe: Some(false),
+        register: None,
+        user_hash: Some(user_hash)
+    } };
+
+    let response = send_query(request)?;
+    notify_sled_db(db, response);
+    Ok(())
+}
+
 pub fn handle_gov_prpsl(user_hash: u64, msg: &str, msg_for_query: &str, db: &sled::Db) -> anyhow::Result<()> {
     if LOOKUP_PROPOSALS_REGEX.is_match(&msg) {
+        if msg.contains(" votes") {
+            return handle_gov_prpsl_votes(user_hash, msg, msg_for_query, db);
+        }
         let caps = LOOKUP_PROPOSALS_REGEX.captures(&msg).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
         let mut filter: Vec<(String, String)> = Vec::new();
         filter.push((
@@ -292,9 +564,8 @@ pub fn handle_gov_prpsl(user_hash: u64, msg: &str, msg_for_query: &str, db: &sle
             register: None,
             user_hash: Some(user_hash)
         } };
-        println!("{:?}",&request);
 
-        let response = client_send_query_request(QUERY_SOCKET,request)?;
+        let response = send_query(request)?;
         notify_sled_db(db, response);
         return Ok(());
     }
@@ -383,4 +654,30 @@ fn add_filter(filter_list: Vec<Vec<(String, String)>>, text: String, list: Vec<&
     // Append the copied and updated filter list to the new filter list and return it.
     new_filter_list.append(&mut filter_list_copy);
     new_filter_list
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn meta_data_commands_capture_blockchain_kind_and_subscription() {
+        let blockchains: Vec<String> = ["juno", "osmosis", "cosmoshub", "terra2", "terra"].iter().map(|x| x.to_string()).collect();
+        let regex = meta_data_regex("vote mirror", &["pending", "cast", "failed"], &blockchains);
+        let captures = |msg: &str| -> Vec<Option<String>> {
+            let caps = regex.captures(msg).unwrap();
+            (1..=4).map(|i| caps.get(i).map(|x| x.as_str().to_string())).collect()
+        };
+        let some = |x: &str| Some(x.to_string());
+
+        assert_eq!(captures("vote mirror juno cast subscribe"), vec![some("juno"), some("cast"), None, some("subscribe")]);
+        assert_eq!(captures("vote mirror terra2 failed 5"), vec![some("terra2"), some("failed"), some("5"), None]);
+        assert_eq!(captures("vote mirror terra"), vec![some("terra"), None, None, None]);
+        assert_eq!(captures("vote mirror pending unsubscribe"), vec![None, some("pending"), None, some("unsubscribe")]);
+        assert_eq!(captures("vote mirror"), vec![None, None, None, None]);
+        // letters of chain names are not a chain.
+        assert_eq!(captures("vote mirror sonic cast")[0], None);
+
+        let regex = meta_data_regex("params history", &[], &blockchains);
+        assert_eq!(regex.captures("params history osmosis 3").unwrap().get(1).unwrap().as_str(), "osmosis");
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::Utc;
+use cosmos_rust_interface::utils::entry::{db::notification::notify_sled_db, CosmosRustServerValue, Notify};
+use bot_library::shared::config::CONFIG;
+use bot_library::shared::settings::client_send_settings_request;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+// Admin commands to change the settings of cosmos-rust-bot at runtime, via its query socket.
+
+/// Comma separated user hashes that may change the settings.
+const ADMIN_USER_HASHES_ENV: &str = "CRB_ADMIN_USER_HASHES";
+
+lazy_static! {
+    pub static ref SETTINGS_REGEX: Regex = Regex::new("^settings (get|set|history)(?: ([a-z ]+?))?(?: (true|false|[0-9]+))?$").unwrap();
+}
+
+fn is_admin(user_hash: u64) -> bool {
+    std::env::var(ADMIN_USER_HASHES_ENV)
+        .map(|admins| admins.split(',').any(|x| x.trim() == user_hash.to_string()))
+        .unwrap_or(false)
+}
+
+fn settings_request(request: serde_json::Value) -> anyhow::Result<serde_json::Value> {
+    let response = client_send_settings_request(&CONFIG.query_socket(), request)?;
+    match response.get("Error") {
+        Some(err) => Err(anyhow::anyhow!("Error: {}", err)),
+        None => Ok(response),
+    }
+}
+
+pub fn handle_settings(user_hash: u64, msg: &str, db: &sled::Db) -> anyhow::Result<()> {
+    if SETTINGS_REGEX.is_match(&msg) {
+        let caps = SETTINGS_REGEX.captures(&msg).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let command = caps.get(1).map(|t| t.as_str()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        // the command separators were turned into spaces, setting names use underscores.
+        let pointer = caps.get(2).map(|t| format!("/{}", t.as_str().trim().replace(' ', "_")));
+        let value = caps.get(3).map(|t| serde_json::from_str::<serde_json::Value>(t.as_str()));
+
+        let reply = if !is_admin(user_hash) {
+            format!("Not authorized, add your user hash ({}) to {} of the telegram bot.", user_hash, ADMIN_USER_HASHES_ENV)
+        } else {
+            let request = match (command, pointer, value) {
+                ("get", pointer, None) => Ok(serde_json::json!({ "Get": pointer })),
+                ("set", Some(pointer), Some(Ok(value))) => Ok(serde_json::json!({ "Set": [pointer, value, format!("telegram:{}", user_hash)] })),
+                ("history", None, None) => Ok(serde_json::json!({ "History": 10 })),
+                _ => Err(anyhow::anyhow!("Error: Usage: settings get [setting] | settings set <setting> <value> | settings history")),
+            };
+            match request.and_then(settings_request) {
+                Ok(response) => serde_json::to_string_pretty(&response)?,
+                Err(err) => err.to_string(),
+            }
+        };
+        notify_sled_db(
+            db,
+            CosmosRustServerValue::Notify(Notify {
+                timestamp: Utc::now().timestamp(),
+                msg: vec![reply],
+                buttons: vec![],
+                user_hash,
+            }),
+        );
+        return Ok(());
+    }
+    Err(anyhow::anyhow!("Error: Unknown Command!"))
+}
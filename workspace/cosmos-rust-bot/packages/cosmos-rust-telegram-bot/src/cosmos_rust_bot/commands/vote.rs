@@ -0,0 +1,335 @@
+use bot_library::shared::config::CONFIG;
+use bot_library::shared::{load_user_settings, UserSettings, VoteMirrorSettings};
+use chrono::Utc;
+use cosmos_rust_interface::utils::entry::{db::notification::notify_sled_db, CosmosRustServerValue, Notify};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+
+use super::{one_of, LIST_BLOCKCHAINS, LIST_VOTE_OPTIONS};
+
+// Unsigned vote transactions, the user signs them with their own wallet, the bot never sees a key.
+// The transaction has the format of `<daemon> tx gov vote .. --generate-only`, account number and sequence
+// are filled in by the signer (e.g. `<daemon> tx sign vote.json --from <key>`).
+// The transaction is only sent in the chat, it is never written to the public directory.
+
+const REGISTERED_ADDRESSES_TREE: &str = "registered_addresses";
+const VOTE_GAS_LIMIT: u64 = 200000;
+
+lazy_static! {
+    /// Read by the vote mirror of cosmos-rust-bot, a pending vote vetoed by one of the veto_users is not cast.
+    static ref VOTE_MIRROR_VETO_PATH: String = format!("{}/", CONFIG.data_path("vote_mirror_vetoes"));
+    /// Bech32 prefix per blockchain, from the prefix field of the supported blockchains.
+    static ref BECH32_PREFIXES: HashMap<String, String> = {
+        let data = std::fs::read_to_string(CONFIG.data_path("supported_blockchains.json")).expect("Unable to read file");
+        let supported_blockchains: HashMap<String, serde_json::Value> = serde_json::from_str(&data).expect("Unable to parse JSON");
+        supported_blockchains
+            .into_iter()
+            .filter_map(|(name, blockchain)| blockchain["prefix"].as_str().map(|prefix| (name, prefix.to_string())))
+            .collect()
+    };
+    pub static ref MY_ADDRESS_REGEX: Regex = my_address_regex(&LIST_BLOCKCHAINS);
+    pub static ref VOTE_REGEX: Regex = vote_regex(&LIST_BLOCKCHAINS);
+
+    pub static ref VETO_VOTE_REGEX: Regex = Regex::new(
+            format!(
+                "^veto vote{} id([0-9]+)$",
+                one_of(&LIST_BLOCKCHAINS),
+            )
+                .as_str(),
+        ).unwrap();
+}
+
+fn my_address_regex(blockchains: &[String]) -> Regex {
+    Regex::new(format!("^my address{} ([a-z0-9]+)$", one_of(blockchains)).as_str()).unwrap()
+}
+
+fn vote_regex(blockchains: &[String]) -> Regex {
+    Regex::new(
+        format!(
+            "^vote{} id([0-9]+){}(?: ([a-z0-9]+))?$",
+            one_of(blockchains),
+            one_of(&LIST_VOTE_OPTIONS.map(|x| x.0)),
+        )
+        .as_str(),
+    )
+    .unwrap()
+}
+
+/// The address must be valid bech32 with the account prefix of the blockchain.
+fn check_address(address: &str, prefix: Option<&str>) -> anyhow::Result<()> {
+    let prefix = prefix.ok_or(anyhow::anyhow!("the address prefix of this blockchain is unknown"))?;
+    let (hrp, _, variant) = bech32::decode(address).map_err(|err| anyhow::anyhow!("{} is not a valid address: {}", address, err))?;
+    if variant != bech32::Variant::Bech32 || hrp != prefix {
+        return Err(anyhow::anyhow!("{} is not a {}1.. address", address, prefix));
+    }
+    Ok(())
+}
+
+fn vote_option(option: &str) -> Option<&'static str> {
+    match option {
+        "yes" => Some("VOTE_OPTION_YES"),
+        "abstain" => Some("VOTE_OPTION_ABSTAIN"),
+        "no" => Some("VOTE_OPTION_NO"),
+        "no with veto" => Some("VOTE_OPTION_NO_WITH_VETO"),
+        _ => None,
+    }
+}
+
+fn registered_address_key(user_hash: u64, blockchain: &str) -> String {
+    format!("{}_{}", user_hash, blockchain)
+}
+
+/// The address the user registered with `my address` for the blockchain.
+pub fn registered_address(db: &sled::Db, user_hash: u64, blockchain: &str) -> anyhow::Result<Option<String>> {
+    match db.open_tree(REGISTERED_ADDRESSES_TREE)?.get(registered_address_key(user_hash, blockchain))? {
+        Some(address) => Ok(Some(String::from_utf8(address.to_vec())?)),
+        None => Ok(None),
+    }
+}
+
+pub fn unsigned_vote_tx(proposal_id: u64, voter: &str, option: &str) -> serde_json::Value {
+    serde_json::json!({
+        "body": {
+            "messages": [{
+                "@type": "/cosmos.gov.v1beta1.MsgVote",
+                "proposal_id": proposal_id.to_string(),
+                "voter": voter,
+                "option": option
+            }],
+            "memo": "",
+            "timeout_height": "0",
+            "extension_options": [],
+            "non_critical_extension_options": []
+        },
+        "auth_info": {
+            "signer_infos": [],
+            "fee": {
+                "amount": [],
+                "gas_limit": VOTE_GAS_LIMIT.to_string(),
+                "payer": "",
+                "granter": ""
+            }
+        },
+        "signatures": []
+    })
+}
+
+/// Stores the address and returns the reply.
+fn register_address(db: &sled::Db, user_hash: u64, blockchain: &str, address: &str, prefix: Option<&str>) -> anyhow::Result<String> {
+    if let Err(err) = check_address(address, prefix) {
+        return Ok(format!("Not registered: {}.", err));
+    }
+    db.open_tree(REGISTERED_ADDRESSES_TREE)?
+        .insert(registered_address_key(user_hash, blockchain), address.as_bytes())?;
+    Ok(format!("Registered {} for {}.\nUse /vote_{}_id<ProposalId>_<yes|no|abstain|no_with_veto> to get an unsigned vote.", address, blockchain, blockchain))
+}
+
+/// The reply to a vote command, the unsigned transaction for the given or the registered address.
+fn vote_reply(db: &sled::Db, user_hash: u64, blockchain: &str, proposal_id: u64, option: &str, address: Option<&str>, prefix: Option<&str>) -> anyhow::Result<Vec<String>> {
+    let voter = match address {
+        Some(address) => {
+            if let Err(err) = check_address(address, prefix) {
+                return Ok(vec![format!("Can not vote: {}.", err)]);
+            }
+            address.to_string()
+        }
+        None => match registered_address(db, user_hash, blockchain)? {
+            Some(address) => address,
+            None => {
+                return Ok(vec![format!("No address registered for {}, use /my_address_{}_<address> first.", blockchain, blockchain)]);
+            }
+        },
+    };
+    Ok(vec![
+        format!(
+            "🗳 Unsigned vote {} on {} #{} for {}\nSave it as vote.json and sign it with your own wallet, e.g. `<daemon> tx sign vote.json --from <key>` (set the fee in auth_info first).",
+            option, blockchain, proposal_id, voter
+        ),
+        serde_json::to_string_pretty(&unsigned_vote_tx(proposal_id, &voter, option))?,
+    ])
+}
+
+pub fn handle_my_address(user_hash: u64, msg: &str, db: &sled::Db) -> anyhow::Result<()> {
+    if MY_ADDRESS_REGEX.is_match(&msg) {
+        let caps = MY_ADDRESS_REGEX.captures(&msg).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let blockchain = caps.get(1).map(|t| t.as_str().trim()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let address = caps.get(2).map(|t| t.as_str()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+
+        let reply = register_address(db, user_hash, blockchain, address, BECH32_PREFIXES.get(blockchain).map(|x| x.as_str()))?;
+        notify_sled_db(
+            db,
+            CosmosRustServerValue::Notify(Notify {
+                timestamp: Utc::now().timestamp(),
+                msg: vec![reply],
+                buttons: vec![],
+                user_hash,
+            }),
+        );
+        return Ok(());
+    }
+    Err(anyhow::anyhow!("Error: Unknown Command!"))
+}
+
+/// Builds an unsigned MsgVote for the registered (or given) address.
+pub fn handle_vote(user_hash: u64, msg: &str, db: &sled::Db) -> anyhow::Result<()> {
+    if VOTE_REGEX.is_match(&msg) {
+        let caps = VOTE_REGEX.captures(&msg).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let blockchain = caps.get(1).map(|t| t.as_str().trim()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let proposal_id = caps.get(2).map(|t| t.as_str()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?.parse::<u64>()?;
+        let option = caps.get(3).and_then(|t| vote_option(t.as_str().trim())).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+
+        let reply = vote_reply(
+            db,
+            user_hash,
+            blockchain,
+            proposal_id,
+            option,
+            caps.get(4).map(|t| t.as_str()),
+            BECH32_PREFIXES.get(blockchain).map(|x| x.as_str()),
+        )?;
+        notify_sled_db(
+            db,
+            CosmosRustServerValue::Notify(Notify {
+                timestamp: Utc::now().timestamp(),
+                msg: reply,
+                buttons: vec![],
+                user_hash,
+            }),
+        );
+        return Ok(());
+    }
+    Err(anyhow::anyhow!("Error: Unknown Command!"))
+}
+
+/// Records the veto of the user, or the reason it is not recorded.
+fn record_veto(veto_path: &str, settings: &UserSettings, user_hash: u64, blockchain: &str, proposal_id: u64) -> anyhow::Result<String> {
+    if !settings.may_veto(blockchain, user_hash) {
+        return Ok(format!(
+            "Not authorized to veto, add your user hash ({}) to veto_users of the {} vote mirror.",
+            user_hash, blockchain
+        ));
+    }
+    fs::create_dir_all(veto_path)?;
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(format!("{}{}_{}", veto_path, blockchain, proposal_id))?;
+    file.write_all(format!("{}\n", user_hash).as_bytes())?;
+    Ok(format!("🛑 Veto recorded for {} #{}, the mirrored vote will not be cast.", blockchain, proposal_id))
+}
+
+/// Stops the vote mirror from casting the pending vote on this proposal, only users in veto_users may veto.
+pub fn handle_veto_vote(user_hash: u64, msg: &str, db: &sled::Db) -> anyhow::Result<()> {
+    if VETO_VOTE_REGEX.is_match(&msg) {
+        let caps = VETO_VOTE_REGEX.captures(&msg).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let blockchain = caps.get(1).map(|t| t.as_str().trim()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?;
+        let proposal_id = caps.get(2).map(|t| t.as_str()).ok_or(anyhow::anyhow!("Error: Parse Error!"))?.parse::<u64>()?;
+
+        let reply = match load_user_settings(&CONFIG.settings_path()) {
+            Ok(settings) => record_veto(&VOTE_MIRROR_VETO_PATH, &settings, user_hash, blockchain, proposal_id)?,
+            Err(err) => format!("Veto not recorded, the settings can not be read: {}", err),
+        };
+        notify_sled_db(
+            db,
+            CosmosRustServerValue::Notify(Notify {
+                timestamp: Utc::now().timestamp(),
+                msg: vec![reply],
+                buttons: vec![],
+                user_hash,
+            }),
+        );
+        return Ok(());
+    }
+    Err(anyhow::anyhow!("Error: Unknown Command!"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OSMO_ADDRESS: &str = "osmo1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5helwsw";
+    const COSMOS_ADDRESS: &str = "cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu";
+
+    fn blockchains() -> Vec<String> {
+        ["osmosis", "cosmoshub"].iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn vote_commands_capture_blockchain_id_option_and_address() {
+        let msg = format!("my address osmosis {}", OSMO_ADDRESS);
+        let caps = my_address_regex(&blockchains()).captures(&msg).unwrap();
+        assert_eq!((caps[1].trim(), &caps[2]), ("osmosis", OSMO_ADDRESS));
+
+        let regex = vote_regex(&blockchains());
+        let caps = regex.captures("vote cosmoshub id12 no with veto").unwrap();
+        assert_eq!((caps[1].trim(), &caps[2], caps[3].trim(), caps.get(4)), ("cosmoshub", "12", "no with veto", None));
+        let msg = format!("vote osmosis id3 yes {}", OSMO_ADDRESS);
+        let caps = regex.captures(&msg).unwrap();
+        assert_eq!(&caps[4], OSMO_ADDRESS);
+        assert!(regex.captures("vote juno id3 yes").is_none());
+    }
+
+    #[test]
+    fn only_valid_addresses_of_the_blockchain_are_registered() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        assert!(register_address(&db, 1, "osmosis", COSMOS_ADDRESS, Some("osmo")).unwrap().starts_with("Not registered"));
+        // last character changed, the checksum fails.
+        assert!(register_address(&db, 1, "osmosis", "osmo1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5helwsq", Some("osmo")).unwrap().starts_with("Not registered"));
+        assert!(register_address(&db, 1, "osmosis", OSMO_ADDRESS, None).unwrap().starts_with("Not registered"));
+        assert_eq!(registered_address(&db, 1, "osmosis").unwrap(), None);
+
+        assert!(register_address(&db, 1, "osmosis", OSMO_ADDRESS, Some("osmo")).unwrap().starts_with("Registered"));
+        assert_eq!(registered_address(&db, 1, "osmosis").unwrap(), Some(OSMO_ADDRESS.to_string()));
+        assert_eq!(registered_address(&db, 2, "osmosis").unwrap(), None);
+    }
+
+    #[test]
+    fn votes_are_built_for_the_given_or_registered_address() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let reply = vote_reply(&db, 1, "osmosis", 3, "VOTE_OPTION_YES", None, Some("osmo")).unwrap();
+        assert_eq!(reply, vec!["No address registered for osmosis, use /my_address_osmosis_<address> first.".to_string()]);
+
+        let reply = vote_reply(&db, 1, "osmosis", 3, "VOTE_OPTION_YES", Some(COSMOS_ADDRESS), Some("osmo")).unwrap();
+        assert_eq!(reply.len(), 1);
+        assert!(reply[0].starts_with("Can not vote"));
+
+        register_address(&db, 1, "osmosis", OSMO_ADDRESS, Some("osmo")).unwrap();
+        let reply = vote_reply(&db, 1, "osmosis", 3, "VOTE_OPTION_YES", None, Some("osmo")).unwrap();
+        assert_eq!(reply.len(), 2);
+        let tx: serde_json::Value = serde_json::from_str(&reply[1]).unwrap();
+        assert_eq!(tx, unsigned_vote_tx(3, OSMO_ADDRESS, "VOTE_OPTION_YES"));
+        assert_eq!(tx["body"]["messages"][0]["voter"], OSMO_ADDRESS);
+        assert_eq!(tx["body"]["messages"][0]["proposal_id"], "3");
+    }
+
+    #[test]
+    fn only_veto_users_can_veto() {
+        let dir = tempfile::tempdir().unwrap();
+        let veto_path = format!("{}/", dir.path().to_str().unwrap());
+        let mut settings = UserSettings::default();
+        settings.vote_mirror.push(VoteMirrorSettings {
+            blockchain: "osmosis".to_string(),
+            validator: "osmovaloper1".to_string(),
+            delay: 3600,
+            excluded_proposal_types: Vec::new(),
+            veto_users: vec![1, 2],
+            fee_denom: "uosmo".to_string(),
+            fee: 5000,
+            gas_limit: 200000,
+        });
+
+        assert!(record_veto(&veto_path, &settings, 3, "osmosis", 7).unwrap().starts_with("Not authorized"));
+        assert!(record_veto(&veto_path, &settings, 1, "cosmoshub", 7).unwrap().starts_with("Not authorized"));
+        assert!(!dir.path().join("osmosis_7").exists());
+
+        assert!(record_veto(&veto_path, &settings, 1, "osmosis", 7).unwrap().starts_with("🛑 Veto recorded"));
+        assert!(record_veto(&veto_path, &settings, 2, "osmosis", 7).unwrap().starts_with("🛑 Veto recorded"));
+        assert_eq!(fs::read_to_string(dir.path().join("osmosis_7")).unwrap(), "1\n2\n");
+
+        settings.vote_mirror[0].veto_users.clear();
+        assert!(record_veto(&veto_path, &settings, 1, "osmosis", 8).unwrap().starts_with("Not authorized"));
+    }
+}
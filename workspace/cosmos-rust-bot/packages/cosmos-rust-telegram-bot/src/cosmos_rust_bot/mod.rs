@@ -5,7 +5,23 @@ mod commands;
 
 use static_commands::*;
 use commands::*;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
+/// Numbers the user commands, the queries a command sends are logged within its span.
+static COMMAND_ID: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    /// user_hash -> id of the command that has not been answered yet.
+    static ref UNANSWERED_COMMANDS: Mutex<HashMap<u64, u64>> = Mutex::new(HashMap::new());
+}
+
+/// The id of the user's command the next notification answers, it is logged with the delivery.
+pub fn take_unanswered_command(user_hash: u64) -> Option<u64> {
+    UNANSWERED_COMMANDS.lock().unwrap().remove(&user_hash)
+}
 
 pub async fn handle_message(user_id: u64, message: String, db: &sled::Db) {
 
@@ -21,6 +37,12 @@ pub async fn handle_message(user_id: u64, message: String, db: &sled::Db) {
 
     let user_hash = UserMetaData::user_hash(user_id);
 
+    let command_id = COMMAND_ID.fetch_add(1, Ordering::SeqCst);
+    UNANSWERED_COMMANDS.lock().unwrap().insert(user_hash, command_id);
+    let span = tracing::info_span!("command", command_id, user_hash, command = %msg);
+    let _enter = span.enter();
+    tracing::info!("command received");
+
     handle_start(user_hash,&msg,db)
         .or_else(|_|handle_about(user_hash,&msg,db))
         .or_else(|_|handle_help_tasks(user_hash,&msg,db))
@@ -35,6 +57,20 @@ pub async fn handle_message(user_id: u64, message: String, db: &sled::Db) {
         .or_else(|_|handle_tasks_count_list_history(user_hash,&msg, &msg_for_query,db))
         .or_else(|_|handle_subscribe_unsubscribe(user_hash,&msg, &msg_for_query,db))
         .or_else(|_|handle_gov_prpsl(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_my_vote(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_my_address(user_hash,&msg,db))
+        .or_else(|_|handle_vote(user_hash,&msg,db))
+        .or_else(|_|handle_param_changes(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_params_history(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_community_pool(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_validator_scorecard(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_authz_grants(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_audit_journal(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_staking_reminders(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_balance_alerts(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_vote_mirror(user_hash,&msg, &msg_for_query,db))
+        .or_else(|_|handle_veto_vote(user_hash,&msg,db))
+        .or_else(|_|handle_settings(user_hash,&msg,db))
         .or_else(|_|handle_register(user_hash,&msg,db))
         .or_else(|_|handle_verify(user_hash,&msg,db))
         .or_else(|_|handle_unknown_command(user_hash,db)).ok();
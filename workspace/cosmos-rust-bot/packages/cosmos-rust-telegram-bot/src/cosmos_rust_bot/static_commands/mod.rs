@@ -1,5 +1,6 @@
 use chrono::Utc;
 use lazy_static::lazy_static;
+use bot_library::shared::config::CONFIG;
 use cosmos_rust_interface::utils::entry::{
     db::{notification::notify_sled_db},
     CosmosRustServerValue, Notify,
@@ -8,7 +9,7 @@ use std::collections::HashMap;
 
 lazy_static! {
     static ref SUPPORTED_BLOCKCHAINS: Vec<(String, serde_json::Value)> = {
-        let data = std::fs::read_to_string("./tmp/supported_blockchains.json").expect("Unable to read file");
+        let data = std::fs::read_to_string(CONFIG.data_path("supported_blockchains.json")).expect("Unable to read file");
         let mut blockchains: HashMap<String, serde_json::Value> = serde_json::from_str(&data).expect("Unable to parse JSON");
         let mut blockchains: Vec<(String, serde_json::Value)> = blockchains.into_iter().collect();
         blockchains.sort_by_key(|(_,b)| b.get("rank").unwrap().as_u64().unwrap());
@@ -191,6 +192,27 @@ EXAMPLES
 
     To look up proposals of type "parameter change" that are in voting period on the cosmos_hub network:
         /gov_prpsl_cosmos_hub_voting_period_parameter_change
+
+    To see how the top validators voted on proposal "1" on the osmosis network:
+        /gov_prpsl_votes_osmosis_id1
+
+    To get an unsigned vote transaction for your own wallet (register your address once):
+        /my_address_osmosis_<your address>
+        /vote_osmosis_id1_yes
+
+    To get notified when a validator you delegate to (with your registered address) votes against your preference:
+        /my_vote_osmosis_id1_yes
+
+    To follow the votes mirrored from your validator and veto one before it is cast:
+        /vote_mirror_osmosis_pending_subscribe
+        /veto_vote_osmosis_id1
+
+    To get reminded before unbondings of your watched address mature or a redelegation lock expires:
+        /staking_reminders_osmosis_reminder_subscribe
+        /staking_reminders_osmosis_completed_subscribe
+
+    To get notified about incoming transfers to your watched address:
+        /balance_alerts_osmosis_incoming_transfer_subscribe
 "#.to_string(),
                 ],
                 buttons: vec![],
@@ -1,6 +1,9 @@
 // RUSTFLAGS="--cfg tokio_unstable" cargo build
 
-use cosmos_rust_telegram_bot::cosmos_rust_bot::handle_message;
+use bot_library::shared::config::{init_config, CONFIG};
+use bot_library::shared::metrics::{spawn_metrics_server, METRICS};
+use bot_library::shared::logging::init_logging;
+use cosmos_rust_telegram_bot::cosmos_rust_bot::{handle_message, take_unanswered_command};
 use teloxide::{prelude::*, types::MessageKind};
 use std::error::Error;
 use chrono::Utc;
@@ -15,24 +18,43 @@ use teloxide::types::ReplyMarkup::InlineKeyboard;
 use tokio::task::JoinSet;
 
 
-const NOTIFICATION_SOCKET: &str = "./tmp/cosmos_rust_bot_notification_socket";
-const TG_SLED_DB: &str = "./tmp/cosmos_rust_telegram_bot_sled_db";
+/// Notify entries that are not sent yet, they are removed once delivered.
+fn record_notification_queue_depth(tree: &sled::Db) {
+    METRICS.set_gauge("crb_notification_queue_depth", "Notifications waiting to be sent.", &[], tree.scan_prefix(Notify::get_prefix()).count() as f64);
+}
 
 // RUST_LOG=error,debug,info
 #[tokio::main]
 async fn main() {
 
+    if let Err(err) = init_config() {
+        eprintln!("{}", err);
+        std::process::exit(2);
+    }
+    if let Err(err) = init_logging(&CONFIG.log_format) {
+        eprintln!("{}", err);
+        return;
+    }
+
     let mut join_set: JoinSet<()> = JoinSet::new();
 
-    let tree = Arc::new(load_sled_db(TG_SLED_DB));
+    let tree = Arc::new(load_sled_db(&CONFIG.data_path("cosmos_rust_telegram_bot_sled_db")));
 
     import_user_meta_data(&tree,CRB_USER_META_DATA_STORE_JSON);
 
-    spawn_socket_notification_server(NOTIFICATION_SOCKET,tree.clone().as_ref());
+    spawn_socket_notification_server(&CONFIG.notification_socket(),tree.clone().as_ref());
 
-    pretty_env_logger::init();
     log::info!("Starting shared state bot...");
 
+    if let Some(address) = &CONFIG.telegram_metrics_listen {
+        record_notification_queue_depth(&tree);
+        // ready once the notification socket accepts notifications.
+        METRICS.set_ready(true);
+        if let Err(err) = spawn_metrics_server(address, &METRICS) {
+            log::error!("metrics: failed to listen on {}: {:?}", address, err);
+        }
+    }
+
     let bot = Bot::from_env();
 
     // task that sends the messages.
@@ -46,71 +68,12 @@ async fn main() {
                     if let CosmosRustServerValue::Notify(notify) =
                         CosmosRustServerValue::try_from(value.to_vec()).unwrap()
                     {
-                        log::info!("Status: Insert Notify Event - {}", Utc::now());
-                        match tree_2.get(notify.user_hash.to_ne_bytes().to_vec()) {
-                            Ok(Some(value)) => {
-                                let chat_id: Option<i64> =
-                                    match CosmosRustServerValue::try_from(value.to_vec()).unwrap() {
-                                        CosmosRustServerValue::UserMetaData(user_meta_data) => {
-                                            Some(user_meta_data.user_chat_id)
-                                        }
-                                        _ => None,
-                                    };
-                                if let Some(id) = chat_id {
-                                    log::info!(
-                                        "Status: ChatID Available - {} - {}",
-                                        Utc::now(),
-                                        id
-                                    );
-                                    for i in 0..notify.msg.len() {
-
-                                        let mut batch: Vec<&str> = Vec::new();
-
-                                        let mut offset = 0;
-                                        let chunk_size = 4000;
-                                        while offset < notify.msg[i].len() {
-                                            let chunk = &notify.msg[i][offset..std::cmp::min(offset + chunk_size, notify.msg[i].len())];
-                                            batch.push(chunk);
-                                            offset += chunk_size;
-                                        }
-
-                                        let batch_pop = batch.pop();
-
-                                        for b in batch {
-                                            bot_clone.send_message(ChatId(id), b)
-                                                .disable_web_page_preview(true)
-                                                .send().await.ok();
-                                        }
-                                        if let Some(last) = batch_pop {
-                                            // Create the inline keyboard with the desired buttons
-                                            let mut buttons: Vec<Vec<InlineKeyboardButton>> = Vec::new();
-                                            if i < notify.buttons.len() {
-                                                for row in &notify.buttons[i] {
-                                                    buttons.push(row.iter().map(|b| {
-                                                        if b.1.starts_with("https://") {
-                                                            InlineKeyboardButton::new(b.0.to_owned(), InlineKeyboardButtonKind::Url(b.1.to_owned().parse().unwrap()))
-                                                        } else {
-                                                            InlineKeyboardButton::new(b.0.to_owned(), InlineKeyboardButtonKind::CallbackData(b.1.to_owned()))
-                                                        }
-                                                    }).collect());
-                                                }
-                                            }
-                                            let keyboard = InlineKeyboardMarkup::new(buttons);
-
-                                            bot_clone.send_message(ChatId(id), last)
-                                                .disable_web_page_preview(true)
-                                                .reply_markup(keyboard)
-                                                .send().await.ok();
-                                        }
-
-                                    }
-                                    // TODO: remove user if not subscribed and has no pending notifications/notify
-                                }
-                            }
-                            _ => {}
-                        }
+                        let command_id = take_unanswered_command(notify.user_hash);
+                        deliver_notification(&bot_clone, &tree_2, &notify, command_id).await;
                         tree_2.remove(key).ok();
+                        METRICS.inc_counter("crb_notifications_delivered_total", "Notifications taken from the queue and sent.", &[], 1.0);
                     }
+                    record_notification_queue_depth(&tree_2);
                 }
                 sled::Event::Remove { .. } => {}
             }
@@ -146,6 +109,74 @@ async fn main() {
     join_set.shutdown().await;
 }
 
+/// Sends the messages of a Notify entry to the chat of the user.
+/// `command_id` is the command it answers, None for notifications of subscriptions.
+#[tracing::instrument(name = "notification", skip_all, fields(user_hash = notify.user_hash, command_id, messages = notify.msg.len()))]
+async fn deliver_notification(bot: &Bot, tree: &sled::Db, notify: &Notify, command_id: Option<u64>) {
+    let chat_id: Option<i64> = match tree.get(notify.user_hash.to_ne_bytes().to_vec()) {
+        Ok(Some(value)) => match CosmosRustServerValue::try_from(value.to_vec()).unwrap() {
+            CosmosRustServerValue::UserMetaData(user_meta_data) => Some(user_meta_data.user_chat_id),
+            _ => None,
+        },
+        _ => None,
+    };
+    let id = match chat_id {
+        Some(id) => id,
+        None => {
+            tracing::warn!("no chat id for the user, notification dropped");
+            return;
+        }
+    };
+    tracing::info!(chat_id = id, "delivering notification");
+    for i in 0..notify.msg.len() {
+
+        let mut batch: Vec<&str> = Vec::new();
+
+        let mut offset = 0;
+        let chunk_size = 4000;
+        while offset < notify.msg[i].len() {
+            let chunk = &notify.msg[i][offset..std::cmp::min(offset + chunk_size, notify.msg[i].len())];
+            batch.push(chunk);
+            offset += chunk_size;
+        }
+
+        let batch_pop = batch.pop();
+
+        for b in batch {
+            if let Err(err) = bot.send_message(ChatId(id), b)
+                .disable_web_page_preview(true)
+                .send().await {
+                tracing::error!(error = %err, "sending failed");
+            }
+        }
+        if let Some(last) = batch_pop {
+            // Create the inline keyboard with the desired buttons
+            let mut buttons: Vec<Vec<InlineKeyboardButton>> = Vec::new();
+            if i < notify.buttons.len() {
+                for row in &notify.buttons[i] {
+                    buttons.push(row.iter().map(|b| {
+                        if b.1.starts_with("https://") {
+                            InlineKeyboardButton::new(b.0.to_owned(), InlineKeyboardButtonKind::Url(b.1.to_owned().parse().unwrap()))
+                        } else {
+                            InlineKeyboardButton::new(b.0.to_owned(), InlineKeyboardButtonKind::CallbackData(b.1.to_owned()))
+                        }
+                    }).collect());
+                }
+            }
+            let keyboard = InlineKeyboardMarkup::new(buttons);
+
+            if let Err(err) = bot.send_message(ChatId(id), last)
+                .disable_web_page_preview(true)
+                .reply_markup(keyboard)
+                .send().await {
+                tracing::error!(error = %err, "sending failed");
+            }
+        }
+
+    }
+    // TODO: remove user if not subscribed and has no pending notifications/notify
+}
+
 async fn receive(
     tree: Arc<sled::Db>,
     message: Message,
@@ -0,0 +1,219 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use secstr::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+// The mnemonic is encrypted with XChaCha20-Poly1305 under a key derived from the passphrase with Argon2id.
+// The version field allows to change the cipher or the kdf later on without breaking existing keystores.
+
+pub const KEYSTORE_VERSION: u32 = 1;
+const KDF: &str = "argon2id";
+const CIPHER: &str = "xchacha20poly1305";
+
+/// Bounds of the kdf params a keystore may ask for: below they are too weak to protect the mnemonic,
+/// above a crafted keystore could make the unlock take all memory or run forever.
+const M_COST_RANGE: (u32, u32) = (8 * 1024, 4 * 1024 * 1024);
+const T_COST_RANGE: (u32, u32) = (1, 64);
+const P_COST_RANGE: (u32, u32) = (1, 16);
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KdfParams {
+    /// memory cost in KiB
+    pub m_cost: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl KdfParams {
+    pub fn check(&self) -> anyhow::Result<()> {
+        for (name, value, (min, max)) in [
+            ("m_cost", self.m_cost, M_COST_RANGE),
+            ("t_cost", self.t_cost, T_COST_RANGE),
+            ("p_cost", self.p_cost, P_COST_RANGE),
+        ] {
+            if value < min || value > max {
+                return Err(anyhow::anyhow!("Error: Keystore kdf param {} = {} is outside of {}..={}", name, value, min, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> KdfParams {
+        KdfParams {
+            m_cost: 64 * 1024,
+            t_cost: 3,
+            p_cost: 1,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Keystore {
+    pub version: u32,
+    pub kdf: String,
+    pub kdf_params: KdfParams,
+    pub cipher: String,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn derive_key(passphrase: &SecUtf8, salt: &[u8], params: &KdfParams) -> anyhow::Result<Zeroizing<[u8; KEY_LEN]>> {
+    let argon2 = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(params.m_cost, params.t_cost, params.p_cost, Some(KEY_LEN))
+            .map_err(|e| anyhow::anyhow!("Error: Invalid kdf params: {}", e))?,
+    );
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    argon2
+        .hash_password_into(passphrase.unsecure().as_bytes(), salt, key.as_mut())
+        .map_err(|e| anyhow::anyhow!("Error: Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+impl Keystore {
+    pub fn encrypt(mnemonic: &SecUtf8, passphrase: &SecUtf8) -> anyhow::Result<Keystore> {
+        Keystore::encrypt_with_params(mnemonic, passphrase, KdfParams::default())
+    }
+
+    pub fn encrypt_with_params(mnemonic: &SecUtf8, passphrase: &SecUtf8, kdf_params: KdfParams) -> anyhow::Result<Keystore> {
+        kdf_params.check()?;
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let key = derive_key(passphrase, &salt, &kdf_params)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), mnemonic.unsecure().as_bytes())
+            .map_err(|_| anyhow::anyhow!("Error: Encryption failed"))?;
+
+        Ok(Keystore {
+            version: KEYSTORE_VERSION,
+            kdf: KDF.to_string(),
+            kdf_params,
+            cipher: CIPHER.to_string(),
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// Fails if the passphrase is wrong or the keystore was tampered with.
+    pub fn decrypt(&self, passphrase: &SecUtf8) -> anyhow::Result<SecUtf8> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(anyhow::anyhow!("Error: Unsupported keystore version: {}", self.version));
+        }
+        if self.kdf != KDF || self.cipher != CIPHER {
+            return Err(anyhow::anyhow!("Error: Unsupported keystore kdf {} or cipher {}, expected {} and {}", self.kdf, self.cipher, KDF, CIPHER));
+        }
+        self.kdf_params.check()?;
+        let key = derive_key(passphrase, &hex::decode(&self.salt)?, &self.kdf_params)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let nonce = hex::decode(&self.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(anyhow::anyhow!("Error: Invalid keystore nonce"));
+        }
+        let plaintext = Zeroizing::new(
+            cipher
+                .decrypt(XNonce::from_slice(&nonce), hex::decode(&self.ciphertext)?.as_slice())
+                .map_err(|_| anyhow::anyhow!("Error: Wrong passphrase or corrupted keystore"))?,
+        );
+        Ok(SecUtf8::from(std::str::from_utf8(&plaintext)?))
+    }
+
+    pub fn change_passphrase(&self, old_passphrase: &SecUtf8, new_passphrase: &SecUtf8) -> anyhow::Result<Keystore> {
+        let mnemonic = self.decrypt(old_passphrase)?;
+        Keystore::encrypt_with_params(&mnemonic, new_passphrase, self.kdf_params.clone())
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Keystore> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn exists(path: &str) -> bool {
+        Path::new(path).exists()
+    }
+
+    /// Writes to a temporary file that is only readable by the owner, then renames it.
+    pub fn save(&self, path: &str) -> anyhow::Result<()> {
+        let tmp_path = format!("{}.tmp", path);
+        {
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let mut file = options.open(&tmp_path)?;
+            file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_params() -> KdfParams {
+        KdfParams {
+            m_cost: M_COST_RANGE.0,
+            t_cost: 1,
+            p_cost: 1,
+        }
+    }
+
+    #[test]
+    fn encrypt_decrypt_roundtrip() -> anyhow::Result<()> {
+        let mnemonic = SecUtf8::from("test test test test test test test test test test test junk");
+        let keystore = Keystore::encrypt_with_params(&mnemonic, &SecUtf8::from("passphrase"), test_params())?;
+        assert_eq!(keystore.decrypt(&SecUtf8::from("passphrase"))?.unsecure(), mnemonic.unsecure());
+        assert!(keystore.decrypt(&SecUtf8::from("wrong")).is_err());
+
+        let changed = keystore.change_passphrase(&SecUtf8::from("passphrase"), &SecUtf8::from("new"))?;
+        assert!(changed.decrypt(&SecUtf8::from("passphrase")).is_err());
+        assert_eq!(changed.decrypt(&SecUtf8::from("new"))?.unsecure(), mnemonic.unsecure());
+        Ok(())
+    }
+
+    #[test]
+    fn decrypt_rejects_unexpected_kdf_cipher_and_params() -> anyhow::Result<()> {
+        let passphrase = SecUtf8::from("passphrase");
+        let keystore = Keystore::encrypt_with_params(&SecUtf8::from("test test test test test test test test test test test junk"), &passphrase, test_params())?;
+
+        let mut other = keystore.clone();
+        other.kdf = "scrypt".to_string();
+        assert!(other.decrypt(&passphrase).is_err());
+
+        let mut other = keystore.clone();
+        other.cipher = "aes-128-ctr".to_string();
+        assert!(other.decrypt(&passphrase).is_err());
+
+        let mut other = keystore.clone();
+        other.kdf_params.m_cost = 16;
+        assert!(other.decrypt(&passphrase).is_err());
+
+        let mut other = keystore;
+        other.kdf_params.t_cost = 1_000_000;
+        assert!(other.decrypt(&passphrase).is_err());
+        Ok(())
+    }
+}
@@ -1 +1,3 @@
-pub mod wallet;
\ No newline at end of file
+pub mod keystore;
+pub mod signer;
+pub mod wallet;
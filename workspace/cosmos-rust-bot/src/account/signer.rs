@@ -0,0 +1,344 @@
+use cosmrs::bip32::{DerivationPath, Language, Mnemonic};
+use cosmrs::crypto::secp256k1::SigningKey;
+use cosmrs::crypto::PublicKey;
+use cosmrs::proto::cosmos::tx::v1beta1::TxRaw;
+use cosmrs::tx::{Raw, SignDoc};
+use cosmos_sdk_proto::cosmos::tx::v1beta1::{SignDoc as ProtoSignDoc, TxBody};
+use prost::Message;
+use secstr::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+
+use super::keystore::Keystore;
+use crate::blockchain::tx::unwrap_messages;
+
+// Task code only ever sees a Signer, the key material stays inside the implementation
+// (or inside a separate process in case of the RemoteSigner).
+// One mnemonic covers all chains, the key is selected by the slip44 coin type of the chain.
+
+pub const COSMOS_COIN_TYPE: u32 = 118;
+pub const TERRA_COIN_TYPE: u32 = 330;
+/// Ethermint chains (e.g. evmos) derive eth_secp256k1 keys with keccak addresses, not supported by the signers.
+pub const ETHERMINT_COIN_TYPE: u32 = 60;
+
+pub fn derivation_path(coin_type: u32) -> String {
+    format!("m/44'/{}'/0'/0/0", coin_type)
+}
+
+pub trait Signer: Send + Sync {
+    fn public_key(&self, coin_type: u32) -> anyhow::Result<PublicKey>;
+
+    /// Returns the signature over the serialized SignDoc.
+    fn sign_bytes(&self, coin_type: u32, sign_doc_bytes: &[u8]) -> anyhow::Result<Vec<u8>>;
+
+    fn account_address(&self, coin_type: u32, prefix: &str) -> anyhow::Result<String> {
+        Ok(self
+            .public_key(coin_type)?
+            .account_id(prefix)
+            .map_err(|e| anyhow::anyhow!("Error: {}", e))?
+            .to_string())
+    }
+
+    fn sign(&self, coin_type: u32, sign_doc: SignDoc) -> anyhow::Result<Raw> {
+        let body_bytes = sign_doc.body_bytes.clone();
+        let auth_info_bytes = sign_doc.auth_info_bytes.clone();
+        let sign_doc_bytes = sign_doc.into_bytes().map_err(|e| anyhow::anyhow!("Error: {}", e))?;
+        let signature = self.sign_bytes(coin_type, &sign_doc_bytes)?;
+        Ok(Raw::from(TxRaw {
+            body_bytes,
+            auth_info_bytes,
+            signatures: vec![signature],
+        }))
+    }
+}
+
+/// Holds the signing keys derived for the given coin types in memory, the mnemonic itself is not kept.
+pub struct MnemonicSigner {
+    signing_keys: BTreeMap<u32, SigningKey>,
+}
+
+impl MnemonicSigner {
+    pub fn new(mnemonic: &SecUtf8, coin_types: &[u32]) -> anyhow::Result<MnemonicSigner> {
+        let mnemonic = Mnemonic::new(mnemonic.unsecure(), Language::English).map_err(|e| anyhow::anyhow!("Error: Invalid mnemonic: {}", e))?;
+        let seed = mnemonic.to_seed("");
+        let mut signing_keys: BTreeMap<u32, SigningKey> = BTreeMap::new();
+        for coin_type in coin_types {
+            let path: DerivationPath = derivation_path(*coin_type).parse().map_err(|e| anyhow::anyhow!("Error: Invalid derivation path: {}", e))?;
+            let signing_key = SigningKey::derive_from_path(&seed, &path).map_err(|e| anyhow::anyhow!("Error: {}", e))?;
+            signing_keys.insert(*coin_type, signing_key);
+        }
+        Ok(MnemonicSigner { signing_keys })
+    }
+
+    fn signing_key(&self, coin_type: u32) -> anyhow::Result<&SigningKey> {
+        self.signing_keys
+            .get(&coin_type)
+            .ok_or(anyhow::anyhow!("Error: No key derived for coin type {}", coin_type))
+    }
+}
+
+impl Signer for MnemonicSigner {
+    fn public_key(&self, coin_type: u32) -> anyhow::Result<PublicKey> {
+        Ok(self.signing_key(coin_type)?.public_key())
+    }
+
+    fn sign_bytes(&self, coin_type: u32, sign_doc_bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let signature = self.signing_key(coin_type)?.sign(sign_doc_bytes).map_err(|e| anyhow::anyhow!("Error: {}", e))?;
+        Ok(signature.to_bytes().to_vec())
+    }
+}
+
+/// Signs with the key stored in an encrypted keystore file, it is unlocked once on creation.
+pub struct KeystoreSigner {
+    inner: MnemonicSigner,
+}
+
+impl KeystoreSigner {
+    pub fn unlock(path: &str, passphrase: &SecUtf8, coin_types: &[u32]) -> anyhow::Result<KeystoreSigner> {
+        let mnemonic = Keystore::load(path)?.decrypt(passphrase)?;
+        Ok(KeystoreSigner {
+            inner: MnemonicSigner::new(&mnemonic, coin_types)?,
+        })
+    }
+}
+
+impl Signer for KeystoreSigner {
+    fn public_key(&self, coin_type: u32) -> anyhow::Result<PublicKey> {
+        self.inner.public_key(coin_type)
+    }
+
+    fn sign_bytes(&self, coin_type: u32, sign_doc_bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.inner.sign_bytes(coin_type, sign_doc_bytes)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum RemoteSignerRequest {
+    /// coin type
+    PublicKey(u32),
+    /// coin type, hex encoded SignDoc bytes
+    Sign(u32, String),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum RemoteSignerResponse {
+    /// PublicKey as json
+    PublicKey(String),
+    /// hex encoded signature
+    Signature(String),
+    Error(String),
+}
+
+/// Signs via a separate process listening on a Unix socket (see serve_remote_signer).
+/// One request per connection, json encoded and terminated by a newline.
+pub struct RemoteSigner {
+    socket_path: String,
+}
+
+impl RemoteSigner {
+    pub fn new(socket_path: &str) -> RemoteSigner {
+        RemoteSigner {
+            socket_path: socket_path.to_string(),
+        }
+    }
+
+    fn request(&self, request: &RemoteSignerRequest) -> anyhow::Result<RemoteSignerResponse> {
+        let mut stream = UnixStream::connect(&self.socket_path)?;
+        stream.write_all(format!("{}\n", serde_json::to_string(request)?).as_bytes())?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn public_key(&self, coin_type: u32) -> anyhow::Result<PublicKey> {
+        match self.request(&RemoteSignerRequest::PublicKey(coin_type))? {
+            RemoteSignerResponse::PublicKey(json) => PublicKey::from_json(&json).map_err(|e| anyhow::anyhow!("Error: {}", e)),
+            RemoteSignerResponse::Error(err) => Err(anyhow::anyhow!("Error: Remote signer: {}", err)),
+            _ => Err(anyhow::anyhow!("Error: Remote signer: unexpected response")),
+        }
+    }
+
+    fn sign_bytes(&self, coin_type: u32, sign_doc_bytes: &[u8]) -> anyhow::Result<Vec<u8>> {
+        match self.request(&RemoteSignerRequest::Sign(coin_type, hex::encode(sign_doc_bytes)))? {
+            RemoteSignerResponse::Signature(signature) => Ok(hex::decode(signature)?),
+            RemoteSignerResponse::Error(err) => Err(anyhow::anyhow!("Error: Remote signer: {}", err)),
+            _ => Err(anyhow::anyhow!("Error: Remote signer: unexpected response")),
+        }
+    }
+}
+
+/// What the remote signer signs: SignDocs for the chain ids of the configured accounts, with the key of that chain,
+/// that only contain the allowed message types (messages executed by a MsgExec are checked as well).
+pub struct SignPolicy {
+    /// chain id -> coin type of the chain
+    pub chains: BTreeMap<String, u32>,
+    /// type urls
+    pub msg_types: Vec<String>,
+}
+
+impl SignPolicy {
+    pub fn check(&self, coin_type: u32, sign_doc_bytes: &[u8]) -> anyhow::Result<()> {
+        let sign_doc = ProtoSignDoc::decode(sign_doc_bytes)?;
+        match self.chains.get(&sign_doc.chain_id) {
+            Some(chain_coin_type) if *chain_coin_type == coin_type => {}
+            Some(chain_coin_type) => {
+                return Err(anyhow::anyhow!("Error: Chain {} uses coin type {}, not {}", sign_doc.chain_id, chain_coin_type, coin_type));
+            }
+            None => {
+                return Err(anyhow::anyhow!("Error: Chain id {} is not allowed", sign_doc.chain_id));
+            }
+        }
+        let body = TxBody::decode(sign_doc.body_bytes.as_slice())?;
+        if body.messages.is_empty() {
+            return Err(anyhow::anyhow!("Error: The transaction has no messages"));
+        }
+        for message in unwrap_messages(body.messages) {
+            if !self.msg_types.contains(&message.type_url) {
+                return Err(anyhow::anyhow!("Error: Message type {} is not allowed", message.type_url));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle_remote_signer_request(signer: &dyn Signer, policy: &SignPolicy, request: RemoteSignerRequest) -> RemoteSignerResponse {
+    let result = match request {
+        RemoteSignerRequest::PublicKey(coin_type) => signer.public_key(coin_type).map(|p| RemoteSignerResponse::PublicKey(p.to_json())),
+        RemoteSignerRequest::Sign(coin_type, sign_doc_bytes) => hex::decode(sign_doc_bytes)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| policy.check(coin_type, &bytes).map(|_| bytes))
+            .and_then(|bytes| signer.sign_bytes(coin_type, &bytes))
+            .map(|signature| RemoteSignerResponse::Signature(hex::encode(signature))),
+    };
+    result.unwrap_or_else(|err| RemoteSignerResponse::Error(err.to_string()))
+}
+
+/// Binds the socket inside a directory only the owner can enter, restricts the socket to the owner
+/// and only then moves it to socket_path, so no one else can connect in between.
+fn bind_private_socket(socket_path: &str) -> anyhow::Result<UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let path = Path::new(socket_path);
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let file_name = path
+        .file_name()
+        .ok_or(anyhow::anyhow!("Error: Invalid socket path: {}", socket_path))?
+        .to_string_lossy()
+        .to_string();
+    let staging_dir = path.with_file_name(format!(".{}.staging", file_name));
+    if staging_dir.exists() {
+        std::fs::remove_dir_all(&staging_dir)?;
+    }
+    std::fs::DirBuilder::new().mode(0o700).create(&staging_dir)?;
+    let staged_socket = staging_dir.join("socket");
+    let listener = UnixListener::bind(&staged_socket)?;
+    std::fs::set_permissions(&staged_socket, std::fs::Permissions::from_mode(0o600))?;
+    std::fs::rename(&staged_socket, path)?;
+    std::fs::remove_dir(&staging_dir)?;
+    Ok(listener)
+}
+
+/// Serves the given signer on a Unix socket, blocks forever. Requests the policy does not allow are answered with an error.
+pub fn serve_remote_signer(socket_path: &str, signer: &dyn Signer, policy: &SignPolicy) -> anyhow::Result<()> {
+    let listener = bind_private_socket(socket_path)?;
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => {
+                continue;
+            }
+        };
+        let mut line = String::new();
+        if BufReader::new(&stream).read_line(&mut line).is_err() {
+            continue;
+        }
+        let response = match serde_json::from_str::<RemoteSignerRequest>(&line) {
+            Ok(request) => handle_remote_signer_request(signer, policy, request),
+            Err(err) => RemoteSignerResponse::Error(err.to_string()),
+        };
+        if let Ok(response) = serde_json::to_string(&response) {
+            stream.write_all(format!("{}\n", response).as_bytes()).ok();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmrs::tx::{Body, Fee, SignerInfo};
+    use crate::blockchain::authz::MSG_VOTE;
+    use cosmrs::Coin;
+    use std::thread;
+    use std::time::Duration;
+
+    const TEST_MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn test_sign_doc(public_key: PublicKey, chain_id: &str, msg_type: &str) -> SignDoc {
+        let msg = cosmrs::Any {
+            type_url: msg_type.to_string(),
+            value: Vec::new(),
+        };
+        let body = Body::new(vec![msg], "test", 0u16);
+        let fee = Fee::from_amount_and_gas(
+            Coin {
+                denom: "uatom".parse().unwrap(),
+                amount: 1000u128,
+            },
+            100_000u64,
+        );
+        let auth_info = SignerInfo::single_direct(Some(public_key), 0).auth_info(fee);
+        SignDoc::new(&body, &auth_info, &chain_id.parse().unwrap(), 1).unwrap()
+    }
+
+    fn test_policy() -> SignPolicy {
+        SignPolicy {
+            chains: [("cosmoshub-4".to_string(), COSMOS_COIN_TYPE), ("phoenix-1".to_string(), TERRA_COIN_TYPE)].into_iter().collect(),
+            msg_types: vec![MSG_VOTE.to_string()],
+        }
+    }
+
+    #[test]
+    fn remote_signer_matches_local_signer() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let socket_path = dir.path().join("remote_signer_socket").to_str().unwrap().to_string();
+
+        // stand-in for the separate signer process
+        let server_socket_path = socket_path.clone();
+        thread::spawn(move || {
+            let signer = MnemonicSigner::new(&SecUtf8::from(TEST_MNEMONIC), &[COSMOS_COIN_TYPE, TERRA_COIN_TYPE]).unwrap();
+            serve_remote_signer(&server_socket_path, &signer, &test_policy()).unwrap();
+        });
+        while !Path::new(&socket_path).exists() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let local = MnemonicSigner::new(&SecUtf8::from(TEST_MNEMONIC), &[COSMOS_COIN_TYPE, TERRA_COIN_TYPE])?;
+        let remote = RemoteSigner::new(&socket_path);
+
+        assert_eq!(remote.account_address(COSMOS_COIN_TYPE, "cosmos")?, local.account_address(COSMOS_COIN_TYPE, "cosmos")?);
+        assert_ne!(local.account_address(COSMOS_COIN_TYPE, "terra")?, local.account_address(TERRA_COIN_TYPE, "terra")?);
+        assert!(remote.public_key(529).is_err());
+
+        let public_key = remote.public_key(COSMOS_COIN_TYPE)?;
+        let local_tx = local.sign(COSMOS_COIN_TYPE, test_sign_doc(public_key, "cosmoshub-4", MSG_VOTE))?.to_bytes().unwrap();
+        let remote_tx = remote.sign(COSMOS_COIN_TYPE, test_sign_doc(public_key, "cosmoshub-4", MSG_VOTE))?.to_bytes().unwrap();
+        assert_eq!(local_tx, remote_tx);
+
+        // no blind signing: other chains, other keys and other messages are refused.
+        assert!(remote.sign(COSMOS_COIN_TYPE, test_sign_doc(public_key, "osmosis-1", MSG_VOTE)).is_err());
+        assert!(remote.sign(TERRA_COIN_TYPE, test_sign_doc(public_key, "cosmoshub-4", MSG_VOTE)).is_err());
+        assert!(remote.sign(COSMOS_COIN_TYPE, test_sign_doc(public_key, "cosmoshub-4", "/cosmos.bank.v1beta1.MsgSend")).is_err());
+
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(&socket_path)?.permissions().mode() & 0o777, 0o600);
+        Ok(())
+    }
+}
@@ -1,37 +1,249 @@
 use secstr::*;
 // https://github.com/unrelentingtech/secstr
 
-/* (defined in main.rs, needs to be at the root)
-#[macro_use]
-extern crate litcrypt;
-// https://github.com/anvie/litcrypt.rs
-use_litcrypt!();
-
-*/
-
-fn xor_cipher(text: String) -> String {
-    let mut v1: Vec<u8> = text.to_string().into_bytes();
-    /* INFO: Action needed!
-     *
-     * You need to replace the string within lc!("...") with a random string of at least 256 characters.
-     * The secret will be encrypting during compilation time and
-     * will remain encrypted in both disk and memory during runtime until it is used.
-     *
-     * It will be used to encrypt/decrypt the seed phrase!
-     * This provides additional security in the case when someone is able to access the RAM due to a memory leak or exploit.
-     */
-    let secret: Vec<u8> = lc!("9c9d2f0f9ef476137eb392d5cb347035840786dde834188148daf42d1bbb98ca792fef3fa7a4b32b230737a55cdc8932889cc4741d77bc864c57ba973ca6950c40b1139a6fc8ac744e673307cd7f9a624be5f7bcc2d4f7904e24122a3c5788b94af78ec60c7a0c75981aab5a68b8273466436f177f2136131a625e57ab352ce63ee6d2ad02b1ef715e8f1cd2a6e1b36bf7eaa3492df9405f1ddff55eba673dffeee131fcec54bf79012f82e62ec9ef2e16bdc1985d9ab4a12fc7b4b801eec004dc3d2b89761e66bdcfbebfae37e6a1d6cf634ee4747c91152fbec9df65d7eb134791fb8aafd25cdcda56b2dd96586cb8e48b305e415b0d2475431904bb23649e").to_string().into_bytes();
-
-    v1.iter_mut()
-        .zip(secret.iter())
-        .for_each(|(x1, x2)| *x1 ^= *x2);
-    return String::from_utf8_lossy(&v1).to_string();
-}
-
-pub fn encrypt_text_with_secret(text: String) -> SecUtf8 {
-    return SecUtf8::from(xor_cipher(text));
-}
-
-pub fn decrypt_text_with_secret(secret: &SecUtf8) -> String {
-    return xor_cipher(secret.unsecure().to_string());
-}
\ No newline at end of file
+use cosmrs::bip32::{Language, Mnemonic};
+use log::warn;
+use rand::rngs::OsRng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::sync::Arc;
+
+use super::keystore::Keystore;
+use super::signer::{KeystoreSigner, SignPolicy, Signer, COSMOS_COIN_TYPE, ETHERMINT_COIN_TYPE, TERRA_COIN_TYPE};
+use crate::blockchain::authz::REQUIRED_MSG_TYPES;
+
+/// If set, the keystore is unlocked without an interactive prompt (e.g. when running as a service).
+pub const KEYSTORE_PASSPHRASE_ENV: &str = "CRB_KEYSTORE_PASSPHRASE";
+
+fn prompt_secret(prompt: &str) -> anyhow::Result<SecUtf8> {
+    Ok(SecUtf8::from(rpassword::prompt_password(prompt)?))
+}
+
+fn prompt_new_passphrase() -> anyhow::Result<SecUtf8> {
+    let passphrase = prompt_secret("Enter a new keystore passphrase: ")?;
+    if passphrase.unsecure().is_empty() {
+        return Err(anyhow::anyhow!("Error: The passphrase must not be empty"));
+    }
+    if prompt_secret("Repeat the passphrase: ")?.unsecure() != passphrase.unsecure() {
+        return Err(anyhow::anyhow!("Error: The passphrases do not match"));
+    }
+    Ok(passphrase)
+}
+
+fn read_passphrase() -> anyhow::Result<SecUtf8> {
+    match std::env::var(KEYSTORE_PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(SecUtf8::from(passphrase)),
+        Err(_) => prompt_secret("Enter the keystore passphrase: "),
+    }
+}
+
+pub fn unlock_keystore(path: &str) -> anyhow::Result<SecUtf8> {
+    Keystore::load(path)?.decrypt(&read_passphrase()?)
+}
+
+pub fn unlock_keystore_signer(path: &str, coin_types: &[u32]) -> anyhow::Result<KeystoreSigner> {
+    KeystoreSigner::unlock(path, &read_passphrase()?, coin_types)
+}
+
+/// A typo or a wrong checksum would otherwise be encrypted into the keystore and derive a wrong account.
+fn validate_mnemonic(mnemonic: &SecUtf8) -> anyhow::Result<()> {
+    Mnemonic::new(mnemonic.unsecure(), Language::English).map_err(|e| anyhow::anyhow!("Error: Invalid seed phrase: {}", e))?;
+    Ok(())
+}
+
+pub fn import_keystore(path: &str) -> anyhow::Result<()> {
+    if Keystore::exists(path) {
+        return Err(anyhow::anyhow!("Error: A keystore already exists at {}", path));
+    }
+    let mnemonic = prompt_secret("Enter your seed phrase: ")?;
+    validate_mnemonic(&mnemonic)?;
+    Keystore::encrypt(&mnemonic, &prompt_new_passphrase()?)?.save(path)
+}
+
+/// Creates a new (hot) key for the bot, it is meant to act as authz grantee of the owner's account.
+pub fn generate_keystore(path: &str) -> anyhow::Result<()> {
+    if Keystore::exists(path) {
+        return Err(anyhow::anyhow!("Error: A keystore already exists at {}", path));
+    }
+    let mnemonic = Mnemonic::random(OsRng, Language::English);
+    Keystore::encrypt(&SecUtf8::from(mnemonic.phrase()), &prompt_new_passphrase()?)?.save(path)
+}
+
+pub fn export_keystore(path: &str) -> anyhow::Result<SecUtf8> {
+    unlock_keystore(path)
+}
+
+pub fn change_keystore_passphrase(path: &str) -> anyhow::Result<()> {
+    let keystore = Keystore::load(path)?;
+    let old_passphrase = prompt_secret("Enter the current keystore passphrase: ")?;
+    keystore
+        .change_passphrase(&old_passphrase, &prompt_new_passphrase()?)?
+        .save(path)
+}
+
+/// The parts of a chain-registry `chain.json` needed to derive an account.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RegistryChain {
+    pub chain_name: String,
+    #[serde(default)]
+    pub chain_id: String,
+    pub slip44: u32,
+    pub bech32_prefix: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DerivedAccount {
+    pub blockchain: String,
+    pub coin_type: u32,
+    pub prefix: String,
+    pub address: String,
+}
+
+/// Reads `<path>/<chain>/chain.json` for every chain in the registry, chains without slip44 or bech32 prefix are skipped.
+/// Ethermint chains are skipped as well, their accounts can not be derived.
+pub fn load_registry_chains(path: &str) -> Vec<RegistryChain> {
+    let mut chains: Vec<RegistryChain> = match fs::read_dir(path) {
+        Ok(dir) => dir
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| fs::read_to_string(entry.path().join("chain.json")).ok())
+            .filter_map(|file| serde_json::from_str::<RegistryChain>(&file).ok())
+            .filter(|chain| chain.slip44 != ETHERMINT_COIN_TYPE)
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    chains.sort_by(|a, b| a.chain_name.cmp(&b.chain_name));
+    chains
+}
+
+/// The coin types a signer needs to derive, the cosmos and terra coin types are always included.
+pub fn registry_coin_types(chains: &[RegistryChain]) -> Vec<u32> {
+    let mut coin_types: BTreeSet<u32> = chains.iter().map(|c| c.slip44).collect();
+    coin_types.insert(COSMOS_COIN_TYPE);
+    coin_types.insert(TERRA_COIN_TYPE);
+    coin_types.into_iter().collect()
+}
+
+/// The remote signer only signs for the chains in the registry, and only the messages the bot sends.
+pub fn registry_sign_policy(chains: &[RegistryChain]) -> SignPolicy {
+    SignPolicy {
+        chains: chains
+            .iter()
+            .filter(|c| !c.chain_id.is_empty())
+            .map(|c| (c.chain_id.to_owned(), c.slip44))
+            .collect(),
+        msg_types: REQUIRED_MSG_TYPES.iter().map(|x| x.to_string()).collect(),
+    }
+}
+
+// chain names differ in spelling between the registry and the supported blockchains (e.g. "cosmoshub", "cosmos_hub").
+fn normalize_chain_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '-'], "")
+}
+
+/// One signer and the accounts derived from it, one per chain in the registry.
+#[derive(Clone)]
+pub struct Wallet {
+    pub signer: Arc<dyn Signer>,
+    pub accounts: Vec<DerivedAccount>,
+}
+
+impl Wallet {
+    /// A chain the signer can not derive an account for is skipped, the other chains are still usable.
+    pub fn new(signer: Arc<dyn Signer>, chains: &[RegistryChain]) -> Wallet {
+        let mut accounts: Vec<DerivedAccount> = Vec::new();
+        for chain in chains {
+            match signer.account_address(chain.slip44, &chain.bech32_prefix) {
+                Ok(address) => accounts.push(DerivedAccount {
+                    blockchain: chain.chain_name.to_owned(),
+                    coin_type: chain.slip44,
+                    prefix: chain.bech32_prefix.to_owned(),
+                    address,
+                }),
+                Err(err) => {
+                    warn!("Wallet::new: skipping {}, unable to derive the account: {:?}", chain.chain_name, err);
+                }
+            }
+        }
+        Wallet { signer, accounts }
+    }
+
+    pub fn account(&self, blockchain: &str) -> Option<&DerivedAccount> {
+        let name = normalize_chain_name(blockchain);
+        self.accounts.iter().find(|a| normalize_chain_name(&a.blockchain) == name)
+    }
+
+    /// The table only holds public addresses, it is shown via `cosmos-rust-bot keys accounts`.
+    pub fn save_accounts(&self, path: &str) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(&self.accounts)?)?;
+        Ok(())
+    }
+}
+
+pub fn load_accounts(path: &str) -> anyhow::Result<Vec<DerivedAccount>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::account::signer::MnemonicSigner;
+
+    const TEST_MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn chain(chain_name: &str, slip44: u32, bech32_prefix: &str) -> RegistryChain {
+        RegistryChain {
+            chain_name: chain_name.to_string(),
+            chain_id: "".to_string(),
+            slip44,
+            bech32_prefix: bech32_prefix.to_string(),
+        }
+    }
+
+    #[test]
+    fn seed_phrases_with_a_bad_checksum_are_rejected() {
+        assert!(validate_mnemonic(&SecUtf8::from(TEST_MNEMONIC)).is_ok());
+        // 12 valid words, but the last one does not match the checksum
+        let bad_checksum = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+        assert!(validate_mnemonic(&SecUtf8::from(bad_checksum)).is_err());
+        assert!(validate_mnemonic(&SecUtf8::from("abandon abandon about")).is_err());
+    }
+
+    #[test]
+    fn registry_chains_are_loaded_sorted_without_ethermint_and_incomplete_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, chain_json: &str| {
+            fs::create_dir_all(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("chain.json"), chain_json).unwrap();
+        };
+        write("osmosis", r#"{"chain_name": "osmosis", "chain_id": "osmosis-1", "slip44": 118, "bech32_prefix": "osmo", "status": "live"}"#);
+        write("cosmoshub", r#"{"chain_name": "cosmoshub", "slip44": 118, "bech32_prefix": "cosmos"}"#);
+        write("evmos", r#"{"chain_name": "evmos", "slip44": 60, "bech32_prefix": "evmos"}"#);
+        write("testnet", r#"{"chain_name": "testnet", "bech32_prefix": "test"}"#);
+        fs::create_dir_all(dir.path().join("_IBC")).unwrap();
+
+        let chains = load_registry_chains(dir.path().to_str().unwrap());
+        assert_eq!(chains, vec![chain("cosmoshub", 118, "cosmos"), RegistryChain { chain_id: "osmosis-1".to_string(), ..chain("osmosis", 118, "osmo") }]);
+        assert!(load_registry_chains(dir.path().join("missing").to_str().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn chains_without_a_derived_key_are_skipped() {
+        let signer = MnemonicSigner::new(&SecUtf8::from(TEST_MNEMONIC), &[COSMOS_COIN_TYPE]).unwrap();
+        let wallet = Wallet::new(Arc::new(signer), &[chain("cosmoshub", COSMOS_COIN_TYPE, "cosmos"), chain("terra2", TERRA_COIN_TYPE, "terra")]);
+        assert_eq!(wallet.accounts.len(), 1);
+        assert_eq!(wallet.accounts[0].address, wallet.signer.account_address(COSMOS_COIN_TYPE, "cosmos").unwrap());
+    }
+
+    #[test]
+    fn accounts_are_found_by_any_spelling_of_the_chain_name() {
+        assert_eq!(normalize_chain_name("Cosmos_Hub"), "cosmoshub");
+        assert_eq!(normalize_chain_name("terra-2"), normalize_chain_name("terra2"));
+
+        let signer = MnemonicSigner::new(&SecUtf8::from(TEST_MNEMONIC), &[COSMOS_COIN_TYPE]).unwrap();
+        let wallet = Wallet::new(Arc::new(signer), &[chain("cosmoshub", COSMOS_COIN_TYPE, "cosmos"), chain("osmosis", COSMOS_COIN_TYPE, "osmo")]);
+        assert_eq!(wallet.account("cosmos_hub").map(|a| a.prefix.as_str()), Some("cosmos"));
+        assert_eq!(wallet.account("Osmosis").map(|a| a.prefix.as_str()), Some("osmo"));
+        assert!(wallet.account("juno").is_none());
+    }
+}
@@ -0,0 +1,140 @@
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::utils::response::TaskResult;
+use cosmos_sdk_proto::cosmos::authz::v1beta1::{query_client::QueryClient, GenericAuthorization, QueryGrantsRequest};
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmos::staking::v1beta1::StakeAuthorization;
+use cosmrs::proto::cosmos::authz::v1beta1::MsgExec;
+use cosmrs::proto::traits::Message as _;
+use cosmrs::Any;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+
+use super::channel;
+use crate::store::HistoryStore;
+
+// The bot acts as authz grantee, the owner's key never leaves the owner's wallet.
+// Every automated action is wrapped in a MsgExec that is signed with the bot's own (hot) key.
+
+pub const AUTHZ_GRANTS_TREE: &str = "authz_grants";
+
+pub const MSG_VOTE: &str = "/cosmos.gov.v1beta1.MsgVote";
+pub const MSG_WITHDRAW_DELEGATOR_REWARD: &str = "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward";
+pub const MSG_DELEGATE: &str = "/cosmos.staking.v1beta1.MsgDelegate";
+
+/// Permissions the owner grants to the bot.
+pub const REQUIRED_MSG_TYPES: [&str; 3] = [MSG_VOTE, MSG_WITHDRAW_DELEGATOR_REWARD, MSG_DELEGATE];
+
+const GENERIC_AUTHORIZATION: &str = "/cosmos.authz.v1beta1.GenericAuthorization";
+const STAKE_AUTHORIZATION: &str = "/cosmos.staking.v1beta1.StakeAuthorization";
+pub const MSG_EXEC: &str = "/cosmos.authz.v1beta1.MsgExec";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GrantInfo {
+    pub msg_type_url: String,
+    /// unix timestamp, None if the grant does not expire.
+    pub expiration: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuthzGrants {
+    pub blockchain: String,
+    pub granter: String,
+    pub grantee: String,
+    pub grants: Vec<GrantInfo>,
+}
+
+pub fn grants_key(blockchain: &str, granter: &str) -> String {
+    format!("{}_{}", blockchain, granter)
+}
+
+/// The message type a grant authorizes, stake authorizations are mapped to the staking messages.
+fn authorized_msg_type(authorization: &prost_types::Any) -> Option<String> {
+    match authorization.type_url.as_str() {
+        GENERIC_AUTHORIZATION => GenericAuthorization::decode(authorization.value.as_slice()).ok().map(|a| a.msg),
+        STAKE_AUTHORIZATION => match StakeAuthorization::decode(authorization.value.as_slice()).ok()?.authorization_type {
+            1 => Some(MSG_DELEGATE.to_string()),
+            2 => Some("/cosmos.staking.v1beta1.MsgUndelegate".to_string()),
+            3 => Some("/cosmos.staking.v1beta1.MsgBeginRedelegate".to_string()),
+            _ => None,
+        },
+        other => Some(other.to_string()),
+    }
+}
+
+pub async fn query_grants(blockchain: &SupportedBlockchain, granter: &str, grantee: &str) -> anyhow::Result<Vec<GrantInfo>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    let mut grants: Vec<GrantInfo> = Vec::new();
+    let mut next_key: Vec<u8> = Vec::new();
+    loop {
+        let res = client
+            .grants(QueryGrantsRequest {
+                granter: granter.to_string(),
+                grantee: grantee.to_string(),
+                msg_type_url: "".to_string(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    offset: 0,
+                    limit: 100,
+                    count_total: false,
+                    reverse: false,
+                }),
+            })
+            .await?
+            .into_inner();
+        for grant in res.grants {
+            if let Some(msg_type_url) = grant.authorization.as_ref().and_then(authorized_msg_type) {
+                grants.push(GrantInfo {
+                    msg_type_url,
+                    expiration: grant.expiration.map(|t| t.seconds),
+                });
+            }
+        }
+        next_key = res.pagination.map(|p| p.next_key).unwrap_or_default();
+        if next_key.is_empty() {
+            break;
+        }
+    }
+    Ok(grants)
+}
+
+pub async fn fetch_authz_grants(blockchain: SupportedBlockchain, granter: String, grantee: String, history_store: HistoryStore, key: String) -> anyhow::Result<TaskResult> {
+    let grants = query_grants(&blockchain, &granter, &grantee).await?;
+    history_store.push(
+        AUTHZ_GRANTS_TREE,
+        &grants_key(&blockchain.name, &granter),
+        &AuthzGrants {
+            blockchain: blockchain.name.to_owned(),
+            granter,
+            grantee,
+            grants,
+        },
+    )?;
+    Ok(TaskResult {
+        list_of_keys_modified: vec![key, blockchain.name.to_owned()],
+    })
+}
+
+/// Wraps the messages (signed by the granter) into a MsgExec of the grantee.
+pub fn msg_exec(grantee: &str, msgs: Vec<Any>) -> Any {
+    Any {
+        type_url: MSG_EXEC.to_string(),
+        value: MsgExec {
+            grantee: grantee.to_string(),
+            msgs,
+        }
+        .encode_to_vec(),
+    }
+}
+
+/// The MsgGrant messages the owner needs to sign, as CLI commands.
+pub fn grant_instructions(granter: &str, grantee: &str, expiration: i64) -> Vec<String> {
+    REQUIRED_MSG_TYPES
+        .iter()
+        .map(|msg_type_url| {
+            format!(
+                "<daemon> tx authz grant {} generic --msg-type={} --expiration={} --from {}",
+                grantee, msg_type_url, expiration, granter
+            )
+        })
+        .collect()
+}
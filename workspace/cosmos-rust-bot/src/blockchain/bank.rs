@@ -0,0 +1,314 @@
+use bot_library::shared::WatchedAddress;
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::utils::response::TaskResult;
+use cosmos_sdk_proto::cosmos::bank::v1beta1::{query_client::QueryClient, MsgMultiSend, MsgSend, QueryAllBalancesRequest};
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+use log::warn;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use super::channel;
+use super::tx::{query_latest_height, search_txs, FoundTx};
+use crate::store::HistoryStore;
+
+pub const BALANCES_TREE: &str = "balances";
+pub const INCOMING_TRANSFERS_TREE: &str = "incoming_transfers";
+
+const MSG_SEND: &str = "/cosmos.bank.v1beta1.MsgSend";
+const MSG_MULTI_SEND: &str = "/cosmos.bank.v1beta1.MsgMultiSend";
+/// Minimum that applies to every denom without a minimum of its own.
+const ANY_DENOM: &str = "*";
+const MAX_TRANSFERS_KEPT: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BalanceSnapshot {
+    pub blockchain: String,
+    pub address: String,
+    /// denom -> amount
+    pub balances: BTreeMap<String, String>,
+    /// denom -> signed difference to the previous snapshot
+    pub changes: Vec<(String, String)>,
+    /// transactions up to this height were searched for incoming transfers
+    pub last_transfer_height: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IncomingTransfer {
+    pub blockchain: String,
+    pub address: String,
+    pub txhash: String,
+    pub height: i64,
+    pub timestamp: String,
+    pub sender: String,
+    /// (denom, amount)
+    pub amount: Vec<(String, String)>,
+    pub memo: String,
+}
+
+fn parse_amount(amount: &str) -> i128 {
+    amount.parse::<i128>().unwrap_or(0)
+}
+
+/// Dust: nothing reaches the minimum amount of its denom. Denoms without a minimum (and no "*" minimum)
+/// are ignored, anyone can send tokens of any denom to the address.
+pub fn above_min_amounts(amounts: &[(String, String)], min_amounts: &HashMap<String, u64>) -> bool {
+    amounts.iter().any(|(denom, amount)| {
+        match min_amounts.get(denom).or_else(|| min_amounts.get(ANY_DENOM)) {
+            Some(min_amount) => parse_amount(amount).abs() >= *min_amount as i128,
+            None => false,
+        }
+    })
+}
+
+fn balance_changes(previous: &BTreeMap<String, String>, current: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    let mut changes: Vec<(String, String)> = Vec::new();
+    for denom in previous.keys().chain(current.keys().filter(|d| !previous.contains_key(*d))) {
+        let before = previous.get(denom).map(|a| parse_amount(a)).unwrap_or(0);
+        let after = current.get(denom).map(|a| parse_amount(a)).unwrap_or(0);
+        if before != after {
+            changes.push((denom.to_owned(), (after - before).to_string()));
+        }
+    }
+    changes
+}
+
+pub async fn query_balances(blockchain: &SupportedBlockchain, address: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    let mut balances: BTreeMap<String, String> = BTreeMap::new();
+    let mut next_key: Vec<u8> = Vec::new();
+    loop {
+        let res = client
+            .all_balances(QueryAllBalancesRequest {
+                address: address.to_string(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    offset: 0,
+                    limit: 100,
+                    count_total: false,
+                    reverse: false,
+                }),
+            })
+            .await?
+            .into_inner();
+        balances.extend(res.balances.into_iter().map(|c| (c.denom, c.amount)));
+        next_key = res.pagination.map(|p| p.next_key).unwrap_or_default();
+        if next_key.is_empty() {
+            break;
+        }
+    }
+    Ok(balances)
+}
+
+fn coin_amounts(coins: Vec<Coin>) -> Vec<(String, String)> {
+    coins.into_iter().map(|c| (c.denom, c.amount)).collect()
+}
+
+/// The bank sends (MsgSend and MsgMultiSend) to the address within the transaction,
+/// the messages of an authz MsgExec are already unwrapped by search_txs.
+/// A message that can not be decoded is skipped, the other transfers of the transaction are still reported.
+fn incoming_transfers(blockchain: &str, address: &str, tx: &FoundTx) -> Vec<IncomingTransfer> {
+    let mut transfers: Vec<IncomingTransfer> = Vec::new();
+    for msg in tx.messages.iter() {
+        let (sender, amount) = match msg.type_url.as_str() {
+            MSG_SEND => {
+                let send = match MsgSend::decode(msg.value.as_slice()) {
+                    Ok(send) => send,
+                    Err(err) => {
+                        warn!("incoming_transfers: skipping undecodable {} in {}: {:?}", msg.type_url, tx.txhash, err);
+                        continue;
+                    }
+                };
+                if send.to_address != address {
+                    continue;
+                }
+                (send.from_address, coin_amounts(send.amount))
+            }
+            MSG_MULTI_SEND => {
+                let send = match MsgMultiSend::decode(msg.value.as_slice()) {
+                    Ok(send) => send,
+                    Err(err) => {
+                        warn!("incoming_transfers: skipping undecodable {} in {}: {:?}", msg.type_url, tx.txhash, err);
+                        continue;
+                    }
+                };
+                let coins: Vec<Coin> = send.outputs.into_iter().filter(|o| o.address == address).flat_map(|o| o.coins).collect();
+                if coins.is_empty() {
+                    continue;
+                }
+                (send.inputs.into_iter().map(|i| i.address).collect::<Vec<String>>().join(", "), coin_amounts(coins))
+            }
+            _ => {
+                continue;
+            }
+        };
+        transfers.push(IncomingTransfer {
+            blockchain: blockchain.to_string(),
+            address: address.to_string(),
+            txhash: tx.txhash.to_owned(),
+            height: tx.height,
+            timestamp: tx.timestamp.to_owned(),
+            sender,
+            amount,
+            memo: tx.memo.to_owned(),
+        });
+    }
+    transfers
+}
+
+/// Returns the bank sends to the address in all transactions above after_height, oldest first,
+/// and the height up to which the transactions were searched.
+pub async fn query_incoming_transfers(blockchain: &SupportedBlockchain, address: &str, after_height: i64) -> anyhow::Result<(Vec<IncomingTransfer>, i64)> {
+    let txs = search_txs(blockchain, vec![format!("transfer.recipient='{}'", address)], Some(after_height)).await?;
+    let mut transfers: Vec<IncomingTransfer> = Vec::new();
+    for tx in txs.iter() {
+        transfers.append(&mut incoming_transfers(&blockchain.name, address, tx));
+    }
+    let searched_height = txs.iter().map(|tx| tx.height).max().unwrap_or(after_height).max(after_height);
+    Ok((transfers, searched_height))
+}
+
+/// Compares the balances of the watched addresses with the previous refresh.
+/// On an increase the transactions since the last search are searched for the bank sends that caused it.
+pub async fn fetch_balance_changes(blockchain: SupportedBlockchain, watched: Vec<WatchedAddress>, history_store: HistoryStore, key: String) -> anyhow::Result<TaskResult> {
+    let mut list_of_keys_modified = vec![key];
+    for watched in watched {
+        let address_key = format!("{}_{}", blockchain.name, watched.address);
+        let balances = query_balances(&blockchain, &watched.address).await?;
+        let previous = history_store.latest::<BalanceSnapshot>(BALANCES_TREE, &address_key).map(|(_, s)| s);
+
+        let (changes, mut last_transfer_height) = match &previous {
+            Some(previous) if previous.balances == balances => {
+                continue;
+            }
+            Some(previous) => (balance_changes(&previous.balances, &balances), previous.last_transfer_height),
+            None => (Vec::new(), -1),
+        };
+
+        if last_transfer_height < 0 {
+            // the first snapshot only sets the cursor, old transfers are not reported.
+            last_transfer_height = query_latest_height(&blockchain).await?;
+        } else if changes.iter().any(|(_, change)| parse_amount(change) > 0) {
+            let (transfers, searched_height) = query_incoming_transfers(&blockchain, &watched.address, last_transfer_height).await?;
+            for transfer in transfers.iter() {
+                if above_min_amounts(&transfer.amount, &watched.min_amounts) {
+                    let transfer_key = format!("{}_{:020}_{}", address_key, transfer.height, transfer.txhash);
+                    history_store.insert(INCOMING_TRANSFERS_TREE, &transfer_key, transfer)?;
+                    list_of_keys_modified.push(transfer_key);
+                }
+            }
+            last_transfer_height = searched_height;
+        }
+
+        history_store.push(BALANCES_TREE, &address_key, &BalanceSnapshot {
+            blockchain: blockchain.name.to_owned(),
+            address: watched.address.to_owned(),
+            balances,
+            changes,
+            last_transfer_height,
+        })?;
+        list_of_keys_modified.push(address_key.to_owned());
+
+        let mut kept: Vec<String> = history_store
+            .values::<IncomingTransfer>(INCOMING_TRANSFERS_TREE)
+            .into_iter()
+            .map(|(k, _)| k)
+            .filter(|k| k.starts_with(&format!("{}_", address_key)))
+            .collect();
+        kept.sort();
+        let kept = kept.split_off(kept.len().saturating_sub(MAX_TRANSFERS_KEPT));
+        history_store.retain(INCOMING_TRANSFERS_TREE, &format!("{}_", address_key), &kept)?;
+    }
+    Ok(TaskResult { list_of_keys_modified })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn balance_changes_and_dust() {
+        let previous: BTreeMap<String, String> = [("uosmo".to_string(), "100".to_string()), ("uion".to_string(), "5".to_string())].into_iter().collect();
+        let current: BTreeMap<String, String> = [("uosmo".to_string(), "40".to_string()), ("uatom".to_string(), "7".to_string())].into_iter().collect();
+        let changes = balance_changes(&previous, &current);
+        assert_eq!(changes, vec![
+            ("uion".to_string(), "-5".to_string()),
+            ("uosmo".to_string(), "-60".to_string()),
+            ("uatom".to_string(), "7".to_string()),
+        ]);
+
+        let min_amounts: HashMap<String, u64> = [("uatom".to_string(), 10), ("uosmo".to_string(), 50)].into_iter().collect();
+        assert!(!above_min_amounts(&[("uatom".to_string(), "7".to_string())], &min_amounts));
+        assert!(above_min_amounts(&changes, &min_amounts));
+        // denoms without a minimum are ignored, unless there is a minimum for "*".
+        assert!(!above_min_amounts(&[("uion".to_string(), "1".to_string())], &min_amounts));
+        let mut min_amounts = min_amounts;
+        min_amounts.insert(ANY_DENOM.to_string(), 1);
+        assert!(above_min_amounts(&[("uion".to_string(), "1".to_string())], &min_amounts));
+        assert!(!above_min_amounts(&[("uatom".to_string(), "7".to_string())], &min_amounts));
+    }
+
+    fn coin(denom: &str, amount: &str) -> Coin {
+        Coin {
+            denom: denom.to_string(),
+            amount: amount.to_string(),
+        }
+    }
+
+    fn any(type_url: &str, msg: &impl Message) -> prost_types::Any {
+        prost_types::Any {
+            type_url: type_url.to_string(),
+            value: msg.encode_to_vec(),
+        }
+    }
+
+    #[test]
+    fn sends_multi_sends_and_sends_via_authz_are_incoming_transfers() {
+        use crate::blockchain::authz::MSG_EXEC;
+        use crate::blockchain::tx::unwrap_messages;
+        use cosmos_sdk_proto::cosmos::authz::v1beta1::MsgExec;
+        use cosmos_sdk_proto::cosmos::bank::v1beta1::{Input, Output};
+
+        let send = |to_address: &str| MsgSend {
+            from_address: "osmo1sender".to_string(),
+            to_address: to_address.to_string(),
+            amount: vec![coin("uosmo", "5")],
+        };
+        let multi_send = MsgMultiSend {
+            inputs: vec![
+                Input { address: "osmo1a".to_string(), coins: vec![coin("uosmo", "9")] },
+                Input { address: "osmo1b".to_string(), coins: vec![coin("uosmo", "1")] },
+            ],
+            outputs: vec![
+                Output { address: "osmo1me".to_string(), coins: vec![coin("uosmo", "7")] },
+                Output { address: "osmo1other".to_string(), coins: vec![coin("uosmo", "3")] },
+            ],
+        };
+        let exec = MsgExec {
+            grantee: "osmo1grantee".to_string(),
+            msgs: vec![any(MSG_SEND, &send("osmo1me"))],
+        };
+        let tx = FoundTx {
+            txhash: "HASH".to_string(),
+            height: 42,
+            timestamp: "2023-05-01T00:00:00Z".to_string(),
+            memo: "thanks".to_string(),
+            messages: unwrap_messages(vec![
+                any(MSG_SEND, &send("osmo1other")),
+                any(MSG_MULTI_SEND, &multi_send),
+                any(MSG_EXEC, &exec),
+                prost_types::Any {
+                    type_url: MSG_SEND.to_string(),
+                    value: vec![0xff],
+                },
+            ]),
+        };
+
+        let transfers = incoming_transfers("osmosis", "osmo1me", &tx);
+        assert_eq!(transfers.len(), 2);
+        assert_eq!((transfers[0].sender.as_str(), transfers[0].amount.clone()), ("osmo1a, osmo1b", vec![("uosmo".to_string(), "7".to_string())]));
+        assert_eq!((transfers[1].sender.as_str(), transfers[1].amount.clone()), ("osmo1sender", vec![("uosmo".to_string(), "5".to_string())]));
+        assert_eq!((transfers[1].height, transfers[1].memo.as_str()), (42, "thanks"));
+    }
+}
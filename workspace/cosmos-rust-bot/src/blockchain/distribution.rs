@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::utils::response::TaskResult;
+use cosmos_sdk_proto::cosmos::distribution::v1beta1::{query_client::QueryClient, QueryCommunityPoolRequest, QueryDelegationTotalRewardsRequest};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use super::{channel, dec_to_string};
+use crate::store::HistoryStore;
+
+pub const COMMUNITY_POOL_TREE: &str = "community_pool";
+/// snapshots kept per chain, the context of spend proposals only needs the latest two.
+const COMMUNITY_POOL_HISTORY_LEN: usize = 100;
+
+/// Community pool balance, denom -> amount in whole units.
+pub type CommunityPool = BTreeMap<String, String>;
+
+pub async fn fetch_community_pool(blockchain: SupportedBlockchain, history_store: HistoryStore, key: String) -> anyhow::Result<TaskResult> {
+    let mut client = QueryClient::new(channel(&blockchain).await?);
+    let res = client.community_pool(QueryCommunityPoolRequest {}).await?.into_inner();
+    let pool: CommunityPool = res
+        .pool
+        .into_iter()
+        // the fractional part changes with every block, a new snapshot is only stored if a whole unit changed.
+        .map(|c| (c.denom, dec_to_string(&c.amount).split('.').next().unwrap_or("0").to_string()))
+        .collect();
+    history_store.push_capped(COMMUNITY_POOL_TREE, &blockchain.name, &pool, COMMUNITY_POOL_HISTORY_LEN)?;
+    Ok(TaskResult {
+        list_of_keys_modified: vec![key, blockchain.name.to_owned()],
+    })
+}
+
+/// Claimable staking rewards of the delegator in the given denom, (validator, amount) truncated to whole units.
+pub async fn query_delegation_rewards(blockchain: &SupportedBlockchain, delegator_address: &str, denom: &str) -> anyhow::Result<Vec<(String, u64)>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    let res = client
+        .delegation_total_rewards(QueryDelegationTotalRewardsRequest {
+            delegator_address: delegator_address.to_string(),
+        })
+        .await?
+        .into_inner();
+    Ok(res
+        .rewards
+        .into_iter()
+        .map(|r| {
+            let amount = r
+                .reward
+                .iter()
+                .filter(|c| c.denom == denom)
+                .filter_map(|c| Decimal::from_str(&dec_to_string(&c.amount)).ok())
+                .sum::<Decimal>()
+                .trunc()
+                .to_string()
+                .parse::<u64>()
+                .unwrap_or(0);
+            (r.validator_address, amount)
+        })
+        .collect())
+}
@@ -0,0 +1,524 @@
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::utils::response::TaskResult;
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmos::distribution::v1beta1::CommunityPoolSpendProposal;
+use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
+use cosmos_sdk_proto::cosmos::gov::v1beta1::{query_client::QueryClient, MsgVote as ProtoMsgVote, MsgVoteWeighted, Proposal, ProposalStatus, QueryProposalRequest, QueryProposalsRequest, QueryVoteRequest, TallyResult, TextProposal, Vote, VoteOption};
+use cosmrs::proto::cosmos::gov::v1beta1::MsgVote;
+use cosmrs::proto::traits::Message as _;
+use cosmrs::Any;
+use cosmos_sdk_proto::cosmos::params::v1beta1::ParameterChangeProposal;
+use prost::Message;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tonic::transport::Channel;
+use tonic::Code;
+
+use super::authz::MSG_VOTE;
+use super::channel;
+use super::staking::{query_bonded_validators, query_delegations, ValidatorInfo};
+use super::tx::search_txs;
+use bot_library::shared::config::CONFIG;
+use bot_library::shared::vote_preferences::{read_preferences, VotePreference, VOTE_PREFERENCES_DIR};
+use crate::store::HistoryStore;
+
+pub const PARAM_CHANGE_PROPOSALS_TREE: &str = "param_change_proposals";
+pub const COMMUNITY_POOL_SPEND_PROPOSALS_TREE: &str = "community_pool_spend_proposals";
+pub const VALIDATOR_VOTES_TREE: &str = "validator_votes";
+
+/// chain -> id of the latest finished proposal the backfill has looked at.
+const VALIDATOR_VOTES_BACKFILL_TREE: &str = "validator_votes_backfill";
+
+const MSG_VOTE_WEIGHTED: &str = "/cosmos.gov.v1beta1.MsgVoteWeighted";
+/// Finished proposals whose validator votes are recovered from the vote transactions per refresh, oldest first.
+const BACKFILL_PROPOSALS_PER_RUN: usize = 3;
+
+const PARAMETER_CHANGE_PROPOSAL_TYPE_URL: &str = "/cosmos.params.v1beta1.ParameterChangeProposal";
+const COMMUNITY_POOL_SPEND_PROPOSAL_TYPE_URL: &str = "/cosmos.distribution.v1beta1.CommunityPoolSpendProposal";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ParamChange {
+    pub subspace: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ParamChangeProposalInfo {
+    pub blockchain: String,
+    pub proposal_id: u64,
+    pub title: String,
+    pub status: String,
+    pub voting_end_time: i64,
+    pub changes: Vec<ParamChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommunityPoolSpendProposalInfo {
+    pub blockchain: String,
+    pub proposal_id: u64,
+    pub title: String,
+    pub status: String,
+    pub recipient: String,
+    /// denom -> amount
+    pub amount: Vec<(String, String)>,
+}
+
+fn proposal_status(proposal: &Proposal) -> String {
+    format!("{:?}", ProposalStatus::from_i32(proposal.status).unwrap_or(ProposalStatus::Unspecified))
+}
+
+pub fn proposal_key(blockchain: &str, proposal_id: u64) -> String {
+    format!("{}_{}", blockchain, proposal_id)
+}
+
+/// True if the key is a proposal_key of exactly this chain, "osmosis" does not own "osmosis_testnet_1".
+pub fn is_proposal_key_of(key: &str, blockchain: &str) -> bool {
+    key.strip_prefix(blockchain)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .map(|id| id.parse::<u64>().is_ok())
+        .unwrap_or(false)
+}
+
+/// Removes the proposals of the chain that are not listed in keep.
+pub fn retain_proposals(history_store: &HistoryStore, tree: &str, blockchain: &str, keep: &[String]) -> anyhow::Result<()> {
+    history_store.retain_matching(tree, blockchain, |k| is_proposal_key_of(k, blockchain), keep)
+}
+
+pub async fn query_proposals(blockchain: &SupportedBlockchain, status: ProposalStatus, limit: Option<u64>) -> anyhow::Result<Vec<Proposal>> {
+    query_proposals_after(blockchain, status, limit, 0).await
+}
+
+/// Like query_proposals, but only the proposals with an id above `after`, the pages (newest first) after them are not fetched.
+async fn query_proposals_after(blockchain: &SupportedBlockchain, status: ProposalStatus, limit: Option<u64>, after: u64) -> anyhow::Result<Vec<Proposal>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    let mut proposals: Vec<Proposal> = Vec::new();
+    let mut next_key: Vec<u8> = Vec::new();
+    loop {
+        let res = client
+            .proposals(QueryProposalsRequest {
+                proposal_status: status as i32,
+                voter: "".to_string(),
+                depositor: "".to_string(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    offset: 0,
+                    limit: limit.unwrap_or(100),
+                    count_total: false,
+                    reverse: true,
+                }),
+            })
+            .await?
+            .into_inner();
+        let reached_after = res.proposals.iter().any(|p| p.proposal_id <= after);
+        proposals.extend(res.proposals.into_iter().filter(|p| p.proposal_id > after));
+        next_key = res.pagination.map(|p| p.next_key).unwrap_or_default();
+        if next_key.is_empty() || limit.is_some() || reached_after {
+            break;
+        }
+    }
+    Ok(proposals)
+}
+
+fn param_change_proposal_info(blockchain: &str, proposal: &Proposal) -> Option<ParamChangeProposalInfo> {
+    let content = proposal.content.as_ref()?;
+    if content.type_url != PARAMETER_CHANGE_PROPOSAL_TYPE_URL {
+        return None;
+    }
+    let decoded = ParameterChangeProposal::decode(content.value.as_slice()).ok()?;
+    Some(ParamChangeProposalInfo {
+        blockchain: blockchain.to_string(),
+        proposal_id: proposal.proposal_id,
+        title: decoded.title,
+        status: proposal_status(proposal),
+        voting_end_time: proposal.voting_end_time.as_ref().map(|t| t.seconds).unwrap_or(0),
+        changes: decoded
+            .changes
+            .into_iter()
+            .map(|c| ParamChange {
+                subspace: c.subspace,
+                key: c.key,
+                value: c.value,
+            })
+            .collect(),
+    })
+}
+
+/// Stores the parameter change proposals currently in voting period and the latest passed ones.
+pub async fn fetch_param_change_proposals(blockchain: SupportedBlockchain, history_store: HistoryStore, key: String) -> anyhow::Result<TaskResult> {
+    let mut list_of_keys_modified = vec![key];
+    let voting = query_proposals(&blockchain, ProposalStatus::VotingPeriod, None).await?;
+    let passed = query_proposals(&blockchain, ProposalStatus::Passed, Some(50)).await?;
+    for proposal in voting.iter().chain(passed.iter()) {
+        if let Some(info) = param_change_proposal_info(&blockchain.name, proposal) {
+            let proposal_key = proposal_key(&blockchain.name, info.proposal_id);
+            history_store.insert(PARAM_CHANGE_PROPOSALS_TREE, &proposal_key, &info)?;
+            list_of_keys_modified.push(proposal_key);
+        }
+    }
+    // proposals that left the queried states (e.g. rejected) are no longer relevant.
+    retain_proposals(&history_store, PARAM_CHANGE_PROPOSALS_TREE, &blockchain.name, &list_of_keys_modified)?;
+    Ok(TaskResult { list_of_keys_modified })
+}
+
+fn community_pool_spend_proposal_info(blockchain: &str, proposal: &Proposal) -> Option<CommunityPoolSpendProposalInfo> {
+    let content = proposal.content.as_ref()?;
+    if content.type_url != COMMUNITY_POOL_SPEND_PROPOSAL_TYPE_URL {
+        return None;
+    }
+    let decoded = CommunityPoolSpendProposal::decode(content.value.as_slice()).ok()?;
+    Some(CommunityPoolSpendProposalInfo {
+        blockchain: blockchain.to_string(),
+        proposal_id: proposal.proposal_id,
+        title: decoded.title,
+        status: proposal_status(proposal),
+        recipient: decoded.recipient,
+        amount: decoded.amount.into_iter().map(|c| (c.denom, c.amount)).collect(),
+    })
+}
+
+/// Stores the open (deposit and voting period) and all passed community pool spend proposals.
+pub async fn fetch_community_pool_spend_proposals(blockchain: SupportedBlockchain, history_store: HistoryStore, key: String) -> anyhow::Result<TaskResult> {
+    let mut list_of_keys_modified = vec![key];
+    let deposit = query_proposals(&blockchain, ProposalStatus::DepositPeriod, None).await?;
+    let voting = query_proposals(&blockchain, ProposalStatus::VotingPeriod, None).await?;
+    // all passed proposals, the passed spend total covers the whole history of the chain.
+    let passed = query_proposals(&blockchain, ProposalStatus::Passed, None).await?;
+    for proposal in deposit.iter().chain(voting.iter()).chain(passed.iter()) {
+        if let Some(info) = community_pool_spend_proposal_info(&blockchain.name, proposal) {
+            let proposal_key = proposal_key(&blockchain.name, info.proposal_id);
+            history_store.insert(COMMUNITY_POOL_SPEND_PROPOSALS_TREE, &proposal_key, &info)?;
+            list_of_keys_modified.push(proposal_key);
+        }
+    }
+    // proposals that left the queried states (e.g. rejected) are no longer relevant.
+    retain_proposals(&history_store, COMMUNITY_POOL_SPEND_PROPOSALS_TREE, &blockchain.name, &list_of_keys_modified)?;
+    Ok(TaskResult { list_of_keys_modified })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidatorVote {
+    pub validator: ValidatorInfo,
+    /// share of the bonded voting power in percent
+    pub voting_power: String,
+    /// None if the validator did not vote (yet)
+    pub option: Option<String>,
+    /// block time of the vote transaction, None if it was not found (e.g. pruned or cast via authz)
+    #[serde(default)]
+    pub voted_at: Option<i64>,
+}
+
+/// A delegator who stated a preferred vote option, together with the validators they delegate to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DelegatorPreference {
+    pub preference: VotePreference,
+    /// operator addresses
+    pub validators: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProposalValidatorVotes {
+    pub blockchain: String,
+    pub proposal_id: u64,
+    pub voting_start_time: i64,
+    pub voting_end_time: i64,
+    pub votes: Vec<ValidatorVote>,
+    /// the winning option of the final tally, set once the voting period ended
+    #[serde(default)]
+    pub final_majority: Option<String>,
+    #[serde(default)]
+    pub delegators: Vec<DelegatorPreference>,
+}
+
+pub fn vote_option_to_string(option: i32) -> String {
+    match VoteOption::from_i32(option).unwrap_or(VoteOption::Unspecified) {
+        VoteOption::Yes => "Yes",
+        VoteOption::Abstain => "Abstain",
+        VoteOption::No => "No",
+        VoteOption::NoWithVeto => "NoWithVeto",
+        VoteOption::Unspecified => "Unspecified",
+    }
+    .to_string()
+}
+
+fn majority_vote_option(vote: &Vote) -> i32 {
+    // weighted votes: the option with the highest weight is reported.
+    let weighted = vote
+        .options
+        .iter()
+        .max_by(|a, b| {
+            let a = Decimal::from_str(&a.weight).unwrap_or_default();
+            let b = Decimal::from_str(&b.weight).unwrap_or_default();
+            a.cmp(&b)
+        })
+        .map(|o| o.option);
+    #[allow(deprecated)]
+    weighted.unwrap_or(vote.option)
+}
+
+fn vote_option(vote: &Vote) -> String {
+    vote_option_to_string(majority_vote_option(vote))
+}
+
+/// Returns the vote of the voter, None if the voter did not vote.
+/// Any other error (timeouts, connection failures, ..) is returned, it does not mean the voter did not vote.
+async fn lookup_vote(client: &mut QueryClient<Channel>, proposal_id: u64, voter: &str) -> anyhow::Result<Option<Vote>> {
+    match client
+        .vote(QueryVoteRequest {
+            proposal_id,
+            voter: voter.to_string(),
+        })
+        .await
+    {
+        Ok(res) => Ok(res.into_inner().vote),
+        Err(status) if status.code() == Code::NotFound => Ok(None),
+        // the SDK answers votes that do not exist with InvalidArgument "voter: .. not found for proposal: .."
+        Err(status) if status.code() == Code::InvalidArgument && status.message().contains("not found") => Ok(None),
+        Err(status) => Err(anyhow::anyhow!("Error: Failed to query the vote of {} on proposal {}: {}", voter, proposal_id, status)),
+    }
+}
+
+/// Returns the (majority) vote option of the voter, None if the voter did not vote.
+pub async fn query_vote(blockchain: &SupportedBlockchain, proposal_id: u64, voter: &str) -> anyhow::Result<Option<i32>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    Ok(lookup_vote(&mut client, proposal_id, voter).await?.map(|v| majority_vote_option(&v)))
+}
+
+pub fn proposal_type(proposal: &Proposal) -> String {
+    proposal.content.as_ref().map(|c| c.type_url.to_owned()).unwrap_or_default()
+}
+
+pub fn proposal_title(proposal: &Proposal) -> String {
+    // all v1beta1 proposal contents start with title and description, unknown fields are skipped.
+    proposal
+        .content
+        .as_ref()
+        .and_then(|c| TextProposal::decode(c.value.as_slice()).ok())
+        .map(|t| t.title)
+        .unwrap_or_default()
+}
+
+pub fn msg_vote(proposal_id: u64, voter: &str, option: i32) -> Any {
+    Any {
+        type_url: MSG_VOTE.to_string(),
+        value: MsgVote {
+            proposal_id,
+            voter: voter.to_string(),
+            option,
+        }
+        .encode_to_vec(),
+    }
+}
+
+fn tally_majority(tally: &TallyResult) -> String {
+    let options = [
+        (VoteOption::Yes as i32, &tally.yes),
+        (VoteOption::Abstain as i32, &tally.abstain),
+        (VoteOption::No as i32, &tally.no),
+        (VoteOption::NoWithVeto as i32, &tally.no_with_veto),
+    ];
+    let (option, _) = options
+        .iter()
+        .max_by_key(|(_, count)| Decimal::from_str(count).unwrap_or_default())
+        .unwrap();
+    vote_option_to_string(*option)
+}
+
+/// Sets the final majority of stored proposals whose voting period ended.
+async fn finalize_validator_votes(blockchain: &SupportedBlockchain, history_store: &HistoryStore) -> anyhow::Result<Vec<String>> {
+    let mut keys_modified = Vec::new();
+    let now = Utc::now().timestamp();
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    for (k, mut record) in history_store.values::<ProposalValidatorVotes>(VALIDATOR_VOTES_TREE) {
+        if record.blockchain != blockchain.name || record.final_majority.is_some() || record.voting_end_time > now {
+            continue;
+        }
+        let proposal = client
+            .proposal(QueryProposalRequest { proposal_id: record.proposal_id })
+            .await?
+            .into_inner()
+            .proposal;
+        if let Some(tally) = proposal.and_then(|p| p.final_tally_result) {
+            record.final_majority = Some(tally_majority(&tally));
+            history_store.insert(VALIDATOR_VOTES_TREE, &k, &record)?;
+            keys_modified.push(k);
+        }
+    }
+    Ok(keys_modified)
+}
+
+/// (option, block time) of the latest vote of each voter, found in the vote transactions of the proposal.
+/// Votes are deleted from the state once the proposal is tallied, afterwards only the transactions tell who voted.
+/// With a voter, only transactions sent by the voter are searched (votes via authz are not found).
+pub async fn query_vote_txs(blockchain: &SupportedBlockchain, proposal_id: u64, voter: Option<&str>) -> anyhow::Result<HashMap<String, (String, i64)>> {
+    let mut events = vec![format!("proposal_vote.proposal_id='{}'", proposal_id)];
+    if let Some(voter) = voter {
+        events.push(format!("message.sender='{}'", voter));
+    }
+    let mut votes: HashMap<String, (String, i64)> = HashMap::new();
+    // oldest first, a later vote replaces the earlier one.
+    for tx in search_txs(blockchain, events, None).await? {
+        let timestamp = tx.unix_timestamp().unwrap_or(0);
+        for message in tx.messages.iter() {
+            #[allow(deprecated)]
+            let vote = match message.type_url.as_str() {
+                MSG_VOTE => {
+                    let msg = ProtoMsgVote::decode(message.value.as_slice())?;
+                    Vote { proposal_id: msg.proposal_id, voter: msg.voter, option: msg.option, options: Vec::new() }
+                }
+                MSG_VOTE_WEIGHTED => {
+                    let msg = MsgVoteWeighted::decode(message.value.as_slice())?;
+                    Vote { proposal_id: msg.proposal_id, voter: msg.voter, option: 0, options: msg.options }
+                }
+                _ => {
+                    continue;
+                }
+            };
+            if vote.proposal_id == proposal_id {
+                votes.insert(vote.voter.to_owned(), (vote_option(&vote), timestamp));
+            }
+        }
+    }
+    Ok(votes)
+}
+
+fn voting_power(validator: &ValidatorInfo, total_tokens: Decimal) -> String {
+    if total_tokens.is_zero() {
+        Decimal::ZERO.to_string()
+    } else {
+        (validator.tokens() / total_tokens * Decimal::ONE_HUNDRED).round_dp(2).to_string()
+    }
+}
+
+/// Recovers the validator votes of finished proposals the store does not know yet from their vote transactions.
+/// The current validator set stands in for the set at the time of the proposal. Proposals whose transactions
+/// are no longer available (pruned nodes) are stored without votes. The id of the latest proposal looked at is
+/// kept as cursor, only newer finished proposals are queried on the next refresh.
+async fn backfill_validator_votes(blockchain: &SupportedBlockchain, history_store: &HistoryStore, validators: &[ValidatorInfo], total_tokens: Decimal) -> anyhow::Result<Vec<String>> {
+    let mut keys_modified = Vec::new();
+    let cursor: u64 = history_store.get(VALIDATOR_VOTES_BACKFILL_TREE, &blockchain.name)?.unwrap_or(0);
+    let mut finished: Vec<Proposal> = Vec::new();
+    for status in [ProposalStatus::Passed, ProposalStatus::Rejected, ProposalStatus::Failed] {
+        finished.append(&mut query_proposals_after(blockchain, status, None, cursor).await?);
+    }
+    finished.sort_by_key(|p| p.proposal_id);
+
+    let mut backfilled = 0;
+    for proposal in finished {
+        if backfilled >= BACKFILL_PROPOSALS_PER_RUN {
+            break;
+        }
+        let proposal_key = proposal_key(&blockchain.name, proposal.proposal_id);
+        if history_store.get::<ProposalValidatorVotes>(VALIDATOR_VOTES_TREE, &proposal_key)?.is_some() {
+            history_store.insert(VALIDATOR_VOTES_BACKFILL_TREE, &blockchain.name, &proposal.proposal_id)?;
+            continue;
+        }
+        backfilled += 1;
+        let vote_txs = query_vote_txs(blockchain, proposal.proposal_id, None).await?;
+        let votes: Vec<ValidatorVote> = if vote_txs.is_empty() {
+            Vec::new()
+        } else {
+            validators
+                .iter()
+                .map(|validator| {
+                    let vote = vote_txs.get(&validator.account_address);
+                    ValidatorVote {
+                        validator: validator.clone(),
+                        voting_power: voting_power(validator, total_tokens),
+                        option: vote.map(|(option, _)| option.to_owned()),
+                        voted_at: vote.map(|(_, timestamp)| *timestamp),
+                    }
+                })
+                .collect()
+        };
+        history_store.insert(VALIDATOR_VOTES_TREE, &proposal_key, &ProposalValidatorVotes {
+            blockchain: blockchain.name.to_owned(),
+            proposal_id: proposal.proposal_id,
+            voting_start_time: proposal.voting_start_time.as_ref().map(|t| t.seconds).unwrap_or(0),
+            voting_end_time: proposal.voting_end_time.as_ref().map(|t| t.seconds).unwrap_or(0),
+            votes,
+            final_majority: proposal.final_tally_result.as_ref().map(tally_majority),
+            delegators: Vec::new(),
+        })?;
+        history_store.insert(VALIDATOR_VOTES_BACKFILL_TREE, &blockchain.name, &proposal.proposal_id)?;
+        keys_modified.push(proposal_key);
+    }
+    Ok(keys_modified)
+}
+
+/// Stores the votes of all bonded validators on every proposal in voting period,
+/// and the delegations of the delegators that stated a vote preference for it.
+/// Records of finished proposals are kept, they make up the governance history of the validators,
+/// finished proposals from before the bot ran are backfilled.
+pub async fn fetch_validator_votes(blockchain: SupportedBlockchain, history_store: HistoryStore, key: String) -> anyhow::Result<TaskResult> {
+    let mut list_of_keys_modified = vec![key];
+    let validators = query_bonded_validators(&blockchain).await?;
+    let total_tokens: Decimal = validators.iter().map(|v| v.tokens()).sum();
+    let voting = query_proposals(&blockchain, ProposalStatus::VotingPeriod, None).await?;
+    let preferences = read_preferences(&CONFIG.data_path(VOTE_PREFERENCES_DIR), &blockchain.name);
+
+    let mut client = QueryClient::new(channel(&blockchain).await?);
+    for proposal in voting {
+        let proposal_key = proposal_key(&blockchain.name, proposal.proposal_id);
+        let previous: Option<ProposalValidatorVotes> = history_store.get(VALIDATOR_VOTES_TREE, &proposal_key)?;
+        let mut delegators: Vec<DelegatorPreference> = Vec::new();
+        for preference in preferences.iter().filter(|p| p.proposal_id == proposal.proposal_id) {
+            delegators.push(DelegatorPreference {
+                validators: query_delegations(&blockchain, &preference.delegator).await?,
+                preference: preference.clone(),
+            });
+        }
+        let mut votes: Vec<ValidatorVote> = Vec::new();
+        for validator in validators.iter() {
+            let option = lookup_vote(&mut client, proposal.proposal_id, &validator.account_address)
+                .await?
+                .map(|v| vote_option(&v));
+            let previous_vote = previous
+                .as_ref()
+                .and_then(|p| p.votes.iter().find(|v| v.validator.operator_address == validator.operator_address));
+            // the vote time is looked up once, when the vote shows up.
+            let voted_at = match (&option, previous_vote.and_then(|v| v.voted_at)) {
+                (Some(_), Some(voted_at)) => Some(voted_at),
+                (Some(_), None) => query_vote_txs(&blockchain, proposal.proposal_id, Some(&validator.account_address))
+                    .await?
+                    .get(&validator.account_address)
+                    .map(|(_, timestamp)| *timestamp),
+                (None, _) => None,
+            };
+            votes.push(ValidatorVote {
+                validator: validator.clone(),
+                voting_power: voting_power(validator, total_tokens),
+                option,
+                voted_at,
+            });
+        }
+        history_store.insert(VALIDATOR_VOTES_TREE, &proposal_key, &ProposalValidatorVotes {
+            blockchain: blockchain.name.to_owned(),
+            proposal_id: proposal.proposal_id,
+            voting_start_time: proposal.voting_start_time.as_ref().map(|t| t.seconds).unwrap_or(0),
+            voting_end_time: proposal.voting_end_time.as_ref().map(|t| t.seconds).unwrap_or(0),
+            votes,
+            final_majority: None,
+            delegators,
+        })?;
+        list_of_keys_modified.push(proposal_key);
+    }
+    list_of_keys_modified.append(&mut finalize_validator_votes(&blockchain, &history_store).await?);
+    list_of_keys_modified.append(&mut backfill_validator_votes(&blockchain, &history_store, &validators, total_tokens).await?);
+    Ok(TaskResult { list_of_keys_modified })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn proposal_keys_belong_to_exactly_one_chain() {
+        assert!(is_proposal_key_of(&proposal_key("osmosis", 42), "osmosis"));
+        assert!(!is_proposal_key_of(&proposal_key("osmosis_testnet", 42), "osmosis"));
+        assert!(is_proposal_key_of(&proposal_key("osmosis_testnet", 42), "osmosis_testnet"));
+        assert!(!is_proposal_key_of("osmosis2_42", "osmosis"));
+    }
+}
@@ -0,0 +1,42 @@
+pub mod authz;
+pub mod bank;
+pub mod distribution;
+pub mod gov;
+pub mod params;
+pub mod staking;
+pub mod tx;
+
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use tonic::transport::Channel;
+
+// gRPC queries that are not (yet) provided by cosmos-rust-interface.
+// Results are written into the HistoryStore, the task itself only returns the modified keys.
+
+pub async fn channel(blockchain: &SupportedBlockchain) -> anyhow::Result<Channel> {
+    blockchain.channel().await
+}
+
+/// sdk.Dec values are returned by gRPC as integer strings with 18 decimal places.
+pub fn dec_to_string(dec: &str) -> String {
+    let dec = dec.trim_start_matches('0');
+    let padded = format!("{:0>19}", dec);
+    let (int, frac) = padded.split_at(padded.len() - 18);
+    let frac = frac.trim_end_matches('0');
+    if frac.is_empty() {
+        int.to_string()
+    } else {
+        format!("{}.{}", int, frac)
+    }
+}
+
+pub fn dec_bytes_to_string(dec: &[u8]) -> String {
+    dec_to_string(&String::from_utf8_lossy(dec))
+}
+
+/// Durations are compared in nanoseconds, the encoding used by parameter change proposals.
+pub fn duration_to_string(duration: &Option<prost_types::Duration>) -> String {
+    match duration {
+        Some(d) => (d.seconds as i128 * 1_000_000_000 + d.nanos as i128).to_string(),
+        None => "".to_string(),
+    }
+}
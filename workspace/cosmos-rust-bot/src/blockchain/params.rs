@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::utils::response::TaskResult;
+use cosmos_sdk_proto::cosmos::{distribution, gov, mint, slashing, staking};
+use serde_json::json;
+
+use super::{channel, dec_bytes_to_string, dec_to_string, duration_to_string};
+use crate::store::HistoryStore;
+
+pub const PARAMS_TREE: &str = "params";
+
+pub const PARAM_MODULES: [&str; 5] = [
+    "gov",
+    "staking",
+    "slashing",
+    "mint",
+    "distribution",
+];
+
+/// Flattened on-chain params of one module, field name -> value.
+pub type ModuleParams = BTreeMap<String, String>;
+
+pub fn params_key(blockchain: &str, module: &str) -> String {
+    format!("{}_{}", blockchain, module)
+}
+
+/// Adds the params of one gov params query to `params`.
+/// Every response contains all three params structs (gogoproto non-nullable fields are always encoded),
+/// only the one of the queried `params_type` is filled in, the others are defaults.
+fn insert_gov_params(params: &mut ModuleParams, params_type: &str, res: gov::v1beta1::QueryParamsResponse) -> anyhow::Result<()> {
+    match params_type {
+        "voting" => {
+            let voting = res.voting_params.ok_or(anyhow::anyhow!("Error: gov voting params missing"))?;
+            params.insert("voting_period".to_string(), duration_to_string(&voting.voting_period));
+        }
+        "tallying" => {
+            let tally = res.tally_params.ok_or(anyhow::anyhow!("Error: gov tally params missing"))?;
+            params.insert("quorum".to_string(), dec_bytes_to_string(&tally.quorum));
+            params.insert("threshold".to_string(), dec_bytes_to_string(&tally.threshold));
+            params.insert("veto_threshold".to_string(), dec_bytes_to_string(&tally.veto_threshold));
+        }
+        "deposit" => {
+            let deposit = res.deposit_params.ok_or(anyhow::anyhow!("Error: gov deposit params missing"))?;
+            let min_deposit = deposit.min_deposit.iter().map(|c| json!({"denom": c.denom, "amount": c.amount})).collect::<Vec<serde_json::Value>>();
+            params.insert("min_deposit".to_string(), serde_json::to_string(&min_deposit)?);
+            params.insert("max_deposit_period".to_string(), duration_to_string(&deposit.max_deposit_period));
+        }
+        _ => {
+            return Err(anyhow::anyhow!("Error: Unknown gov params type: {}", params_type));
+        }
+    }
+    Ok(())
+}
+
+const GOV_PARAMS_TYPES: [&str; 3] = ["voting", "tallying", "deposit"];
+
+async fn query_gov_params(blockchain: &SupportedBlockchain) -> anyhow::Result<ModuleParams> {
+    let mut client = gov::v1beta1::query_client::QueryClient::new(channel(blockchain).await?);
+    let mut params = ModuleParams::new();
+    for params_type in GOV_PARAMS_TYPES {
+        let res = client
+            .params(gov::v1beta1::QueryParamsRequest { params_type: params_type.to_string() })
+            .await?
+            .into_inner();
+        insert_gov_params(&mut params, params_type, res)?;
+    }
+    Ok(params)
+}
+
+async fn query_staking_params(blockchain: &SupportedBlockchain) -> anyhow::Result<ModuleParams> {
+    let mut client = staking::v1beta1::query_client::QueryClient::new(channel(blockchain).await?);
+    let res = client.params(staking::v1beta1::QueryParamsRequest {}).await?.into_inner();
+    let p = res.params.ok_or(anyhow::anyhow!("Error: staking params missing"))?;
+    let mut params = ModuleParams::new();
+    params.insert("unbonding_time".to_string(), duration_to_string(&p.unbonding_time));
+    params.insert("max_validators".to_string(), p.max_validators.to_string());
+    params.insert("max_entries".to_string(), p.max_entries.to_string());
+    params.insert("historical_entries".to_string(), p.historical_entries.to_string());
+    params.insert("bond_denom".to_string(), p.bond_denom);
+    params.insert("min_commission_rate".to_string(), dec_to_string(&p.min_commission_rate));
+    Ok(params)
+}
+
+async fn query_slashing_params(blockchain: &SupportedBlockchain) -> anyhow::Result<ModuleParams> {
+    let mut client = slashing::v1beta1::query_client::QueryClient::new(channel(blockchain).await?);
+    let res = client.params(slashing::v1beta1::QueryParamsRequest {}).await?.into_inner();
+    let p = res.params.ok_or(anyhow::anyhow!("Error: slashing params missing"))?;
+    let mut params = ModuleParams::new();
+    params.insert("signed_blocks_window".to_string(), p.signed_blocks_window.to_string());
+    params.insert("min_signed_per_window".to_string(), dec_bytes_to_string(&p.min_signed_per_window));
+    params.insert("downtime_jail_duration".to_string(), duration_to_string(&p.downtime_jail_duration));
+    params.insert("slash_fraction_double_sign".to_string(), dec_bytes_to_string(&p.slash_fraction_double_sign));
+    params.insert("slash_fraction_downtime".to_string(), dec_bytes_to_string(&p.slash_fraction_downtime));
+    Ok(params)
+}
+
+async fn query_mint_params(blockchain: &SupportedBlockchain) -> anyhow::Result<ModuleParams> {
+    let mut client = mint::v1beta1::query_client::QueryClient::new(channel(blockchain).await?);
+    let res = client.params(mint::v1beta1::QueryParamsRequest {}).await?.into_inner();
+    let p = res.params.ok_or(anyhow::anyhow!("Error: mint params missing"))?;
+    let mut params = ModuleParams::new();
+    params.insert("mint_denom".to_string(), p.mint_denom);
+    params.insert("inflation_rate_change".to_string(), dec_to_string(&p.inflation_rate_change));
+    params.insert("inflation_max".to_string(), dec_to_string(&p.inflation_max));
+    params.insert("inflation_min".to_string(), dec_to_string(&p.inflation_min));
+    params.insert("goal_bonded".to_string(), dec_to_string(&p.goal_bonded));
+    params.insert("blocks_per_year".to_string(), p.blocks_per_year.to_string());
+    Ok(params)
+}
+
+async fn query_distribution_params(blockchain: &SupportedBlockchain) -> anyhow::Result<ModuleParams> {
+    let mut client = distribution::v1beta1::query_client::QueryClient::new(channel(blockchain).await?);
+    let res = client.params(distribution::v1beta1::QueryParamsRequest {}).await?.into_inner();
+    let p = res.params.ok_or(anyhow::anyhow!("Error: distribution params missing"))?;
+    let mut params = ModuleParams::new();
+    params.insert("community_tax".to_string(), dec_to_string(&p.community_tax));
+    params.insert("base_proposer_reward".to_string(), dec_to_string(&p.base_proposer_reward));
+    params.insert("bonus_proposer_reward".to_string(), dec_to_string(&p.bonus_proposer_reward));
+    params.insert("withdraw_addr_enabled".to_string(), p.withdraw_addr_enabled.to_string());
+    Ok(params)
+}
+
+pub async fn fetch_module_params(blockchain: SupportedBlockchain, module: String, history_store: HistoryStore, key: String) -> anyhow::Result<TaskResult> {
+    let params = match module.as_str() {
+        "gov" => query_gov_params(&blockchain).await?,
+        "staking" => query_staking_params(&blockchain).await?,
+        "slashing" => query_slashing_params(&blockchain).await?,
+        "mint" => query_mint_params(&blockchain).await?,
+        "distribution" => query_distribution_params(&blockchain).await?,
+        _ => {
+            return Err(anyhow::anyhow!("Error: Unknown params module: {}", module));
+        }
+    };
+    let params_key = params_key(&blockchain.name, &module);
+    history_store.push(PARAMS_TREE, &params_key, &params)?;
+    Ok(TaskResult {
+        list_of_keys_modified: vec![key, params_key],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmos_sdk_proto::cosmos::base::v1beta1::Coin;
+    use gov::v1beta1::{DepositParams, QueryParamsResponse, TallyParams, VotingParams};
+
+    /// The responses of a node: every params struct is present, only the queried one has values.
+    fn gov_params_responses() -> Vec<(&'static str, QueryParamsResponse)> {
+        let defaults = QueryParamsResponse {
+            voting_params: Some(VotingParams::default()),
+            deposit_params: Some(DepositParams::default()),
+            tally_params: Some(TallyParams::default()),
+        };
+        vec![
+            ("voting", QueryParamsResponse {
+                voting_params: Some(VotingParams { voting_period: Some(prost_types::Duration { seconds: 1209600, nanos: 0 }) }),
+                ..defaults.clone()
+            }),
+            ("tallying", QueryParamsResponse {
+                tally_params: Some(TallyParams {
+                    quorum: b"400000000000000000".to_vec(),
+                    threshold: b"500000000000000000".to_vec(),
+                    veto_threshold: b"334000000000000000".to_vec(),
+                }),
+                ..defaults.clone()
+            }),
+            ("deposit", QueryParamsResponse {
+                deposit_params: Some(DepositParams {
+                    min_deposit: vec![Coin { denom: "uatom".to_string(), amount: "250000000".to_string() }],
+                    max_deposit_period: Some(prost_types::Duration { seconds: 1209600, nanos: 0 }),
+                }),
+                ..defaults
+            }),
+        ]
+    }
+
+    fn gov_params() -> ModuleParams {
+        let mut params = ModuleParams::new();
+        for (params_type, res) in gov_params_responses() {
+            insert_gov_params(&mut params, params_type, res).unwrap();
+        }
+        params
+    }
+
+    #[test]
+    fn default_filled_responses_do_not_overwrite_the_queried_params() {
+        let params = gov_params();
+        assert_eq!(params.get("voting_period").unwrap(), "1209600000000000");
+        assert_eq!(params.get("quorum").unwrap(), "0.4");
+        assert_eq!(params.get("threshold").unwrap(), "0.5");
+        assert_eq!(params.get("veto_threshold").unwrap(), "0.334");
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(params.get("min_deposit").unwrap()).unwrap(),
+            json!([{"denom": "uatom", "amount": "250000000"}])
+        );
+        assert_eq!(params.get("max_deposit_period").unwrap(), "1209600000000000");
+    }
+}
@@ -0,0 +1,249 @@
+use bot_library::shared::WatchedAddress;
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
+use cosmos_rust_interface::utils::response::TaskResult;
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmos::staking::v1beta1::{
+    query_client::QueryClient, QueryDelegatorDelegationsRequest, QueryDelegatorUnbondingDelegationsRequest, QueryRedelegationsRequest, QueryValidatorsRequest,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use super::channel;
+use crate::store::HistoryStore;
+
+pub const STAKING_SCHEDULE_TREE: &str = "staking_schedule";
+
+const BOND_STATUS_BONDED: &str = "BOND_STATUS_BONDED";
+/// Completed entries are no longer returned by the chain, they are kept this long to report them.
+const COMPLETED_RETENTION: i64 = 60 * 60 * 24 * 7;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidatorInfo {
+    pub operator_address: String,
+    pub account_address: String,
+    pub moniker: String,
+    pub tokens: String,
+}
+
+impl ValidatorInfo {
+    pub fn tokens(&self) -> Decimal {
+        Decimal::from_str(&self.tokens).unwrap_or(Decimal::ZERO)
+    }
+}
+
+/// The account address of a validator shares the bytes of its operator address, e.g. osmovaloper.. -> osmo..
+pub fn operator_to_account_address(operator_address: &str) -> anyhow::Result<String> {
+    let (hrp, data, variant) = bech32::decode(operator_address)?;
+    let prefix = hrp.strip_suffix("valoper").ok_or(anyhow::anyhow!("Error: Not an operator address: {}", operator_address))?;
+    Ok(bech32::encode(prefix, data, variant)?)
+}
+
+/// Returns the bonded validators, sorted by voting power (highest first).
+pub async fn query_bonded_validators(blockchain: &SupportedBlockchain) -> anyhow::Result<Vec<ValidatorInfo>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    let mut validators: Vec<ValidatorInfo> = Vec::new();
+    let mut next_key: Vec<u8> = Vec::new();
+    loop {
+        let res = client
+            .validators(QueryValidatorsRequest {
+                status: BOND_STATUS_BONDED.to_string(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    offset: 0,
+                    limit: 200,
+                    count_total: false,
+                    reverse: false,
+                }),
+            })
+            .await?
+            .into_inner();
+        for v in res.validators {
+            validators.push(ValidatorInfo {
+                account_address: operator_to_account_address(&v.operator_address)?,
+                operator_address: v.operator_address,
+                moniker: v.description.map(|d| d.moniker).unwrap_or_default(),
+                tokens: v.tokens,
+            });
+        }
+        next_key = res.pagination.map(|p| p.next_key).unwrap_or_default();
+        if next_key.is_empty() {
+            break;
+        }
+    }
+    validators.sort_by(|a, b| b.tokens().cmp(&a.tokens()));
+    Ok(validators)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UnbondingEntry {
+    pub validator: String,
+    pub balance: String,
+    pub completion_time: i64,
+}
+
+/// Until completion_time no further redelegation from validator_dst is possible.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RedelegationEntry {
+    pub validator_src: String,
+    pub validator_dst: String,
+    pub balance: String,
+    pub completion_time: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StakingSchedule {
+    pub blockchain: String,
+    pub address: String,
+    pub reminder_lead_time: i64,
+    pub unbondings: Vec<UnbondingEntry>,
+    pub redelegations: Vec<RedelegationEntry>,
+}
+
+/// Operator addresses of the validators the address delegates to.
+pub async fn query_delegations(blockchain: &SupportedBlockchain, address: &str) -> anyhow::Result<Vec<String>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    let mut validators: Vec<String> = Vec::new();
+    let mut next_key: Vec<u8> = Vec::new();
+    loop {
+        let res = client
+            .delegator_delegations(QueryDelegatorDelegationsRequest {
+                delegator_addr: address.to_string(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    offset: 0,
+                    limit: 100,
+                    count_total: false,
+                    reverse: false,
+                }),
+            })
+            .await?
+            .into_inner();
+        validators.extend(
+            res.delegation_responses
+                .into_iter()
+                .filter_map(|d| d.delegation)
+                .map(|d| d.validator_address),
+        );
+        next_key = res.pagination.map(|p| p.next_key).unwrap_or_default();
+        if next_key.is_empty() {
+            break;
+        }
+    }
+    Ok(validators)
+}
+
+pub async fn query_unbondings(blockchain: &SupportedBlockchain, address: &str) -> anyhow::Result<Vec<UnbondingEntry>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    let mut entries: Vec<UnbondingEntry> = Vec::new();
+    let mut next_key: Vec<u8> = Vec::new();
+    loop {
+        let res = client
+            .delegator_unbonding_delegations(QueryDelegatorUnbondingDelegationsRequest {
+                delegator_addr: address.to_string(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    offset: 0,
+                    limit: 100,
+                    count_total: false,
+                    reverse: false,
+                }),
+            })
+            .await?
+            .into_inner();
+        for unbonding in res.unbonding_responses {
+            for entry in unbonding.entries {
+                entries.push(UnbondingEntry {
+                    validator: unbonding.validator_address.to_owned(),
+                    balance: entry.balance,
+                    completion_time: entry.completion_time.map(|t| t.seconds).unwrap_or(0),
+                });
+            }
+        }
+        next_key = res.pagination.map(|p| p.next_key).unwrap_or_default();
+        if next_key.is_empty() {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+pub async fn query_redelegations(blockchain: &SupportedBlockchain, address: &str) -> anyhow::Result<Vec<RedelegationEntry>> {
+    let mut client = QueryClient::new(channel(blockchain).await?);
+    let mut entries: Vec<RedelegationEntry> = Vec::new();
+    let mut next_key: Vec<u8> = Vec::new();
+    loop {
+        let res = client
+            .redelegations(QueryRedelegationsRequest {
+                delegator_addr: address.to_string(),
+                src_validator_addr: "".to_string(),
+                dst_validator_addr: "".to_string(),
+                pagination: Some(PageRequest {
+                    key: next_key,
+                    offset: 0,
+                    limit: 100,
+                    count_total: false,
+                    reverse: false,
+                }),
+            })
+            .await?
+            .into_inner();
+        for response in res.redelegation_responses {
+            let redelegation = match response.redelegation {
+                Some(redelegation) => redelegation,
+                None => {
+                    continue;
+                }
+            };
+            for entry in response.entries {
+                entries.push(RedelegationEntry {
+                    validator_src: redelegation.validator_src_address.to_owned(),
+                    validator_dst: redelegation.validator_dst_address.to_owned(),
+                    balance: entry.balance,
+                    completion_time: entry.redelegation_entry.and_then(|e| e.completion_time).map(|t| t.seconds).unwrap_or(0),
+                });
+            }
+        }
+        next_key = res.pagination.map(|p| p.next_key).unwrap_or_default();
+        if next_key.is_empty() {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+/// Keeps the entries that completed recently, the chain drops them once they mature.
+fn keep_completed<T: PartialEq + Clone>(current: &mut Vec<T>, previous: &[T], completion_time: fn(&T) -> i64, now: i64) {
+    for entry in previous {
+        let t = completion_time(entry);
+        if t <= now && t > now - COMPLETED_RETENTION && !current.contains(entry) {
+            current.push(entry.clone());
+        }
+    }
+}
+
+/// Stores the unbonding and redelegation entries of the watched addresses on this chain.
+pub async fn fetch_staking_schedule(blockchain: SupportedBlockchain, watched: Vec<WatchedAddress>, history_store: HistoryStore, key: String) -> anyhow::Result<TaskResult> {
+    let now = Utc::now().timestamp();
+    let mut list_of_keys_modified = vec![key];
+    for watched in watched {
+        let schedule_key = format!("{}_{}", blockchain.name, watched.address);
+        let previous: Option<StakingSchedule> = history_store.get(STAKING_SCHEDULE_TREE, &schedule_key)?;
+        let mut unbondings = query_unbondings(&blockchain, &watched.address).await?;
+        let mut redelegations = query_redelegations(&blockchain, &watched.address).await?;
+        if let Some(previous) = previous {
+            keep_completed(&mut unbondings, &previous.unbondings, |e| e.completion_time, now);
+            keep_completed(&mut redelegations, &previous.redelegations, |e| e.completion_time, now);
+        }
+        history_store.insert(STAKING_SCHEDULE_TREE, &schedule_key, &StakingSchedule {
+            blockchain: blockchain.name.to_owned(),
+            address: watched.address.to_owned(),
+            reminder_lead_time: watched.reminder_lead_time,
+            unbondings,
+            redelegations,
+        })?;
+        list_of_keys_modified.push(schedule_key);
+    }
+    Ok(TaskResult { list_of_keys_modified })
+}
@@ -0,0 +1,194 @@
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::cosmos_rust_package::chrono::DateTime;
+use cosmos_sdk_proto::cosmos::auth::v1beta1::{query_client::QueryClient as AuthQueryClient, BaseAccount, QueryAccountRequest};
+use cosmos_sdk_proto::cosmos::authz::v1beta1::MsgExec as ProtoMsgExec;
+use cosmos_sdk_proto::cosmos::base::query::v1beta1::PageRequest;
+use cosmos_sdk_proto::cosmos::base::tendermint::v1beta1::{service_client::ServiceClient as TendermintServiceClient, GetLatestBlockRequest, GetNodeInfoRequest};
+use cosmos_sdk_proto::cosmos::tx::v1beta1::{service_client::ServiceClient as TxServiceClient, BroadcastMode, BroadcastTxRequest, GetTxsEventRequest, OrderBy, SimulateRequest};
+use cosmrs::tx::{Body, Fee, SignDoc, SignerInfo};
+use cosmrs::{Any, Coin};
+use prost::Message;
+use std::sync::Arc;
+
+use super::authz::MSG_EXEC;
+use super::channel;
+use crate::account::signer::Signer;
+use crate::account::wallet::DerivedAccount;
+
+// Transactions are built and signed with cosmrs, only the encoded bytes are passed to the gRPC clients.
+
+pub struct TxOptions {
+    pub fee_denom: String,
+    pub fee_amount: u64,
+    pub gas_limit: u64,
+    pub memo: String,
+}
+
+/// A transaction found by search_txs, the messages of a MsgExec are listed instead of the MsgExec.
+pub struct FoundTx {
+    pub txhash: String,
+    pub height: i64,
+    /// RFC 3339
+    pub timestamp: String,
+    pub memo: String,
+    pub messages: Vec<prost_types::Any>,
+}
+
+impl FoundTx {
+    pub fn unix_timestamp(&self) -> Option<i64> {
+        DateTime::parse_from_rfc3339(&self.timestamp).ok().map(|t| t.timestamp())
+    }
+}
+
+const TX_SEARCH_PAGE_SIZE: u64 = 100;
+
+/// The messages, with the messages executed by a MsgExec (authz) in place of the MsgExec.
+pub fn unwrap_messages(messages: Vec<prost_types::Any>) -> Vec<prost_types::Any> {
+    let mut unwrapped = Vec::new();
+    for message in messages {
+        match message.type_url.as_str() {
+            MSG_EXEC => match ProtoMsgExec::decode(message.value.as_slice()) {
+                Ok(exec) => unwrapped.append(&mut unwrap_messages(exec.msgs)),
+                Err(_) => unwrapped.push(message),
+            },
+            _ => unwrapped.push(message),
+        }
+    }
+    unwrapped
+}
+
+/// All transactions matching the events (e.g. "transfer.recipient='osmo1..'"), oldest first.
+/// min_height only returns transactions above that height.
+pub async fn search_txs(blockchain: &SupportedBlockchain, events: Vec<String>, min_height: Option<i64>) -> anyhow::Result<Vec<FoundTx>> {
+    let mut client = TxServiceClient::new(channel(blockchain).await?);
+    let mut events = events;
+    if let Some(min_height) = min_height {
+        events.push(format!("tx.height>{}", min_height));
+    }
+    let mut txs: Vec<FoundTx> = Vec::new();
+    let mut offset: u64 = 0;
+    loop {
+        let res = client
+            .get_txs_event(GetTxsEventRequest {
+                events: events.clone(),
+                pagination: Some(PageRequest {
+                    key: Vec::new(),
+                    offset,
+                    limit: TX_SEARCH_PAGE_SIZE,
+                    count_total: false,
+                    reverse: false,
+                }),
+                order_by: OrderBy::Asc as i32,
+                ..Default::default()
+            })
+            .await?
+            .into_inner();
+        let found = res.tx_responses.len() as u64;
+        for (tx, tx_response) in res.txs.into_iter().zip(res.tx_responses.into_iter()) {
+            let body = match tx.body {
+                Some(body) => body,
+                None => {
+                    continue;
+                }
+            };
+            txs.push(FoundTx {
+                txhash: tx_response.txhash,
+                height: tx_response.height,
+                timestamp: tx_response.timestamp,
+                memo: body.memo,
+                messages: unwrap_messages(body.messages),
+            });
+        }
+        if found < TX_SEARCH_PAGE_SIZE {
+            break;
+        }
+        offset += found;
+    }
+    Ok(txs)
+}
+
+pub struct BroadcastResult {
+    pub txhash: String,
+    pub code: u32,
+    pub raw_log: String,
+}
+
+pub async fn query_chain_id(blockchain: &SupportedBlockchain) -> anyhow::Result<String> {
+    let mut client = TendermintServiceClient::new(channel(blockchain).await?);
+    let res = client.get_node_info(GetNodeInfoRequest {}).await?.into_inner();
+    res.default_node_info
+        .map(|info| info.network)
+        .ok_or(anyhow::anyhow!("Error: Node info unavailable for {}", blockchain.name))
+}
+
+pub async fn query_latest_height(blockchain: &SupportedBlockchain) -> anyhow::Result<i64> {
+    let mut client = TendermintServiceClient::new(channel(blockchain).await?);
+    let res = client.get_latest_block(GetLatestBlockRequest {}).await?.into_inner();
+    res.block
+        .and_then(|block| block.header)
+        .map(|header| header.height)
+        .ok_or(anyhow::anyhow!("Error: Latest block unavailable for {}", blockchain.name))
+}
+
+/// Returns (account_number, sequence).
+pub async fn query_account(blockchain: &SupportedBlockchain, address: &str) -> anyhow::Result<(u64, u64)> {
+    let mut client = AuthQueryClient::new(channel(blockchain).await?);
+    let res = client
+        .account(QueryAccountRequest {
+            address: address.to_string(),
+        })
+        .await?
+        .into_inner();
+    let account = res.account.ok_or(anyhow::anyhow!("Error: Account not found: {}", address))?;
+    let account = BaseAccount::decode(account.value.as_slice())?;
+    Ok((account.account_number, account.sequence))
+}
+
+/// Returns the encoded and signed transaction.
+pub async fn sign_tx(blockchain: &SupportedBlockchain, signer: &Arc<dyn Signer>, account: &DerivedAccount, msgs: Vec<Any>, options: &TxOptions) -> anyhow::Result<Vec<u8>> {
+    let chain_id = query_chain_id(blockchain).await?;
+    let (account_number, sequence) = query_account(blockchain, &account.address).await?;
+
+    let body = Body::new(msgs, options.memo.to_owned(), 0u32);
+    let fee = Fee::from_amount_and_gas(
+        Coin {
+            denom: options.fee_denom.parse().map_err(|e| anyhow::anyhow!("Error: {}", e))?,
+            amount: options.fee_amount as u128,
+        },
+        options.gas_limit,
+    );
+    let auth_info = SignerInfo::single_direct(Some(signer.public_key(account.coin_type)?), sequence).auth_info(fee);
+    let sign_doc = SignDoc::new(
+        &body,
+        &auth_info,
+        &chain_id.parse().map_err(|e| anyhow::anyhow!("Error: {}", e))?,
+        account_number,
+    )
+    .map_err(|e| anyhow::anyhow!("Error: {}", e))?;
+    signer.sign(account.coin_type, sign_doc)?.to_bytes().map_err(|e| anyhow::anyhow!("Error: {}", e))
+}
+
+/// Returns the gas used by the transaction, nothing is broadcast.
+pub async fn simulate_tx(blockchain: &SupportedBlockchain, tx_bytes: Vec<u8>) -> anyhow::Result<u64> {
+    let mut client = TxServiceClient::new(channel(blockchain).await?);
+    #[allow(deprecated)]
+    let res = client.simulate(SimulateRequest { tx: None, tx_bytes }).await?.into_inner();
+    Ok(res.gas_info.map(|g| g.gas_used).unwrap_or(0))
+}
+
+pub async fn broadcast_tx(blockchain: &SupportedBlockchain, tx_bytes: Vec<u8>) -> anyhow::Result<BroadcastResult> {
+    let mut client = TxServiceClient::new(channel(blockchain).await?);
+    let res = client
+        .broadcast_tx(BroadcastTxRequest {
+            tx_bytes,
+            mode: BroadcastMode::Sync as i32,
+        })
+        .await?
+        .into_inner();
+    let res = res.tx_response.ok_or(anyhow::anyhow!("Error: No tx response"))?;
+    Ok(BroadcastResult {
+        txhash: res.txhash,
+        code: res.code,
+        raw_log: res.raw_log,
+    })
+}
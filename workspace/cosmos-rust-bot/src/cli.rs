@@ -0,0 +1,145 @@
+// Subcommands of the cosmos-rust-bot binary, the config flags (--config, --data-dir, ...) are removed before by
+// bot_library::shared::config::Config::from_args.
+
+/// Exit codes, for systemd and scripts.
+pub const EXIT_USAGE: u8 = 2;
+
+const KEYS_COMMANDS: [&str; 7] = ["generate", "import", "export", "change-passphrase", "accounts", "grants", "serve-signer"];
+
+pub const USAGE: &str = "Usage: cosmos-rust-bot [--config <file>] [--data-dir <dir>] [<command>]
+
+Commands:
+  run                  run the bot until it is stopped (default)
+  plan                 list the tasks the current settings enable, without running them
+  generate-features    write the feature list for the supported blockchains
+  query <index> [--filter <key>=<value>]... [--order-by <field>] [--limit <n>]
+                       query the entries of a running instance via its query socket, e.g. `query task_meta_data_errors`
+  export <file>        write settings, history and subscriptions to a file (the bot must be stopped)
+  import <file>        restore an export, replacing the current state (the bot must be stopped)
+  keys <generate|import|export|change-passphrase|accounts|grants|serve-signer>
+  help                 show this message
+
+Exit codes: 0 success, 1 error, 2 invalid usage, configuration or settings.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run,
+    Plan,
+    GenerateFeatures,
+    Query {
+        index: String,
+        filter: Vec<(String, String)>,
+        order_by: String,
+        limit: usize,
+    },
+    Export(String),
+    Import(String),
+    Keys(String),
+    Help,
+}
+
+fn value<'a>(args: &mut impl Iterator<Item = &'a String>, name: &str) -> anyhow::Result<String> {
+    args.next()
+        .map(|x| x.to_string())
+        .ok_or(anyhow::anyhow!("Error: Missing value for {}", name))
+}
+
+fn parse_query(args: &[String]) -> anyhow::Result<Command> {
+    let mut args = args.iter();
+    let index = value(&mut args, "<index>")?;
+    let mut filter: Vec<(String, String)> = Vec::new();
+    let mut order_by = "timestamp".to_string();
+    let mut limit = 10usize;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--filter" => {
+                let f = value(&mut args, "--filter")?;
+                let (key, value) = f
+                    .split_once('=')
+                    .ok_or(anyhow::anyhow!("Error: Invalid filter {}, expected <key>=<value>", f))?;
+                filter.push((key.to_string(), value.to_string()));
+            }
+            "--order-by" => {
+                order_by = value(&mut args, "--order-by")?;
+            }
+            "--limit" => {
+                limit = value(&mut args, "--limit")?
+                    .parse::<usize>()
+                    .map_err(|err| anyhow::anyhow!("Error: Invalid value for --limit: {}", err))?;
+            }
+            _ => {
+                return Err(anyhow::anyhow!("Error: Unknown argument for query: {}", arg));
+            }
+        }
+    }
+    Ok(Command::Query { index, filter, order_by, limit })
+}
+
+/// args without the program name.
+pub fn parse(args: &[String]) -> anyhow::Result<Command> {
+    let command = match args.first() {
+        Some(command) => command.as_str(),
+        None => {
+            return Ok(Command::Run);
+        }
+    };
+    let rest = &args[1..];
+    let expect_one = |name: &str| -> anyhow::Result<String> {
+        match rest {
+            [x] => Ok(x.to_string()),
+            _ => Err(anyhow::anyhow!("Error: {} expects exactly one argument", name)),
+        }
+    };
+    let expect_none = |command: Command| -> anyhow::Result<Command> {
+        match rest.first() {
+            Some(arg) => Err(anyhow::anyhow!("Error: Unexpected argument: {}", arg)),
+            None => Ok(command),
+        }
+    };
+    match command {
+        "run" => expect_none(Command::Run),
+        "plan" => expect_none(Command::Plan),
+        "generate-features" => expect_none(Command::GenerateFeatures),
+        "query" => parse_query(rest),
+        "export" => Ok(Command::Export(expect_one("export")?)),
+        "import" => Ok(Command::Import(expect_one("import")?)),
+        "keys" => {
+            let command = expect_one("keys")?;
+            if !KEYS_COMMANDS.contains(&command.as_str()) {
+                return Err(anyhow::anyhow!("Error: Unknown keys command: {}", command));
+            }
+            Ok(Command::Keys(command))
+        }
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        _ => Err(anyhow::anyhow!("Error: Unknown command: {}", command)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(x: &str) -> Vec<String> {
+        x.split_whitespace().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(parse(&args("")).unwrap(), Command::Run);
+        assert_eq!(parse(&args("keys accounts")).unwrap(), Command::Keys("accounts".to_string()));
+        assert_eq!(
+            parse(&args("query task_meta_data_errors --filter kind=error --limit 5")).unwrap(),
+            Command::Query {
+                index: "task_meta_data_errors".to_string(),
+                filter: vec![("kind".to_string(), "error".to_string())],
+                order_by: "timestamp".to_string(),
+                limit: 5,
+            }
+        );
+        assert!(parse(&args("export")).is_err());
+        assert!(parse(&args("keys delete")).is_err());
+        assert!(parse(&args("plan now")).is_err());
+        assert!(parse(&args("query errors --limit five")).is_err());
+        assert!(parse(&args("start")).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use bot_library::shared::AutoCompoundSettings;
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::utils::response::TaskResult;
+use cosmrs::distribution::MsgWithdrawDelegatorReward;
+use cosmrs::staking::MsgDelegate;
+use cosmrs::tx::Msg;
+use cosmrs::{AccountId, Any, Coin};
+use serde::{Deserialize, Serialize};
+
+use super::executor::{Execution, Executor};
+use crate::blockchain::authz::msg_exec;
+use crate::blockchain::distribution::query_delegation_rewards;
+use crate::blockchain::tx::TxOptions;
+use crate::store::HistoryStore;
+
+pub const AUTO_COMPOUND_TREE: &str = "auto_compound";
+const FEES_SPENT_TREE: &str = "auto_compound_fees_spent";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CompoundReport {
+    pub blockchain: String,
+    pub delegator_address: String,
+    /// (validator, amount) that is claimed and delegated again.
+    pub restake: Vec<(String, u64)>,
+    pub denom: String,
+    pub fee: u64,
+    pub gas_used: u64,
+    /// "simulated", "broadcast", "failed", "rejected" or "skipped"
+    pub outcome: String,
+    pub txhash: Option<String>,
+    pub note: String,
+}
+
+fn to_account_id(address: &str) -> anyhow::Result<AccountId> {
+    address.parse().map_err(|e| anyhow::anyhow!("Error: Invalid address {}: {}", address, e))
+}
+
+fn compound_msgs(delegator_address: &str, denom: &str, restake: &[(String, u64)]) -> anyhow::Result<Vec<Any>> {
+    let mut msgs: Vec<Any> = Vec::new();
+    for (validator, amount) in restake {
+        msgs.push(
+            MsgWithdrawDelegatorReward {
+                delegator_address: to_account_id(delegator_address)?,
+                validator_address: to_account_id(validator)?,
+            }
+            .to_any()
+            .map_err(|e| anyhow::anyhow!("Error: {}", e))?,
+        );
+        msgs.push(
+            MsgDelegate {
+                delegator_address: to_account_id(delegator_address)?,
+                validator_address: to_account_id(validator)?,
+                amount: Coin {
+                    denom: denom.parse().map_err(|e| anyhow::anyhow!("Error: {}", e))?,
+                    amount: *amount as u128,
+                },
+            }
+            .to_any()
+            .map_err(|e| anyhow::anyhow!("Error: {}", e))?,
+        );
+    }
+    Ok(msgs)
+}
+
+/// The fee is kept back from the claimed rewards (largest first), so the liquid balance stays unchanged.
+fn restake_amounts(rewards: &[(String, u64)], fee: u64) -> Vec<(String, u64)> {
+    let mut restake: Vec<(String, u64)> = rewards.iter().filter(|(_, amount)| *amount > 0).cloned().collect();
+    restake.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut fee = fee;
+    for (_, amount) in restake.iter_mut() {
+        let kept_back = fee.min(*amount);
+        *amount -= kept_back;
+        fee -= kept_back;
+    }
+    restake.into_iter().filter(|(_, amount)| *amount > 0).collect()
+}
+
+/// Claims the staking rewards and delegates them again once they pass the threshold.
+/// With a granter the rewards of the granter are compounded via MsgExec, the bot (grantee) pays the fee.
+/// In test mode the transaction is only simulated, the report shows what would have been broadcast.
+pub async fn auto_compound(
+    blockchain: SupportedBlockchain,
+    settings: AutoCompoundSettings,
+    executor: Executor,
+    granter: Option<String>,
+    history_store: HistoryStore,
+    key: String,
+) -> anyhow::Result<TaskResult> {
+    let account = executor
+        .wallet
+        .account(&blockchain.name)
+        .ok_or(anyhow::anyhow!("Error: No derived account for {}", blockchain.name))?
+        .clone();
+    let delegator_address = granter.clone().unwrap_or(account.address.to_owned());
+    let rewards = query_delegation_rewards(&blockchain, &delegator_address, &settings.denom).await?;
+    let total: u64 = rewards.iter().map(|(_, amount)| amount).sum();
+
+    if total < settings.threshold {
+        return Ok(TaskResult {
+            list_of_keys_modified: vec![key],
+        });
+    }
+
+    let fees_spent: u64 = history_store.get(FEES_SPENT_TREE, &blockchain.name)?.unwrap_or(0);
+    let mut report = CompoundReport {
+        blockchain: blockchain.name.to_owned(),
+        delegator_address: delegator_address.to_owned(),
+        restake: if granter.is_some() {
+            restake_amounts(&rewards, 0)
+        } else {
+            restake_amounts(&rewards, settings.fee)
+        },
+        denom: settings.denom.to_owned(),
+        fee: settings.fee,
+        gas_used: 0,
+        outcome: "skipped".to_string(),
+        txhash: None,
+        note: "".to_string(),
+    };
+
+    if fees_spent + settings.fee > settings.gas_budget {
+        report.note = format!("gas budget exhausted ({} of {} {} spent)", fees_spent, settings.gas_budget, settings.denom);
+    } else if granter.is_none() && total <= settings.fee {
+        report.note = format!("rewards ({} {}) do not cover the fee", total, settings.denom);
+    } else {
+        let options = TxOptions {
+            fee_denom: settings.denom.to_owned(),
+            fee_amount: settings.fee,
+            gas_limit: settings.gas_limit,
+            memo: "cosmos-rust-bot auto-compound".to_string(),
+        };
+        let mut msgs = compound_msgs(&delegator_address, &settings.denom, &report.restake)?;
+        if granter.is_some() {
+            msgs = vec![msg_exec(&account.address, msgs)];
+        }
+        match executor.execute(&blockchain, &account, msgs, &options).await? {
+            Execution::Rejected(reason) => {
+                report.outcome = "rejected".to_string();
+                report.note = format!("rejected by policy: {}", reason);
+            }
+            Execution::OutOfGas(gas_used) => {
+                report.gas_used = gas_used;
+                report.note = format!("estimated gas {} exceeds the gas limit {}", gas_used, settings.gas_limit);
+            }
+            Execution::Simulated(gas_used) => {
+                report.gas_used = gas_used;
+                report.outcome = "simulated".to_string();
+                report.note = "test mode, nothing was broadcast".to_string();
+            }
+            Execution::Broadcast(gas_used, res) => {
+                history_store.insert(FEES_SPENT_TREE, &blockchain.name, &(fees_spent + settings.fee))?;
+                report.gas_used = gas_used;
+                report.txhash = Some(res.txhash);
+                if res.code == 0 {
+                    report.outcome = "broadcast".to_string();
+                } else {
+                    report.outcome = "failed".to_string();
+                    report.note = res.raw_log;
+                }
+            }
+        }
+    }
+    history_store.push(AUTO_COMPOUND_TREE, &blockchain.name, &report)?;
+    Ok(TaskResult {
+        list_of_keys_modified: vec![key, blockchain.name.to_owned()],
+    })
+}
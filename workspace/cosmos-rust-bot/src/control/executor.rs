@@ -0,0 +1,88 @@
+use bot_library::shared::PolicySettings;
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmrs::Any;
+
+use super::journal::{AuditJournal, JournalEntry};
+use super::policy::{check, spent_last_day, summarize};
+use crate::account::wallet::{DerivedAccount, Wallet};
+use crate::blockchain::tx::{broadcast_tx, sign_tx, simulate_tx, BroadcastResult, TxOptions};
+
+// Every automated transaction goes through execute, such that the policy is checked in one place
+// and each decision ends up in the audit journal.
+
+#[derive(Clone)]
+pub struct Executor {
+    pub wallet: Wallet,
+    pub policy: PolicySettings,
+    pub journal: AuditJournal,
+    pub simulate_only: bool,
+}
+
+pub enum Execution {
+    Rejected(String),
+    /// estimated gas exceeds the gas limit
+    OutOfGas(u64),
+    Simulated(u64),
+    Broadcast(u64, BroadcastResult),
+}
+
+impl Executor {
+    pub async fn execute(&self, blockchain: &SupportedBlockchain, account: &DerivedAccount, msgs: Vec<Any>, options: &TxOptions) -> anyhow::Result<Execution> {
+        let _journal_lock = self.journal.lock().await;
+        let fee = vec![(options.fee_denom.to_owned(), options.fee_amount)];
+        let mut entry = JournalEntry::new(&blockchain.name, &account.address, summarize(&msgs)?, fee.clone(), self.simulate_only);
+
+        if let Err(reason) = check(&self.policy, &entry.msgs, &fee, &spent_last_day(&self.journal.entries(), &blockchain.name)) {
+            entry.decision = "rejected".to_string();
+            entry.reason = reason.to_owned();
+            self.journal.append(&entry)?;
+            return Ok(Execution::Rejected(reason));
+        }
+        entry.decision = "accepted".to_string();
+        self.journal.append(&entry)?;
+
+        // every accepted transaction gets a terminal entry, such that the spent amounts know whether it was broadcast.
+        let (tx_bytes, gas_used) = match self.sign_and_simulate(blockchain, account, msgs, options).await {
+            Ok(x) => x,
+            Err(err) => {
+                entry.decision = "failed".to_string();
+                entry.reason = err.to_string();
+                self.journal.append(&entry)?;
+                return Err(err);
+            }
+        };
+        if gas_used > options.gas_limit {
+            entry.decision = "out_of_gas".to_string();
+            entry.reason = format!("estimated gas {} exceeds the gas limit {}", gas_used, options.gas_limit);
+            self.journal.append(&entry)?;
+            return Ok(Execution::OutOfGas(gas_used));
+        }
+        if self.simulate_only {
+            entry.decision = "simulated".to_string();
+            self.journal.append(&entry)?;
+            return Ok(Execution::Simulated(gas_used));
+        }
+
+        let res = match broadcast_tx(blockchain, tx_bytes).await {
+            Ok(res) => res,
+            Err(err) => {
+                // the transaction might still have reached the mempool.
+                entry.decision = "broadcast_error".to_string();
+                entry.reason = err.to_string();
+                self.journal.append(&entry)?;
+                return Err(err);
+            }
+        };
+        entry.decision = "broadcast".to_string();
+        entry.reason = if res.code == 0 { "".to_string() } else { res.raw_log.to_owned() };
+        entry.txhash = Some(res.txhash.to_owned());
+        self.journal.append(&entry)?;
+        Ok(Execution::Broadcast(gas_used, res))
+    }
+
+    async fn sign_and_simulate(&self, blockchain: &SupportedBlockchain, account: &DerivedAccount, msgs: Vec<Any>, options: &TxOptions) -> anyhow::Result<(Vec<u8>, u64)> {
+        let tx_bytes = sign_tx(blockchain, &self.wallet.signer, account, msgs, options).await?;
+        let gas_used = simulate_tx(blockchain, tx_bytes.clone()).await?;
+        Ok((tx_bytes, gas_used))
+    }
+}
@@ -0,0 +1,89 @@
+use bot_library::shared::config::CONFIG;
+use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
+use cosmos_rust_interface::cosmos_rust_package::tokio::sync::{Mutex, MutexGuard};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+
+use super::policy::MsgSummary;
+
+lazy_static! {
+    pub static ref AUDIT_JOURNAL_PATH: String = CONFIG.data_path("cosmos-rust-bot-audit-journal.jsonl");
+    /// Shared by all journals of the process, the executors are created per task.
+    static ref JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    pub timestamp: i64,
+    pub blockchain: String,
+    pub signer: String,
+    pub msgs: Vec<MsgSummary>,
+    pub fee: Vec<(String, u64)>,
+    /// "accepted" or "rejected", an accepted transaction is followed by its outcome:
+    /// "failed" (signing or simulating), "out_of_gas", "simulated", "broadcast_error" or "broadcast"
+    pub decision: String,
+    pub reason: String,
+    pub simulate_only: bool,
+    pub txhash: Option<String>,
+}
+
+impl JournalEntry {
+    pub fn new(blockchain: &str, signer: &str, msgs: Vec<MsgSummary>, fee: Vec<(String, u64)>, simulate_only: bool) -> JournalEntry {
+        JournalEntry {
+            timestamp: Utc::now().timestamp(),
+            blockchain: blockchain.to_string(),
+            signer: signer.to_string(),
+            msgs,
+            fee,
+            decision: "".to_string(),
+            reason: "".to_string(),
+            simulate_only,
+            txhash: None,
+        }
+    }
+}
+
+/// Append-only log of every transaction the policy engine accepted or rejected, one json object per line.
+#[derive(Clone)]
+pub struct AuditJournal {
+    path: String,
+}
+
+impl AuditJournal {
+    pub fn new(path: &str) -> AuditJournal {
+        AuditJournal { path: path.to_string() }
+    }
+
+    /// Held from reading the spent amounts until the transaction is journaled,
+    /// such that concurrent transactions can not pass the daily limit together.
+    pub async fn lock(&self) -> MutexGuard<'static, ()> {
+        JOURNAL_LOCK.lock().await
+    }
+
+    pub fn append(&self, entry: &JournalEntry) -> anyhow::Result<()> {
+        let mut options = fs::OpenOptions::new();
+        options.append(true).create(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&self.path)?;
+        file.write_all(format!("{}\n", serde_json::to_string(entry)?).as_bytes())?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Returns all entries, oldest first. Lines that can not be parsed are skipped.
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        match fs::read_to_string(&self.path) {
+            Ok(file) => file
+                .lines()
+                .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
@@ -1,3 +1,9 @@
+pub mod compound;
+pub mod executor;
+pub mod journal;
+pub mod policy;
+pub mod vote_mirror;
+
 /*use std::collections::HashMap;
 use std::sync::Arc;
 use cosmos_rust_interface::cosmos_rust_package::tokio::sync::{Mutex};
@@ -0,0 +1,251 @@
+use bot_library::shared::PolicySettings;
+use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
+use cosmrs::proto::cosmos::authz::v1beta1::MsgExec;
+use cosmrs::proto::cosmos::bank::v1beta1::MsgSend;
+use cosmrs::proto::cosmos::base::v1beta1::Coin;
+use cosmrs::proto::cosmos::distribution::v1beta1::MsgWithdrawDelegatorReward;
+use cosmrs::proto::cosmos::gov::v1beta1::MsgVote;
+use cosmrs::proto::cosmos::staking::v1beta1::{MsgBeginRedelegate, MsgDelegate, MsgUndelegate};
+use cosmrs::proto::traits::Message;
+use cosmrs::Any;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::journal::JournalEntry;
+
+const DAY: i64 = 60 * 60 * 24;
+/// Limit key that applies to every denom without a limit of its own.
+pub const ANY_DENOM: &str = "*";
+
+/// What the policy engine needs to know about a message.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct MsgSummary {
+    pub type_url: String,
+    pub amounts: Vec<(String, u64)>,
+    pub recipients: Vec<String>,
+    pub validators: Vec<String>,
+}
+
+fn coin_amounts(coins: &[Coin]) -> anyhow::Result<Vec<(String, u64)>> {
+    coins
+        .iter()
+        .map(|c| Ok((c.denom.to_owned(), c.amount.parse::<u64>()?)))
+        .collect()
+}
+
+/// MsgExec is unwrapped, the policy applies to the messages it executes.
+pub fn summarize(msgs: &[Any]) -> anyhow::Result<Vec<MsgSummary>> {
+    let mut summaries: Vec<MsgSummary> = Vec::new();
+    for msg in msgs {
+        let mut summary = MsgSummary {
+            type_url: msg.type_url.to_owned(),
+            ..Default::default()
+        };
+        let value = msg.value.as_slice();
+        match msg.type_url.as_str() {
+            "/cosmos.authz.v1beta1.MsgExec" => {
+                summaries.append(&mut summarize(&MsgExec::decode(value)?.msgs)?);
+                continue;
+            }
+            "/cosmos.bank.v1beta1.MsgSend" => {
+                let m = MsgSend::decode(value)?;
+                summary.amounts = coin_amounts(&m.amount)?;
+                summary.recipients.push(m.to_address);
+            }
+            "/cosmos.staking.v1beta1.MsgDelegate" => {
+                let m = MsgDelegate::decode(value)?;
+                summary.amounts = coin_amounts(&m.amount.into_iter().collect::<Vec<Coin>>())?;
+                summary.validators.push(m.validator_address);
+            }
+            "/cosmos.staking.v1beta1.MsgUndelegate" => {
+                let m = MsgUndelegate::decode(value)?;
+                summary.amounts = coin_amounts(&m.amount.into_iter().collect::<Vec<Coin>>())?;
+                summary.validators.push(m.validator_address);
+            }
+            "/cosmos.staking.v1beta1.MsgBeginRedelegate" => {
+                let m = MsgBeginRedelegate::decode(value)?;
+                summary.amounts = coin_amounts(&m.amount.into_iter().collect::<Vec<Coin>>())?;
+                summary.validators.push(m.validator_src_address);
+                summary.validators.push(m.validator_dst_address);
+            }
+            "/cosmos.distribution.v1beta1.MsgWithdrawDelegatorReward" => {
+                summary.validators.push(MsgWithdrawDelegatorReward::decode(value)?.validator_address);
+            }
+            "/cosmos.gov.v1beta1.MsgVote" => {
+                MsgVote::decode(value)?;
+            }
+            _ => {}
+        }
+        summaries.push(summary);
+    }
+    Ok(summaries)
+}
+
+fn add_amounts(total: &mut HashMap<String, u64>, amounts: &[(String, u64)]) {
+    for (denom, amount) in amounts {
+        let sum = total.entry(denom.to_owned()).or_insert(0);
+        *sum = sum.saturating_add(*amount);
+    }
+}
+
+/// The limit of the denom, falling back to the wildcard. None if neither is configured.
+fn limit_of(limits: &HashMap<String, u64>, denom: &str) -> Option<u64> {
+    limits.get(denom).or_else(|| limits.get(ANY_DENOM)).cloned()
+}
+
+/// Amounts moved by transactions within the last 24h, fees included, per denom. Simulations are not counted.
+/// A failed broadcast is counted, as is an accepted transaction without an outcome (e.g. the bot stopped while
+/// broadcasting), either might have landed on chain.
+pub fn spent_last_day(journal: &[JournalEntry], blockchain: &str) -> HashMap<String, u64> {
+    let since = Utc::now().timestamp() - DAY;
+    let entries: Vec<&JournalEntry> = journal
+        .iter()
+        .filter(|e| !e.simulate_only && e.blockchain == blockchain && e.timestamp > since)
+        .collect();
+    let mut total: HashMap<String, u64> = HashMap::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let spent = match entry.decision.as_str() {
+            "broadcast" | "broadcast_error" => true,
+            // the journal is locked during execution, the outcome directly follows the accepted entry.
+            "accepted" => !entries
+                .get(i + 1)
+                .map(|next| next.decision != "accepted" && next.decision != "rejected" && next.timestamp == entry.timestamp && next.signer == entry.signer)
+                .unwrap_or(false),
+            _ => false,
+        };
+        if spent {
+            for msg in &entry.msgs {
+                add_amounts(&mut total, &msg.amounts);
+            }
+            add_amounts(&mut total, &entry.fee);
+        }
+    }
+    total
+}
+
+/// Returns the reason if the transaction violates the policy.
+/// A denom without a limit (and no wildcard limit) is denied.
+pub fn check(policy: &PolicySettings, msgs: &[MsgSummary], fee: &[(String, u64)], spent_last_day: &HashMap<String, u64>) -> Result<(), String> {
+    let mut total: HashMap<String, u64> = HashMap::new();
+    for msg in msgs {
+        if !policy.allowed_msg_types.contains(&msg.type_url) {
+            return Err(format!("message type {} is not allowed", msg.type_url));
+        }
+        for (denom, amount) in &msg.amounts {
+            match limit_of(&policy.max_amount_per_msg, denom) {
+                None => return Err(format!("no limit per message is configured for {}", denom)),
+                Some(max) if *amount > max => return Err(format!("{} {} exceeds the limit of {} per message", amount, denom, max)),
+                Some(_) => {}
+            }
+        }
+        if let Some(allowed) = &policy.allowed_recipients {
+            if let Some(recipient) = msg.recipients.iter().find(|r| !allowed.contains(r)) {
+                return Err(format!("recipient {} is not allowed", recipient));
+            }
+        }
+        if let Some(allowed) = &policy.allowed_validators {
+            if let Some(validator) = msg.validators.iter().find(|v| !allowed.contains(v)) {
+                return Err(format!("validator {} is not allowed", validator));
+            }
+        }
+        add_amounts(&mut total, &msg.amounts);
+    }
+    for (denom, amount) in &total {
+        let max = limit_of(&policy.max_amount_per_day, denom).ok_or(format!("no daily limit is configured for {}", denom))?;
+        let spent = spent_last_day.get(denom).cloned().unwrap_or(0);
+        if spent.saturating_add(*amount) > max {
+            return Err(format!("{} {} exceeds the daily limit of {} ({} spent within 24h)", amount, denom, max, spent));
+        }
+    }
+    for (denom, amount) in fee {
+        match limit_of(&policy.max_fee, denom) {
+            None => return Err(format!("no fee limit is configured for {}", denom)),
+            Some(max) if *amount > max => return Err(format!("fee {} {} exceeds the limit of {}", amount, denom, max)),
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn delegate(amount: u64, validator: &str) -> MsgSummary {
+        MsgSummary {
+            type_url: "/cosmos.staking.v1beta1.MsgDelegate".to_string(),
+            amounts: vec![("uatom".to_string(), amount)],
+            recipients: Vec::new(),
+            validators: vec![validator.to_string()],
+        }
+    }
+
+    fn policy() -> PolicySettings {
+        PolicySettings {
+            allowed_msg_types: vec!["/cosmos.staking.v1beta1.MsgDelegate".to_string()],
+            max_amount_per_msg: [("uatom".to_string(), 1000)].into_iter().collect(),
+            max_amount_per_day: [("uatom".to_string(), 1500)].into_iter().collect(),
+            allowed_recipients: None,
+            allowed_validators: Some(vec!["val1".to_string()]),
+            max_fee: [("uatom".to_string(), 10)].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn check_enforces_limits() {
+        let fee = vec![("uatom".to_string(), 5)];
+        let spent: HashMap<String, u64> = [("uatom".to_string(), 1000)].into_iter().collect();
+        assert!(check(&policy(), &[delegate(500, "val1")], &fee, &HashMap::new()).is_ok());
+        assert!(check(&policy(), &[delegate(1001, "val1")], &fee, &HashMap::new()).is_err());
+        assert!(check(&policy(), &[delegate(500, "val2")], &fee, &HashMap::new()).is_err());
+        assert!(check(&policy(), &[delegate(600, "val1")], &fee, &spent).is_err());
+        assert!(check(&policy(), &[delegate(500, "val1")], &[("uatom".to_string(), 11)], &HashMap::new()).is_err());
+        assert!(check(&PolicySettings::default(), &[delegate(1, "val1")], &fee, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn denoms_without_a_limit_are_denied_unless_a_wildcard_is_set() {
+        let fee = vec![("uatom".to_string(), 5)];
+        let mut msg = delegate(500, "val1");
+        msg.amounts.push(("ibc/27394FB0".to_string(), 1));
+        assert_eq!(check(&policy(), &[msg.clone()], &fee, &HashMap::new()), Err("no limit per message is configured for ibc/27394FB0".to_string()));
+        assert!(check(&policy(), &[delegate(500, "val1")], &[("uosmo".to_string(), 1)], &HashMap::new()).is_err());
+
+        let mut wildcard = policy();
+        wildcard.max_amount_per_msg.insert(ANY_DENOM.to_string(), 10);
+        assert_eq!(check(&wildcard, &[msg.clone()], &fee, &HashMap::new()), Err("no daily limit is configured for ibc/27394FB0".to_string()));
+        wildcard.max_amount_per_day.insert(ANY_DENOM.to_string(), 10);
+        assert!(check(&wildcard, &[msg], &fee, &HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn simulations_do_not_count_toward_spend() {
+        let entry = |decision: &str, simulate_only: bool| {
+            let mut entry = JournalEntry::new("cosmoshub", "cosmos1", vec![delegate(100, "val1")], vec![("uatom".to_string(), 5)], simulate_only);
+            entry.decision = decision.to_string();
+            entry
+        };
+        let journal = vec![entry("accepted", true), entry("simulated", true), entry("accepted", false), entry("out_of_gas", false)];
+        assert!(spent_last_day(&journal, "cosmoshub").is_empty());
+        assert!(spent_last_day(&journal, "osmosis").is_empty());
+    }
+
+    #[test]
+    fn broadcasts_and_accepted_transactions_without_an_outcome_count_toward_spend() {
+        let entry = |decision: &str| {
+            let mut entry = JournalEntry::new("cosmoshub", "cosmos1", vec![delegate(100, "val1")], vec![("uatom".to_string(), 5)], false);
+            entry.decision = decision.to_string();
+            entry
+        };
+        let journal = vec![entry("accepted"), entry("broadcast")];
+        assert_eq!(spent_last_day(&journal, "cosmoshub").get("uatom"), Some(&105));
+        let journal = vec![entry("accepted"), entry("broadcast_error")];
+        assert_eq!(spent_last_day(&journal, "cosmoshub").get("uatom"), Some(&105));
+        let journal = vec![entry("accepted"), entry("failed"), entry("rejected"), entry("accepted")];
+        assert_eq!(spent_last_day(&journal, "cosmoshub").get("uatom"), Some(&105));
+
+        let mut total: HashMap<String, u64> = [("uatom".to_string(), u64::MAX)].into_iter().collect();
+        add_amounts(&mut total, &[("uatom".to_string(), 1)]);
+        assert_eq!(total.get("uatom"), Some(&u64::MAX));
+    }
+}
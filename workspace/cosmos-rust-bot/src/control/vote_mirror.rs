@@ -0,0 +1,196 @@
+use bot_library::shared::config::CONFIG;
+use bot_library::shared::VoteMirrorSettings;
+use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
+use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
+use cosmos_rust_interface::utils::response::TaskResult;
+use cosmos_sdk_proto::cosmos::gov::v1beta1::ProposalStatus;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use super::executor::{Execution, Executor};
+use crate::blockchain::authz::msg_exec;
+use crate::blockchain::gov::{msg_vote, proposal_key, proposal_title, proposal_type, query_proposals, query_vote, retain_proposals, vote_option_to_string};
+use crate::blockchain::staking::operator_to_account_address;
+use crate::blockchain::tx::TxOptions;
+use crate::store::HistoryStore;
+
+pub const VOTE_MIRROR_TREE: &str = "vote_mirror";
+lazy_static! {
+    /// One file per vetoed proposal (`<blockchain>_<proposal_id>`), the telegram bot appends the user hash of each veto as a line.
+    pub static ref VOTE_MIRROR_VETO_PATH: String = format!("{}/", CONFIG.data_path("vote_mirror_vetoes"));
+}
+/// The veto window (delay) has to end at least this long before the voting period ends, otherwise the vote is not mirrored.
+const VOTING_END_MARGIN: i64 = 60 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MirroredVote {
+    pub blockchain: String,
+    pub proposal_id: u64,
+    pub title: String,
+    pub proposal_type: String,
+    pub validator: String,
+    pub voter: String,
+    /// the current vote of the validator
+    pub option: String,
+    pub voting_end_time: i64,
+    /// the vote is cast after this time unless it is vetoed
+    pub cast_after: i64,
+    /// "pending", "vetoed", "excluded", "skipped", "simulated", "cast", "rejected" or "failed"
+    pub status: String,
+    pub txhash: Option<String>,
+    pub note: String,
+}
+
+/// The first veto of a user in veto_users, vetoes of other users are ignored.
+fn authorized_veto(vetoes: &str, veto_users: &[u64]) -> Option<u64> {
+    vetoes
+        .lines()
+        .filter_map(|line| line.trim().parse::<u64>().ok())
+        .find(|user_hash| veto_users.contains(user_hash))
+}
+
+fn vetoed_by(blockchain: &str, proposal_id: u64, veto_users: &[u64]) -> Option<u64> {
+    let vetoes = fs::read_to_string(format!("{}{}", *VOTE_MIRROR_VETO_PATH, proposal_key(blockchain, proposal_id))).ok()?;
+    authorized_veto(&vetoes, veto_users)
+}
+
+/// Follows the votes of the configured validator on proposals in voting period.
+/// A new (or changed) vote is published as "pending" first and only cast once the delay passed without a veto.
+/// If the voting period ends before the delay is over (plus VOTING_END_MARGIN) the vote is "skipped", it can not be vetoed in time.
+/// With a granter the vote is cast for the granter via MsgExec.
+pub async fn mirror_votes(
+    blockchain: SupportedBlockchain,
+    settings: VoteMirrorSettings,
+    executor: Executor,
+    granter: Option<String>,
+    history_store: HistoryStore,
+    key: String,
+) -> anyhow::Result<TaskResult> {
+    let now = Utc::now().timestamp();
+    let mut list_of_keys_modified = vec![key];
+    let account = executor
+        .wallet
+        .account(&blockchain.name)
+        .ok_or(anyhow::anyhow!("Error: No derived account for {}", blockchain.name))?
+        .clone();
+    let voter = granter.clone().unwrap_or(account.address.to_owned());
+    let validator_account = operator_to_account_address(&settings.validator)?;
+
+    for proposal in query_proposals(&blockchain, ProposalStatus::VotingPeriod, None).await? {
+        let option = match query_vote(&blockchain, proposal.proposal_id, &validator_account).await? {
+            Some(option) => option,
+            None => {
+                continue;
+            }
+        };
+        let option_name = vote_option_to_string(option);
+        let proposal_key = proposal_key(&blockchain.name, proposal.proposal_id);
+        let voting_end_time = proposal.voting_end_time.as_ref().map(|t| t.seconds).unwrap_or(0);
+        let cast_after = now + settings.delay;
+        let too_late = cast_after > voting_end_time - VOTING_END_MARGIN;
+        let too_late_note = format!("the voting period ends before the veto window of {}s is over", settings.delay);
+
+        let mut record = match history_store.get::<MirroredVote>(VOTE_MIRROR_TREE, &proposal_key)? {
+            // the validator changed its vote: announce it again and restart the delay.
+            Some(previous) if previous.option != option_name && previous.status != "vetoed" && previous.status != "excluded" => MirroredVote {
+                note: if too_late {
+                    format!("the validator changed its vote from {}, {}", previous.option, too_late_note)
+                } else {
+                    format!("the validator changed its vote from {}", previous.option)
+                },
+                option: option_name.to_owned(),
+                cast_after,
+                status: if too_late { "skipped" } else { "pending" }.to_string(),
+                txhash: None,
+                ..previous
+            },
+            Some(previous) => previous,
+            None => {
+                let proposal_type = proposal_type(&proposal);
+                let excluded = settings.excluded_proposal_types.contains(&proposal_type);
+                let (status, note) = if excluded {
+                    ("excluded", "".to_string())
+                } else if too_late {
+                    ("skipped", too_late_note)
+                } else {
+                    ("pending", "".to_string())
+                };
+                MirroredVote {
+                    blockchain: blockchain.name.to_owned(),
+                    proposal_id: proposal.proposal_id,
+                    title: proposal_title(&proposal),
+                    proposal_type,
+                    validator: settings.validator.to_owned(),
+                    voter: voter.to_owned(),
+                    option: option_name.to_owned(),
+                    voting_end_time,
+                    cast_after,
+                    status: status.to_string(),
+                    txhash: None,
+                    note,
+                }
+            }
+        };
+
+        if record.status == "pending" {
+            if let Some(user_hash) = vetoed_by(&blockchain.name, proposal.proposal_id, &settings.veto_users) {
+                record.status = "vetoed".to_string();
+                record.note = format!("vetoed by {}", user_hash);
+            } else if now >= record.cast_after {
+                let options = TxOptions {
+                    fee_denom: settings.fee_denom.to_owned(),
+                    fee_amount: settings.fee,
+                    gas_limit: settings.gas_limit,
+                    memo: "cosmos-rust-bot vote mirror".to_string(),
+                };
+                let mut msgs = vec![msg_vote(proposal.proposal_id, &voter, option)];
+                if granter.is_some() {
+                    msgs = vec![msg_exec(&account.address, msgs)];
+                }
+                match executor.execute(&blockchain, &account, msgs, &options).await? {
+                    Execution::Rejected(reason) => {
+                        record.status = "rejected".to_string();
+                        record.note = format!("rejected by policy: {}", reason);
+                    }
+                    Execution::OutOfGas(gas_used) => {
+                        record.status = "failed".to_string();
+                        record.note = format!("estimated gas {} exceeds the gas limit {}", gas_used, settings.gas_limit);
+                    }
+                    Execution::Simulated(_) => {
+                        record.status = "simulated".to_string();
+                        record.note = "test mode, nothing was broadcast".to_string();
+                    }
+                    Execution::Broadcast(_, res) => {
+                        record.txhash = Some(res.txhash);
+                        if res.code == 0 {
+                            record.status = "cast".to_string();
+                        } else {
+                            record.status = "failed".to_string();
+                            record.note = res.raw_log;
+                        }
+                    }
+                }
+            }
+        }
+        history_store.insert(VOTE_MIRROR_TREE, &proposal_key, &record)?;
+        list_of_keys_modified.push(proposal_key);
+    }
+    // proposals that left the voting period are no longer relevant, the audit journal keeps the cast votes.
+    retain_proposals(&history_store, VOTE_MIRROR_TREE, &blockchain.name, &list_of_keys_modified)?;
+    Ok(TaskResult { list_of_keys_modified })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_vetoes_of_veto_users_count() {
+        let vetoes = "11\n22\nnot a user hash\n";
+        assert_eq!(authorized_veto(vetoes, &[22, 33]), Some(22));
+        assert_eq!(authorized_veto(vetoes, &[33]), None);
+        assert_eq!(authorized_veto(vetoes, &[]), None);
+        assert_eq!(authorized_veto("", &[11]), None);
+    }
+}
@@ -1,40 +1,49 @@
-#[macro_use]
-extern crate litcrypt;
-//https://github.com/anvie/litcrypt.rs
-use_litcrypt!();
-
-use bot_library::shared::{get_input, load_user_settings};
+use bot_library::shared::{load_user_settings, write_atomically, SettingsError};
+use bot_library::shared::config::{init_config, CONFIG};
+use lazy_static::lazy_static;
+use bot_library::shared::settings::{spawn_query_socket_server, SettingsService};
+use bot_library::shared::metrics::{spawn_metrics_server, METRICS};
+use bot_library::shared::logging::init_logging;
 
 mod account;
-//mod control;
+mod blockchain;
+mod cli;
+mod control;
 mod model;
+mod postproc;
+mod store;
 
-use account::wallet::{decrypt_text_with_secret, encrypt_text_with_secret};
+use account::keystore::Keystore;
+use account::signer::{serve_remote_signer, RemoteSigner, Signer};
+use account::wallet::{change_keystore_passphrase, export_keystore, generate_keystore, import_keystore, load_accounts, load_registry_chains, registry_coin_types, registry_sign_policy, unlock_keystore_signer, Wallet};
 //use control::try_run_function;
 use model::requirements::UserSettings;
 
 use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
 use core::future::Future;
 use core::pin::Pin;
-use secstr::*;
 use std::collections::HashMap;
 use std::fs;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use cosmos_rust_interface::cosmos_rust_package::tokio as tokio;
 use tokio::sync::{Mutex, RwLock};
-use tokio::task::JoinSet;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::{sleep, timeout};
 
-use notify::{watcher, RecursiveMode, Watcher};
-use std::sync::mpsc::channel;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 
-use cosmos_rust_interface::blockchain::account_from_seed_phrase;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
-use std::{thread, time};
 
 use cosmos_rust_interface::utils::entry::db::load_sled_db;
-use cosmos_rust_interface::utils::entry::db::query::socket::spawn_socket_query_server;
+use cosmos_rust_interface::utils::entry::db::query::socket::{client_send_query_request, spawn_socket_query_server};
 use cosmos_rust_interface::utils::entry::postproc::blockchain::cosmos::gov::governance_proposal_notifications;
 use cosmos_rust_interface::utils::entry::postproc::meta_data::debug::debug;
 use cosmos_rust_interface::utils::entry::postproc::meta_data::errors::errors;
@@ -44,25 +53,109 @@ use cosmos_rust_interface::utils::response::ResponseResult;
 use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels;
 
 use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
-use crate::model::{get_task_meta_data, poll_resolved_tasks, try_spawn_upcoming_tasks};
-use crate::model::requirements::get_requirements;
+use crate::model::{cancel_tasks, get_task_list, get_task_meta_data, poll_resolved_tasks, record_task_state_metrics, removed_tasks, task_meta_data, try_spawn_upcoming_tasks};
+use crate::model::requirements::{feature_list_to_file, get_requirements};
+use cli::{Command, EXIT_USAGE};
+use crate::blockchain::authz::grant_instructions;
+use crate::control::journal::{AuditJournal, AUDIT_JOURNAL_PATH};
+use crate::postproc::audit_journal::audit_journal_entries;
+use crate::postproc::authz::authz_grant_alerts;
+use crate::postproc::balance_alerts::balance_alerts;
+use crate::postproc::auto_compound::auto_compound_reports;
+use crate::postproc::community_pool::community_pool_spend_context;
+use crate::postproc::params::{param_change_diffs, params_change_alerts};
+use crate::postproc::staking_reminders::staking_reminders;
+use crate::postproc::settings::settings_validation_errors;
+use crate::postproc::scorecard::{export_validator_scorecards, validator_scorecard_entries, validator_scorecards};
+use crate::postproc::validator_votes::validator_vote_breakdown;
+use crate::postproc::vote_mirror::vote_mirror_entries;
+use crate::store::export::{export_state, import_state, StateExport};
+use crate::store::HistoryStore;
 
 use cosmos_rust_interface::utils::entry::db::*;
 use cosmos_rust_interface::utils::entry::db::query::CosmosRustBotStoreInquirer;
+use log::{error, info};
+
+lazy_static! {
+    // see bot_library::shared::config, everything lives in the data directory unless configured otherwise.
+    static ref QUERY_SOCKET: String = CONFIG.query_socket();
+    static ref SETTINGS_PATH: String = CONFIG.settings_path();
+    static ref SETTINGS_HISTORY_PATH: String = CONFIG.settings_history_path();
+    /// The query server of the entry store, behind the query socket that also answers the settings requests.
+    static ref STORE_QUERY_SOCKET: String = format!("{}.store", *QUERY_SOCKET);
+    static ref CRB_SLED_DB: String = CONFIG.data_path("cosmos_rust_bot_sled_db");
+    static ref TASK_STORE_SLED_DB: String = CONFIG.data_path("task_store_sled_db");
+    static ref HISTORY_STORE_SLED_DB: String = CONFIG.data_path("history_store_sled_db");
+    static ref CRB_SUBSCRIPTION_STORE_SLED_DB: String = CONFIG.data_path("cosmos_rust_bot_subscriptions_sled_db");
+    static ref CRB_SUBSCRIPTION_STORE_JSON: String = CONFIG.data_path("cosmos_rust_bot_subscriptions.json");
+    static ref CRB_REGISTRATION_STORE_JSON: String = CONFIG.data_path("cosmos_rust_bot_registrations.json");
+    static ref PUBLIC_PATH: String = CONFIG.public_path();
+    static ref KEYSTORE_PATH: String = CONFIG.data_path("cosmos-rust-bot.keystore");
+    static ref ACCOUNTS_PATH: String = CONFIG.data_path("cosmos-rust-bot-accounts.json");
+    static ref CHAIN_REGISTRY_PATH: String = CONFIG.chain_registry.to_owned();
+}
 
-const QUERY_SOCKET: &str = "./tmp/cosmos_rust_bot_query_socket";
-const SETTINGS_PATH: &str = "./tmp/cosmos-rust-bot.json";
-const CRB_SLED_DB: &str = "./tmp/cosmos_rust_bot_sled_db";
-const TASK_STORE_SLED_DB: &str = "./tmp/task_store_sled_db";
-const CRB_SUBSCRIPTION_STORE_SLED_DB: &str = "./tmp/cosmos_rust_bot_subscriptions_sled_db";
-const CRB_SUBSCRIPTION_STORE_JSON: &str = "./tmp/cosmos_rust_bot_subscriptions.json";
-const CRB_REGISTRATION_STORE_JSON: &str = "./tmp/cosmos_rust_bot_registrations.json";
+/// Below the 10s docker and most process managers wait before they kill the process.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(8);
+/// events of one save (write, rename, ..) are reported as one change.
+const SETTINGS_WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
 
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
+
+    // paths and sockets: --config <file>, --data-dir <dir>, ... (see bot_library::shared::config)
+    // loaded here so an invalid config fails with a readable error before CONFIG is used.
+    let (config, args) = match init_config() {
+        Ok(x) => x,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    if let Err(err) = init_logging(&config.log_format) {
+        eprintln!("{}", err);
+        return ExitCode::from(EXIT_USAGE);
+    }
+    let command = match cli::parse(args.get(1..).unwrap_or(&[])) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let result = match fs::create_dir_all(&config.data_dir) {
+        Err(err) => Err(anyhow::anyhow!("Error: Unable to create the data directory {}: {}", config.data_dir, err)),
+        Ok(()) => match command {
+            Command::Run => run().await,
+            Command::Plan => run_plan(),
+            Command::GenerateFeatures => feature_list_to_file(),
+            Command::Query { index, filter, order_by, limit } => run_query(index, filter, order_by, limit),
+            Command::Export(path) => run_export(&path),
+            Command::Import(path) => run_import(&path),
+            Command::Keys(command) => run_keys_command(&command),
+            Command::Help => {
+                println!("{}", cli::USAGE);
+                Ok(())
+            }
+        },
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        // invalid settings are a configuration mistake, see cli::USAGE
+        Err(err) if err.downcast_ref::<SettingsError>().is_some() => {
+            eprintln!("{}", err);
+            ExitCode::from(EXIT_USAGE)
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    env_logger::init();
+async fn run() -> anyhow::Result<()> {
 
     // TODO: supported blockchains need to be managed in a central manner.
     // to apply certain limitations.
@@ -72,59 +165,75 @@ async fn main() -> anyhow::Result<()> {
     // stores all requirements either as task or the resolved value.
     let mut join_set: JoinSet<()> = JoinSet::new();
     let task_store: TaskMemoryStore = TaskMemoryStore::new(Some(TASK_STORE_SLED_DB.to_string())).unwrap();
+    let history_store: HistoryStore = HistoryStore::new(load_sled_db(&HISTORY_STORE_SLED_DB));
 
-    let mut user_settings: UserSettings = load_user_settings(SETTINGS_PATH);
+    let settings_service = SettingsService::load(&SETTINGS_PATH, &SETTINGS_HISTORY_PATH)?;
+    let mut user_settings: UserSettings = settings_service.get();
     //println!("{}", serde_json::to_string_pretty(&user_settings)?);
 
-    let (wallet_seed_phrase, wallet_acc_address) = get_wallet_details(&user_settings).await;
-
-    // Create a channel to receive the events.
-    let (tx, rx) = channel();
-    // Create a watcher object, delivering debounced events.
-    // The notification back-end is selected based on the platform.
-    let mut watcher = watcher(tx, Duration::from_secs(10)).unwrap();
-    // Add a path to be watched. All files and directories at that path and
-    // below will be monitored for changes.
-    watcher
-        .watch(SETTINGS_PATH, RecursiveMode::Recursive)
-        .unwrap();
-
-    let entry_index_db = load_sled_db(CRB_SLED_DB);
-    let subscription_db = load_sled_db(CRB_SUBSCRIPTION_STORE_SLED_DB);
+    let wallet = get_wallet_details().await?;
+
+    let mut settings_events = watch_settings_file(&SETTINGS_PATH)?;
+
+    let entry_index_db = load_sled_db(&CRB_SLED_DB);
+    let subscription_db = load_sled_db(&CRB_SUBSCRIPTION_STORE_SLED_DB);
     let subscription_store = SubscriptionStore::new(&subscription_db);
-    subscription_store.import_subscriptions(CRB_SUBSCRIPTION_STORE_JSON);
-    subscription_store.import_registrations(CRB_REGISTRATION_STORE_JSON);
+    subscription_store.import_subscriptions(&CRB_SUBSCRIPTION_STORE_JSON);
+    subscription_store.import_registrations(&CRB_REGISTRATION_STORE_JSON);
 
-    let mut cosmos_rust_bot_store = CosmosRustBotStore::new(entry_index_db,subscription_store);
+    let mut cosmos_rust_bot_store = CosmosRustBotStore::new(entry_index_db.clone(),subscription_store);
 
-    spawn_socket_query_server(QUERY_SOCKET,&cosmos_rust_bot_store);
+    spawn_socket_query_server(&STORE_QUERY_SOCKET,&cosmos_rust_bot_store);
+    spawn_query_socket_server(&QUERY_SOCKET, &STORE_QUERY_SOCKET, &settings_service)?;
+    if let Some(address) = &CONFIG.metrics_listen {
+        spawn_metrics_server(address, &METRICS)?;
+        info!("metrics: listening on {}", address);
+    }
 
     let _thread = cosmos_rust_bot_store.spawn_notify_on_subscription_update_task();
 
-        loop {
-            let req = get_requirements(&user_settings);
-
-            while !user_settings.pause_requested {
-                if user_settings.hot_reload {
-                    match rx.recv_timeout(Duration::from_millis(10)) {
-                        Ok(event) => {
-                            println!("{:?}", event);
-                            break;
-                        }
-                        Err(_e) => {
-                            //println!("watch error: {:?}", e),
-                        }
+    let shutdown = spawn_shutdown_listener()?;
+
+        let mut req = get_requirements(&user_settings);
+        let mut settings_version = settings_service.version();
+        // the tasks in the join_set by name, to cancel only those a settings change removes.
+        let mut running: HashMap<String, AbortHandle> = HashMap::new();
+
+            while !shutdown.load(Ordering::SeqCst) {
+                if settings_events.changed() && user_settings.hot_reload {
+                    if let Err(err) = settings_service.reload() {
+                        error!("settings reload failed: {:?}", err);
                     }
                 }
+                // changed via the settings service (or reloaded from the file): tasks that are still required keep running,
+                // everything else (notifications, policy, ..) is read from user_settings and applies from the next iteration.
+                if settings_service.version() != settings_version {
+                    settings_version = settings_service.version();
+                    user_settings = settings_service.get();
+                    let new_req = get_requirements(&user_settings);
+                    let cancelled = cancel_tasks(&removed_tasks(&req, &new_req), &mut running, &task_store);
+                    info!("settings reloaded: {} tasks required, {} cancelled", new_req.len(), cancelled);
+                    req = new_req;
+                }
+                // while paused only a settings change can resume the bot.
+                if user_settings.pause_requested {
+                    let names: Vec<String> = running.keys().cloned().collect();
+                    cancel_tasks(&names, &mut running, &task_store);
+                    poll_resolved_tasks(&mut join_set, &mut running, &req).await;
+                    sleep(Duration::from_millis(500)).await;
+                    continue;
+                }
 
-                let number_of_tasks_resolved = poll_resolved_tasks(&mut join_set).await;
+                let number_of_tasks_resolved = poll_resolved_tasks(&mut join_set, &mut running, &req).await;
 
                 let _number_of_tasks_added = try_spawn_upcoming_tasks(
                     &mut join_set,
+                    &mut running,
                     &task_store,
+                    &history_store,
                     &req,
                     &user_settings,
-                    &wallet_acc_address
+                    &wallet
                 ).await;
 
                 if number_of_tasks_resolved > 0 {
@@ -134,11 +243,38 @@ async fn main() -> anyhow::Result<()> {
 
                     let mut entries: Vec<CosmosRustBotValue> = Vec::new();
 
-                    let mut task_meta_data: Vec<CosmosRustBotValue> = get_task_meta_data(&task_store, &req).await;
-                    entries.append(&mut task_meta_data);
+                    let task_list = get_task_list(&task_store, &req).await;
+                    record_task_state_metrics(&task_list);
+                    record_store_metrics(&history_store, &entry_index_db, &subscription_db);
+                    entries.append(&mut task_meta_data(task_list));
 
                     if user_settings.governance_proposal_notifications {
                         entries.append(&mut governance_proposal_notifications(&task_store));
+                        entries.append(&mut param_change_diffs(&history_store));
+                        entries.append(&mut params_change_alerts(&history_store));
+                        entries.append(&mut community_pool_spend_context(&history_store));
+                        entries.append(&mut validator_vote_breakdown(&history_store));
+
+                        let scorecards = validator_scorecards(&history_store);
+                        entries.append(&mut validator_scorecard_entries(&scorecards));
+                        export_validator_scorecards(&scorecards, &PUBLIC_PATH).ok();
+                    }
+
+                    if !user_settings.auto_compound.is_empty() {
+                        entries.append(&mut auto_compound_reports(&history_store));
+                    }
+                    if !user_settings.authz.is_empty() {
+                        entries.append(&mut authz_grant_alerts(&history_store));
+                    }
+                    if !user_settings.vote_mirror.is_empty() {
+                        entries.append(&mut vote_mirror_entries(&history_store));
+                    }
+                    if !user_settings.watched_addresses.is_empty() {
+                        entries.append(&mut staking_reminders(&history_store));
+                        entries.append(&mut balance_alerts(&history_store, &user_settings.watched_addresses));
+                    }
+                    if wallet.is_some() {
+                        entries.append(&mut audit_journal_entries(&AuditJournal::new(&AUDIT_JOURNAL_PATH)));
                     }
 
                     let mut task_meta_data: Vec<CosmosRustBotValue> = Vec::new();
@@ -148,68 +284,272 @@ async fn main() -> anyhow::Result<()> {
                     //task_meta_data.append(&mut debug);
                     //task_meta_data.append(&mut logs);
                     task_meta_data.append(&mut errors);
+                    task_meta_data.append(&mut settings_validation_errors(settings_service.validation_error()));
                     entries.append(&mut task_meta_data);
 
                     CosmosRustBotValue::add_index(&mut entries, "timestamp", "timestamp");
 
                     cosmos_rust_bot_store.update_items(entries);
                 } else {
-                    let millis = time::Duration::from_millis(500);
-                    thread::sleep(millis);
+                    sleep(Duration::from_millis(500)).await;
                 }
             }
-            join_set.shutdown().await;
-            if user_settings.hot_reload {
-                user_settings = load_user_settings(SETTINGS_PATH);
+
+    // ** graceful shutdown: no new tasks are scheduled, running ones get SHUTDOWN_GRACE_PERIOD to finish **
+    info!("shutdown: waiting up to {}s for {} running tasks", SHUTDOWN_GRACE_PERIOD.as_secs(), join_set.len());
+    if timeout(SHUTDOWN_GRACE_PERIOD, async { while join_set.join_next().await.is_some() {} }).await.is_err() {
+        error!("shutdown: aborting {} tasks that did not finish in time", join_set.len());
+    }
+    join_set.shutdown().await;
+
+    let mut entries: Vec<CosmosRustBotValue> = get_task_meta_data(&task_store, &req).await;
+    entries.append(&mut errors(&task_store));
+    CosmosRustBotValue::add_index(&mut entries, "timestamp", "timestamp");
+    cosmos_rust_bot_store.update_items(entries);
+
+    // the task store flushes when it is dropped at the end of this function.
+    history_store.flush()?;
+    entry_index_db.flush()?;
+    subscription_db.flush()?;
+    let subscription_store = SubscriptionStore::new(&subscription_db);
+    subscription_store.export_subscriptions(&CRB_SUBSCRIPTION_STORE_JSON);
+    subscription_store.export_registrations(&CRB_REGISTRATION_STORE_JSON);
+    info!("shutdown: done");
+    Ok(())
+}
+
+/// Subscription count and the sled sizes, the task store is an in-memory cache backed by TASK_STORE_SLED_DB.
+fn record_store_metrics(history_store: &HistoryStore, entry_index_db: &sled::Db, subscription_db: &sled::Db) {
+    // sled::Db::len only counts the default tree, the subscription store keeps its entries in named trees.
+    let subscription_entries: usize = subscription_db
+        .tree_names()
+        .iter()
+        .filter_map(|name| subscription_db.open_tree(name).ok())
+        .map(|tree| tree.len())
+        .sum();
+    METRICS.set_gauge("crb_subscription_store_entries", "Subscriptions and registrations in the subscription store.", &[], subscription_entries as f64);
+    let sizes = [
+        ("history_store", history_store.size_on_disk().ok()),
+        ("entry_index", entry_index_db.size_on_disk().ok()),
+        ("subscriptions", subscription_db.size_on_disk().ok()),
+    ];
+    for (db, size) in sizes {
+        if let Some(size) = size {
+            METRICS.set_gauge("crb_sled_size_bytes", "Size on disk of the sled databases.", &[("db", db)], size as f64);
+        }
+    }
+}
+
+/// Debounced changes of the settings file. The parent directory is watched, so a replaced file
+/// (write_atomically, editors that save via rename) is picked up as well.
+struct SettingsFileEvents {
+    _debouncer: Debouncer<RecommendedWatcher>,
+    events: UnboundedReceiver<()>,
+}
+
+impl SettingsFileEvents {
+    /// Whether the file changed since the last call, does not block.
+    fn changed(&mut self) -> bool {
+        let mut changed = false;
+        while self.events.try_recv().is_ok() {
+            changed = true;
+        }
+        changed
+    }
+}
+
+fn watch_settings_file(path: &str) -> anyhow::Result<SettingsFileEvents> {
+    let path = Path::new(path);
+    let file_name = path
+        .file_name()
+        .ok_or(anyhow::anyhow!("Error: Invalid settings path {}", path.display()))?
+        .to_owned();
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let (tx, events) = unbounded_channel();
+    let mut debouncer = new_debouncer(SETTINGS_WATCH_DEBOUNCE, move |result: DebounceEventResult| match result {
+        Ok(events) => {
+            if events.iter().any(|event| event.path.file_name() == Some(file_name.as_os_str())) {
+                tx.send(()).ok();
             }
         }
+        Err(err) => error!("settings watcher: {:?}", err),
+    })?;
+    debouncer.watcher().watch(&dir, RecursiveMode::NonRecursive)?;
+    Ok(SettingsFileEvents { _debouncer: debouncer, events })
 }
 
-async fn get_wallet_details(user_settings: &UserSettings) -> (Arc<SecUtf8>, Arc<SecUtf8>) {
+/// Set on SIGTERM (e.g. `docker stop`, systemd) or SIGINT.
+fn spawn_shutdown_listener() -> anyhow::Result<Arc<AtomicBool>> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let requested = shutdown.clone();
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = sigterm.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+        info!("shutdown requested");
+        requested.store(true, Ordering::SeqCst);
+    });
+    Ok(shutdown)
+}
+
+async fn get_wallet_details() -> anyhow::Result<Option<Wallet>> {
     /* Get wallet details */
-    let mut wallet_seed_phrase = SecUtf8::from("".to_string());
-    let mut wallet_acc_address = SecUtf8::from(
-        user_settings
-            .terra_wallet_address
-            .as_ref()
-            .unwrap_or(&"".to_string()),
-    );
-    //  /^terra1[a-z0-9]{38}$/]
-
-    if false
-    /*user_settings.anchor_protocol_auto_repay || user_settings.anchor_protocol_auto_borrow || user_settings.anchor_protocol_auto_stake || user_settings.anchor_protocol_auto_farm*/
-    {
+    let mut signer: Option<Arc<dyn Signer>> = None;
+
+    if let Some(socket_path) = &CONFIG.remote_signer_socket {
+        // ** keys live in a separate process **
+        signer = Some(Arc::new(RemoteSigner::new(&socket_path)));
+    } else if Keystore::exists(&KEYSTORE_PATH) {
         // ** seed phrase needed **
-        wallet_seed_phrase = encrypt_text_with_secret(
-            get_input("Enter your seed phrase (press Enter to skip):").to_string(),
-        );
-        if wallet_acc_address.unsecure().len() != 44 || !user_settings.test {
-            wallet_acc_address = SecUtf8::from(
-                account_from_seed_phrase(
-                    decrypt_text_with_secret(&wallet_seed_phrase),
-                    channels::get_supported_blockchains_from_chain_registry("./chain-registry".to_string(),true,None)
-                        .await.get("terra2")
-                        .unwrap()
-                        .clone(),
-                )
-                .unwrap_or("".to_string()),
-            );
-        }
-    } else if wallet_acc_address.unsecure().len() == 0 {
-        // ** maybe need wallet address **
-        if false
-        /*user_settings.anchor_account_info || user_settings.anchor_protocol_auto_repay || user_settings.anchor_protocol_auto_borrow || user_settings.anchor_protocol_auto_stake || user_settings.anchor_protocol_auto_farm*/
-        {
-            wallet_acc_address = SecUtf8::from(
-                get_input("Enter your wallet address (press Enter to skip):").to_string(),
-            );
+        // running without the key would silently skip every signing task, the bot does not start instead.
+        let keystore_signer = unlock_keystore_signer(&KEYSTORE_PATH, &registry_coin_types(&load_registry_chains(&CHAIN_REGISTRY_PATH)))
+            .map_err(|err| anyhow::anyhow!("Error: Unable to unlock the keystore {}: {}", *KEYSTORE_PATH, err))?;
+        signer = Some(Arc::new(keystore_signer));
+    }
+    // one account per chain in the registry, all derived from the same key.
+    let wallet = signer.map(|signer| {
+        let wallet = Wallet::new(signer, &load_registry_chains(&CHAIN_REGISTRY_PATH));
+        if let Err(err) = wallet.save_accounts(&ACCOUNTS_PATH) {
+            error!("get_wallet_details: unable to save the derived accounts: {:?}", err);
+        }
+        wallet
+    });
+    // tasks get the signer and never see the seed phrase.
+    Ok(wallet)
+}
+
+fn unlock_wallet() -> anyhow::Result<Wallet> {
+    let chains = load_registry_chains(&CHAIN_REGISTRY_PATH);
+    let signer = unlock_keystore_signer(&KEYSTORE_PATH, &registry_coin_types(&chains))?;
+    let wallet = Wallet::new(Arc::new(signer), &chains);
+    wallet.save_accounts(&ACCOUNTS_PATH)?;
+    Ok(wallet)
+}
+
+/// Lists the tasks `run` would schedule with the current settings.
+fn run_plan() -> anyhow::Result<()> {
+    let user_settings: UserSettings = load_user_settings(&SETTINGS_PATH)?;
+    let req = get_requirements(&user_settings);
+    if req.is_empty() {
+        return Err(anyhow::anyhow!("Error: No tasks enabled, run `cosmos-rust-bot generate-features` if the feature list is missing"));
+    }
+    for task in req.iter() {
+        println!("{:<64} {:<28} every {}s", task.name, format!("{:?}", task.kind), task.refresh_rate);
+    }
+    println!("{} tasks", req.len());
+    Ok(())
+}
+
+/// Sends an entries query to the running instance and prints the entries as json.
+#[tracing::instrument(name = "query", skip(filter, order_by, limit))]
+fn run_query(index: String, filter: Vec<(String, String)>, order_by: String, limit: usize) -> anyhow::Result<()> {
+    let request: UserQuery = UserQuery {
+        query_part: QueryPart::EntriesQueryPart(EntriesQueryPart {
+            message: format!("query {}", index),
+            display: "default".to_string(),
+            indices: vec![index],
+            filter: vec![filter],
+            order_by,
+            limit,
+        }),
+        settings_part: SettingsPart {
+            subscribe: None,
+            unsubscribe: None,
+            register: None,
+            user_hash: None,
+        },
+    };
+    match client_send_query_request(&QUERY_SOCKET, request)? {
+        CosmosRustServerValue::Notification(n) => {
+            println!("{}", serde_json::to_string_pretty(&n.entries)?);
+            Ok(())
         }
+        response => Err(anyhow::anyhow!("Error: Unexpected response: {:?}", response)),
     }
-    println!("{esc}c", esc = 27 as char);
+}
+
+fn run_export(path: &str) -> anyhow::Result<()> {
+    let history_store = HistoryStore::new(sled::open(&*HISTORY_STORE_SLED_DB)?);
+    let subscription_db = sled::open(&*CRB_SUBSCRIPTION_STORE_SLED_DB)?;
+    let state = export_state(&history_store, &subscription_db, &SETTINGS_PATH)?;
+    write_atomically(path, &serde_json::to_string_pretty(&state)?)?;
+    println!("Exported to {}", path);
+    Ok(())
+}
+
+fn run_import(path: &str) -> anyhow::Result<()> {
+    let state: StateExport = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let history_store = HistoryStore::new(sled::open(&*HISTORY_STORE_SLED_DB)?);
+    let subscription_db = sled::open(&*CRB_SUBSCRIPTION_STORE_SLED_DB)?;
+    import_state(state, &history_store, &subscription_db, &SETTINGS_PATH)?;
+    println!("Imported {}", path);
+    Ok(())
+}
 
-    // Arc allows multiple references to the same object,
-    // to potentially spawn multiple tasks with access to the seed phrase, while not revealing the string.
-    let wallet_seed_phrase = Arc::new(wallet_seed_phrase);
-    let wallet_acc_address = Arc::new(wallet_acc_address);
-    (wallet_seed_phrase, wallet_acc_address)
+fn run_keys_command(command: &str) -> anyhow::Result<()> {
+    match command {
+        "generate" => {
+            generate_keystore(&KEYSTORE_PATH)?;
+            println!("Keystore saved to {}", *KEYSTORE_PATH);
+            println!("Run `cosmos-rust-bot keys grants` to see the permissions to grant to this key.");
+        }
+        "grants" => {
+            let user_settings: UserSettings = load_user_settings(&SETTINGS_PATH)?;
+            if user_settings.authz.is_empty() {
+                return Err(anyhow::anyhow!("Error: No authz granter configured in {}", *SETTINGS_PATH));
+            }
+            let wallet = unlock_wallet()?;
+            // grants are valid for one year
+            let expiration = Utc::now().timestamp() + 60 * 60 * 24 * 365;
+            for authz in user_settings.authz.iter() {
+                let grantee = &wallet
+                    .account(&authz.blockchain)
+                    .ok_or(anyhow::anyhow!("Error: {} is not in the chain registry at {}", authz.blockchain, *CHAIN_REGISTRY_PATH))?
+                    .address;
+                println!("{}: grant the following permissions from {} to {}", authz.blockchain, authz.granter, grantee);
+                for instruction in grant_instructions(&authz.granter, grantee, expiration) {
+                    println!("    {}", instruction);
+                }
+            }
+        }
+        "accounts" => {
+            // the stored table is shown if present, the keystore is only unlocked to derive it.
+            let accounts = match load_accounts(&ACCOUNTS_PATH) {
+                Ok(accounts) => accounts,
+                Err(_) => unlock_wallet()?.accounts,
+            };
+            for account in accounts {
+                println!("{:<24} {:>6}  {}", account.blockchain, account.coin_type, account.address);
+            }
+        }
+        "import" => {
+            import_keystore(&KEYSTORE_PATH)?;
+            println!("Keystore saved to {}", *KEYSTORE_PATH);
+        }
+        "export" => {
+            let seed_phrase = export_keystore(&KEYSTORE_PATH)?;
+            println!("{}", seed_phrase.unsecure());
+        }
+        "change-passphrase" => {
+            change_keystore_passphrase(&KEYSTORE_PATH)?;
+            println!("Keystore passphrase changed.");
+        }
+        "serve-signer" => {
+            // run as a separate (locked-down) process, the bot connects via --remote-signer-socket or CRB_REMOTE_SIGNER_SOCKET.
+            let socket_path = CONFIG.signer_socket();
+            let chains = load_registry_chains(&CHAIN_REGISTRY_PATH);
+            let signer = unlock_keystore_signer(&KEYSTORE_PATH, &registry_coin_types(&chains))?;
+            println!("Serving signer on {}", socket_path);
+            serve_remote_signer(&socket_path, &signer, &registry_sign_policy(&chains))?;
+        }
+        _ => {
+            return Err(anyhow::anyhow!("Error: Unknown keys command: {} (use generate, import, export, change-passphrase, accounts, grants or serve-signer)", command));
+        }
+    }
+    Ok(())
 }
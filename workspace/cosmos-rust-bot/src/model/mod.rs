@@ -1,18 +1,17 @@
 pub mod requirements;
 
 use requirements::{get_requirements, Feature, TaskSpec, TaskType, UserSettings};
-use secstr::*;
+use bot_library::shared::WatchedAddress;
 
 use std::collections::HashMap;
 
 use anyhow::anyhow;
 
 use heck::ToTitleCase;
-use std::sync::Arc;
 use cosmos_rust_interface::cosmos_rust_package::tokio::sync::Mutex;
-use cosmos_rust_interface::cosmos_rust_package::tokio::task::JoinSet;
+use cosmos_rust_interface::cosmos_rust_package::tokio::task::{AbortHandle, JoinSet};
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use cosmos_rust_interface::cosmos_rust_package::tokio::time::timeout;
 
 use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
@@ -24,7 +23,7 @@ use std::ops::Deref;
 
 use cosmos_rust_interface::utils::entry::*;
 
-use cosmos_rust_interface::blockchain::cosmos::gov::{fetch_params, fetch_proposals, fetch_tally_results};
+use cosmos_rust_interface::blockchain::cosmos::gov::{fetch_proposals, fetch_tally_results};
 use cosmos_rust_interface::utils::response::{ResponseResult, TaskResult};
 use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels;
 use cosmos_rust_interface::cosmos_rust_package::api::core::cosmos::channels::SupportedBlockchain;
@@ -39,10 +38,25 @@ use strum_macros::EnumIter;
 use cosmos_rust_interface::blockchain::cosmos::chain_registry::get_supported_blockchains_from_chain_registry;
 use cosmos_rust_interface::utils::entry::db::{RetrievalMethod, TaskMemoryStore};
 use log::{debug, error, info, trace};
+use lazy_static::lazy_static;
+use tracing::{info_span, Instrument};
 use cosmos_rust_interface::blockchain::cosmos::staking::fetch_pool;
 use cosmos_rust_interface::services::fraud_detection::fraud_detection;
 use cosmos_rust_interface::services::gpt3::gpt3;
 use cosmos_rust_interface::services::link_to_text::link_to_text;
+use crate::blockchain::distribution::fetch_community_pool;
+use crate::blockchain::gov::{fetch_community_pool_spend_proposals, fetch_param_change_proposals, fetch_validator_votes};
+use crate::blockchain::bank::fetch_balance_changes;
+use crate::blockchain::params::fetch_module_params;
+use crate::blockchain::staking::fetch_staking_schedule;
+use crate::store::HistoryStore;
+use crate::account::wallet::Wallet;
+use crate::control::compound::auto_compound;
+use crate::control::executor::Executor;
+use crate::control::journal::{AuditJournal, AUDIT_JOURNAL_PATH};
+use crate::control::vote_mirror::mirror_votes;
+use crate::blockchain::authz::fetch_authz_grants;
+use bot_library::shared::metrics::METRICS;
 
 #[derive(strum_macros::Display, Debug, EnumIter, PartialEq, serde::Serialize)]
 pub enum TaskState {
@@ -110,7 +124,9 @@ pub fn task_meta_data(task_list: Vec<TaskItem>) -> Vec<CosmosRustBotValue> {
     task_meta_data
 }
 
-pub async fn poll_resolved_tasks(join_set: &mut JoinSet<()>) -> usize {
+/// Removes the completed tasks from the join set and from `running`. The attempts of tasks that are no longer
+/// required are forgotten, those of failed tasks that are still required count their retries.
+pub async fn poll_resolved_tasks(join_set: &mut JoinSet<()>, running: &mut HashMap<String, AbortHandle>, req: &[TaskSpec]) -> usize {
 
     debug!("poll_resolved_tasks");
     let mut counter: usize = 0;
@@ -131,16 +147,23 @@ pub async fn poll_resolved_tasks(join_set: &mut JoinSet<()>) -> usize {
             }
         };
     }
+    running.retain(|_, handle| !handle.is_finished());
+    TASK_ATTEMPTS
+        .lock()
+        .unwrap()
+        .retain(|name, _| running.contains_key(name) || req.iter().any(|x| &x.name == name));
     counter
 }
 
 
 pub async fn try_spawn_upcoming_tasks(
     join_set: &mut JoinSet<()>,
+    running: &mut HashMap<String, AbortHandle>,
     task_store: &TaskMemoryStore,
+    history_store: &HistoryStore,
     req: &Vec<TaskSpec>,
     user_settings: &UserSettings,
-    wallet_acc_address: &Arc<SecUtf8>,
+    wallet: &Option<Wallet>,
 ) -> usize {
 
     let task_list: Vec<TaskItem> = get_task_list(task_store,req).await;
@@ -161,9 +184,11 @@ pub async fn try_spawn_upcoming_tasks(
     let number_of_tasks_added =
         spawn_tasks(
             join_set,
+            running,
             task_store,
+            history_store,
             &user_settings,
-            &wallet_acc_address,
+            wallet,
             upcoming_task_spec_list,
         )
         .await;
@@ -176,6 +201,38 @@ pub async fn try_spawn_upcoming_tasks(
     number_of_tasks_added
 }
 
+/// Tasks of `old` that `new` no longer contains, or contains with other arguments.
+pub fn removed_tasks(old: &[TaskSpec], new: &[TaskSpec]) -> Vec<String> {
+    old.iter()
+        .filter(|o| !new.iter().any(|n| n.name == o.name && n.kind == o.kind && n.args == o.args))
+        .map(|o| o.name.to_owned())
+        .collect()
+}
+
+/// Aborts the tasks that are still running, they are marked as failed so they are spawned again (after the failure timeout)
+/// should they be required again. Returns the number of tasks that were cancelled.
+/// A task that completed since the last poll counts as cancelled too, its result is replaced, as it is no longer required.
+pub fn cancel_tasks(names: &[String], running: &mut HashMap<String, AbortHandle>, task_store: &TaskMemoryStore) -> usize {
+    let mut count = 0;
+    for name in names {
+        // a task that is required again starts with its first attempt.
+        TASK_ATTEMPTS.lock().unwrap().remove(name);
+        if let Some(handle) = running.remove(name) {
+            handle.abort();
+            task_store.push(
+                name,
+                Maybe::<ResponseResult> {
+                    data: Err(MaybeError::AnyhowError("Cancelled: no longer required by the settings".to_string())),
+                    timestamp: Utc::now().timestamp(),
+                }
+            ).ok();
+            info!("cancelled task {}", name);
+            count += 1;
+        }
+    }
+    count
+}
+
 pub async fn get_task_meta_data(
                                 task_store: &TaskMemoryStore,
                                 req: &Vec<TaskSpec>
@@ -273,11 +330,42 @@ pub async fn get_task_list(
     task_list.sort_by_key(|k| k.timestamp);
     task_list
 }
+lazy_static! {
+    /// task name -> runs since it last resolved, failed tasks are retried after 1min.
+    static ref TASK_ATTEMPTS: std::sync::Mutex<HashMap<String, u64>> = std::sync::Mutex::new(HashMap::new());
+}
+
+/// 1 for the first run (or the first after a success), then 2, 3, .. while it keeps failing.
+fn next_attempt(name: &str) -> u64 {
+    let mut attempts = TASK_ATTEMPTS.lock().unwrap();
+    let attempt = attempts.entry(name.to_string()).or_insert(0);
+    *attempt += 1;
+    *attempt
+}
+
+/// Durations by task kind, failures by kind and chain.
+fn record_task_metrics(kind: &str, chain: &str, seconds: f64, ok: bool) {
+    METRICS.observe("crb_task_duration_seconds", "Run time of finished tasks.", &[("kind", kind)], seconds);
+    if !ok {
+        METRICS.inc_counter("crb_task_failures_total", "Failed tasks by chain.", &[("kind", kind), ("chain", chain)], 1.0);
+    }
+}
+
+/// Task counts by TaskState, every state is reported (0 if there is no task in it).
+pub fn record_task_state_metrics(task_list: &[TaskItem]) {
+    for state in TaskState::iter() {
+        let count = task_list.iter().filter(|x| x.state == state).count();
+        METRICS.set_gauge("crb_tasks", "Tasks by state.", &[("state", &state.to_string())], count as f64);
+    }
+}
+
 async fn spawn_tasks(
     join_set: &mut JoinSet<()>,
+    running: &mut HashMap<String, AbortHandle>,
     task_store: &TaskMemoryStore,
-    _user_settings: &UserSettings,
-    _wallet_acc_address: &Arc<SecUtf8>,
+    history_store: &HistoryStore,
+    user_settings: &UserSettings,
+    wallet: &Option<Wallet>,
     to_update: Vec<&TaskSpec>,
 ) -> usize {
 
@@ -287,6 +375,7 @@ async fn spawn_tasks(
     let supported_blockchains = match task_store.get("internal_chain_registry",&RetrievalMethod::GetOk) {
         Ok(Maybe{data: Ok(ResponseResult::ChainRegistry(chain_registry)), timestamp: t }) => {
             debug!("spawn_tasks: chain_registry available");
+            METRICS.set_ready(true);
             Some(chain_registry)
         },
         Err(_) | Ok(Maybe{ .. }) => {
@@ -327,12 +416,107 @@ async fn spawn_tasks(
                         .clone();
                     f = Some(Box::pin(fetch_tally_results(blockchain, status, task_store.clone(), req.name.clone())));
                 }
-                TaskType::Params => {
-                    let params_type = req.args["params_type"].as_str().unwrap().to_string();
+                TaskType::ModuleParams => {
+                    let module = req.args["module"].as_str().unwrap().to_string();
                     let blockchain = supported_blockchains.get(req.args["blockchain"].as_str().unwrap())
                         .unwrap()
                         .clone();
-                    f = Some(Box::pin(fetch_params(blockchain, params_type, task_store.clone(), req.name.clone())));
+                    f = Some(Box::pin(fetch_module_params(blockchain, module, history_store.clone(), req.name.clone())));
+                }
+                TaskType::ParamChangeProposals => {
+                    let blockchain = supported_blockchains.get(req.args["blockchain"].as_str().unwrap())
+                        .unwrap()
+                        .clone();
+                    f = Some(Box::pin(fetch_param_change_proposals(blockchain, history_store.clone(), req.name.clone())));
+                }
+                TaskType::CommunityPool => {
+                    let blockchain = supported_blockchains.get(req.args["blockchain"].as_str().unwrap())
+                        .unwrap()
+                        .clone();
+                    f = Some(Box::pin(fetch_community_pool(blockchain, history_store.clone(), req.name.clone())));
+                }
+                TaskType::CommunityPoolSpendProposals => {
+                    let blockchain = supported_blockchains.get(req.args["blockchain"].as_str().unwrap())
+                        .unwrap()
+                        .clone();
+                    f = Some(Box::pin(fetch_community_pool_spend_proposals(blockchain, history_store.clone(), req.name.clone())));
+                }
+                TaskType::ValidatorVotes => {
+                    let blockchain = supported_blockchains.get(req.args["blockchain"].as_str().unwrap())
+                        .unwrap()
+                        .clone();
+                    f = Some(Box::pin(fetch_validator_votes(blockchain, history_store.clone(), req.name.clone())));
+                }
+                TaskType::AutoCompound => {
+                    let name = req.args["blockchain"].as_str().unwrap();
+                    let settings = user_settings.auto_compound.iter().find(|x| x.blockchain == name);
+                    // without a wallet or settings for this chain there is nothing to compound.
+                    if let (Some(settings), Some(wallet)) = (settings, wallet) {
+                        let blockchain = supported_blockchains.get(name)
+                            .unwrap()
+                            .clone();
+                        let granter = user_settings.authz_granter(name).map(|x| x.granter.to_owned());
+                        let executor = Executor {
+                            wallet: wallet.clone(),
+                            policy: user_settings.policy.clone(),
+                            journal: AuditJournal::new(&AUDIT_JOURNAL_PATH),
+                            simulate_only: user_settings.test,
+                        };
+                        f = Some(Box::pin(auto_compound(blockchain, settings.clone(), executor, granter, history_store.clone(), req.name.clone())));
+                    }
+                }
+                TaskType::VoteMirror => {
+                    let name = req.args["blockchain"].as_str().unwrap();
+                    let settings = user_settings.vote_mirror.iter().find(|x| x.blockchain == name);
+                    if let (Some(settings), Some(wallet)) = (settings, wallet) {
+                        let blockchain = supported_blockchains.get(name)
+                            .unwrap()
+                            .clone();
+                        let granter = user_settings.authz_granter(name).map(|x| x.granter.to_owned());
+                        let executor = Executor {
+                            wallet: wallet.clone(),
+                            policy: user_settings.policy.clone(),
+                            journal: AuditJournal::new(&AUDIT_JOURNAL_PATH),
+                            simulate_only: user_settings.test,
+                        };
+                        f = Some(Box::pin(mirror_votes(blockchain, settings.clone(), executor, granter, history_store.clone(), req.name.clone())));
+                    }
+                }
+                TaskType::StakingSchedule => {
+                    let name = req.args["blockchain"].as_str().unwrap();
+                    let watched: Vec<WatchedAddress> = user_settings.watched_addresses.iter().filter(|x| x.blockchain == name).cloned().collect();
+                    if !watched.is_empty() {
+                        let blockchain = supported_blockchains.get(name)
+                            .unwrap()
+                            .clone();
+                        f = Some(Box::pin(fetch_staking_schedule(blockchain, watched, history_store.clone(), req.name.clone())));
+                    }
+                }
+                TaskType::BalanceChanges => {
+                    let name = req.args["blockchain"].as_str().unwrap();
+                    let watched: Vec<WatchedAddress> = user_settings.watched_addresses.iter().filter(|x| x.blockchain == name).cloned().collect();
+                    if !watched.is_empty() {
+                        let blockchain = supported_blockchains.get(name)
+                            .unwrap()
+                            .clone();
+                        f = Some(Box::pin(fetch_balance_changes(blockchain, watched, history_store.clone(), req.name.clone())));
+                    }
+                }
+                TaskType::AuthzGrants => {
+                    let name = req.args["blockchain"].as_str().unwrap();
+                    if let (Some(authz), Some(wallet)) = (user_settings.authz_granter(name), wallet) {
+                        match wallet.account(name) {
+                            Some(account) => {
+                                let blockchain = supported_blockchains.get(name)
+                                    .unwrap()
+                                    .clone();
+                                f = Some(Box::pin(fetch_authz_grants(blockchain, authz.granter.to_owned(), account.address.to_owned(), history_store.clone(), req.name.clone())));
+                            }
+                            None => {
+                                error!("spawn_tasks: no derived account for {}", name);
+                            }
+                        }
+                    }
                 }
                 TaskType::Pool => {
                     let blockchain = supported_blockchains.get(req.args["blockchain"].as_str().unwrap())
@@ -368,14 +552,23 @@ async fn spawn_tasks(
 
             let task_store_copy = task_store.clone();
             let key = req.name.clone();
-            join_set.spawn(async move {
+            let kind = format!("{:?}", req.kind);
+            let chain = req.args.get("blockchain").and_then(|x| x.as_str()).unwrap_or("none").to_string();
+            let span = info_span!("task", task = %key, kind = %kind, chain = %chain, attempt = next_attempt(&key));
+            let handle = join_set.spawn(async move {
                 {
+                    let started = Instant::now();
                     let result = m.await;
+                    record_task_metrics(&kind, &chain, started.elapsed().as_secs_f64(), result.is_ok());
                     let result: Maybe<ResponseResult> = Maybe {
                         data: match result {
-                            Ok(data) => Ok(ResponseResult::TaskResult(data)),
+                            Ok(data) => {
+                                TASK_ATTEMPTS.lock().unwrap().remove(&key);
+                                tracing::info!(duration_ms = started.elapsed().as_millis() as u64, "task resolved");
+                                Ok(ResponseResult::TaskResult(data))
+                            },
                             Err(err) => {
-                                error!("Task {} failed: {:?}",&key, &err);
+                                tracing::error!(duration_ms = started.elapsed().as_millis() as u64, error = ?err, "task failed");
                                 Err(MaybeError::AnyhowError(err.to_string()))
                             },
                         },
@@ -383,7 +576,8 @@ async fn spawn_tasks(
                     };
                     task_store_copy.push(&key,result).ok();
                 }
-            });
+            }.instrument(span));
+            running.insert(req.name.clone(), handle);
             count += 1;
         }
     }
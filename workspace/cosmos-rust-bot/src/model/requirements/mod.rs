@@ -1,3 +1,5 @@
+use bot_library::shared::config::CONFIG;
+use bot_library::shared::migration::{migrate_file, Migration};
 use bot_library::shared::UserSettings as UserSettingsImported;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -6,7 +8,7 @@ use std::fs;
 
 use lazy_static::lazy_static;
 
-const TASKS_PATH: &str = "./tmp/cosmos-rust-bot-feature-list.json";
+use crate::blockchain::params::PARAM_MODULES;
 
 pub type UserSettings = UserSettingsImported;
 
@@ -25,15 +27,10 @@ const PROPOSAL_STATUS_LIST: [&str;5] = [
     /*"nil",*/ // TODO query only if no other states of that proposal exist.
 ];
 
-const PARAM_TYPES: [&str; 3] = [
-    "voting",
-    "tallying",
-    "deposit",
-];
-
 lazy_static! {
+    static ref TASKS_PATH: String = CONFIG.data_path("cosmos-rust-bot-feature-list.json");
     static ref LIST_BLOCKCHAINS: Vec<String> = {
-        let data = std::fs::read_to_string("./tmp/supported_blockchains.json").expect("Unable to read file");
+        let data = std::fs::read_to_string(CONFIG.data_path("supported_blockchains.json")).expect("Unable to read file");
         let supported_blockchains: HashMap<String, serde_json::Value> = serde_json::from_str(&data).expect("Unable to parse JSON");
         supported_blockchains.into_keys().collect()
     };
@@ -47,7 +44,16 @@ pub enum TaskType {
     LinkToText,
     GovernanceProposals,
     TallyResults,
-    Params,
+    ModuleParams,
+    ParamChangeProposals,
+    CommunityPool,
+    CommunityPoolSpendProposals,
+    ValidatorVotes,
+    AutoCompound,
+    AuthzGrants,
+    VoteMirror,
+    StakingSchedule,
+    BalanceChanges,
     Pool,
     None,
 }
@@ -58,6 +64,22 @@ pub struct Feature {
     requirements: Vec<TaskSpec>,
 }
 
+/// The feature list file, versioned like the settings (see bot_library::shared::migration).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeatureList {
+    version: u64,
+    features: Vec<Feature>,
+}
+
+/// v0 -> v1: the plain array of features is wrapped into an object, so the file can carry a version.
+fn feature_list_v0_wrap_features(value: &mut serde_json::Value) {
+    if value.is_array() {
+        *value = json!({ "features": value.take() });
+    }
+}
+
+const FEATURE_LIST_MIGRATIONS: [Migration; 1] = [feature_list_v0_wrap_features];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaskSpec {
     pub name: String,
@@ -67,10 +89,12 @@ pub struct TaskSpec {
 }
 
 pub fn feature_list() -> Vec<Feature> {
-    let feature_list: Vec<Feature> = match fs::read_to_string(TASKS_PATH)
+    let feature_list: Vec<Feature> = match fs::read_to_string(&*TASKS_PATH)
     {
-        Ok(file) => match serde_json::from_str(&file) {
-            Ok(res) => res,
+        Ok(file) => match migrate_file(&TASKS_PATH, &file, &FEATURE_LIST_MIGRATIONS)
+            .and_then(|value| Ok(serde_json::from_value::<FeatureList>(value)?))
+        {
+            Ok(res) => res.features,
             Err(err) => {
                 println!("{:?}", err);
                 Default::default()
@@ -91,20 +115,36 @@ pub fn feature_list_to_file() -> anyhow::Result<()> {
     let mut tally_results: Vec<TaskSpec> = Vec::new();
     let mut params: Vec<TaskSpec> = Vec::new();
     let mut pool: Vec<TaskSpec> = Vec::new();
+    let mut community_pool: Vec<TaskSpec> = Vec::new();
+    let mut validator_votes: Vec<TaskSpec> = Vec::new();
+    let mut auto_compound: Vec<TaskSpec> = Vec::new();
+    let mut authz_grants: Vec<TaskSpec> = Vec::new();
+    let mut vote_mirror: Vec<TaskSpec> = Vec::new();
+    let mut staking_reminders: Vec<TaskSpec> = Vec::new();
+    let mut balance_alerts: Vec<TaskSpec> = Vec::new();
 
     for blockchain in LIST_BLOCKCHAINS.iter() {
-        for params_type in &PARAM_TYPES {
+        for module in &PARAM_MODULES {
             let task = TaskSpec {
-                kind: TaskType::Params,
-                name: format!("{}_params_{}", blockchain, params_type),
+                kind: TaskType::ModuleParams,
+                name: format!("{}_module_params_{}", blockchain, module),
                 args: json!({
                     "blockchain": blockchain,
-                    "params_type": params_type
+                    "module": module
                 }),
                 refresh_rate: MINUTES_10,
             };
             params.push(task);
         }
+        let task = TaskSpec {
+            kind: TaskType::ParamChangeProposals,
+            name: format!("{}_param_change_proposals", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_10,
+        };
+        params.push(task);
         let task = TaskSpec {
             kind: TaskType::Pool,
             name: format!("pool_{}", blockchain),
@@ -114,6 +154,78 @@ pub fn feature_list_to_file() -> anyhow::Result<()> {
             refresh_rate: MINUTES_10,
         };
         pool.push(task);
+        let task = TaskSpec {
+            kind: TaskType::CommunityPool,
+            name: format!("{}_community_pool", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_10,
+        };
+        community_pool.push(task);
+        let task = TaskSpec {
+            kind: TaskType::CommunityPoolSpendProposals,
+            name: format!("{}_community_pool_spend_proposals", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_10,
+        };
+        community_pool.push(task);
+        let task = TaskSpec {
+            kind: TaskType::ValidatorVotes,
+            name: format!("{}_validator_votes_voting_period_proposals", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_10,
+        };
+        validator_votes.push(task);
+        let task = TaskSpec {
+            kind: TaskType::AutoCompound,
+            name: format!("{}_auto_compound", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_10,
+        };
+        auto_compound.push(task);
+        let task = TaskSpec {
+            kind: TaskType::AuthzGrants,
+            name: format!("{}_authz_grants", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_10,
+        };
+        authz_grants.push(task);
+        let task = TaskSpec {
+            kind: TaskType::VoteMirror,
+            name: format!("{}_vote_mirror", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_5,
+        };
+        vote_mirror.push(task);
+        let task = TaskSpec {
+            kind: TaskType::StakingSchedule,
+            name: format!("{}_staking_schedule", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_10,
+        };
+        staking_reminders.push(task);
+        let task = TaskSpec {
+            kind: TaskType::BalanceChanges,
+            name: format!("{}_balance_changes", blockchain),
+            args: json!({
+                    "blockchain": blockchain,
+                }),
+            refresh_rate: MINUTES_5,
+        };
+        balance_alerts.push(task);
         let task = TaskSpec {
             kind: TaskType::TallyResults,
             name: format!("{}_tally_results_{}_proposals", blockchain, "voting_period"),
@@ -154,6 +266,34 @@ pub fn feature_list_to_file() -> anyhow::Result<()> {
         name: "governance_proposal_params".to_string(),
         requirements: params,
     });
+    feature_list.push(Feature {
+        name: "governance_proposal_community_pool".to_string(),
+        requirements: community_pool,
+    });
+    feature_list.push(Feature {
+        name: "governance_proposal_validator_votes".to_string(),
+        requirements: validator_votes,
+    });
+    feature_list.push(Feature {
+        name: "auto_compound".to_string(),
+        requirements: auto_compound,
+    });
+    feature_list.push(Feature {
+        name: "authz_grants".to_string(),
+        requirements: authz_grants,
+    });
+    feature_list.push(Feature {
+        name: "vote_mirror".to_string(),
+        requirements: vote_mirror,
+    });
+    feature_list.push(Feature {
+        name: "staking_reminders".to_string(),
+        requirements: staking_reminders,
+    });
+    feature_list.push(Feature {
+        name: "balance_alerts".to_string(),
+        requirements: balance_alerts,
+    });
 
     let mut chain_registry: Vec<TaskSpec> = Vec::new();
     let task = TaskSpec {
@@ -216,8 +356,12 @@ pub fn feature_list_to_file() -> anyhow::Result<()> {
 
     // TODO: create new feature task that gets all wallet info.
 
+    let feature_list = FeatureList {
+        version: FEATURE_LIST_MIGRATIONS.len() as u64,
+        features: feature_list,
+    };
     let line = format!("{}", serde_json::to_string(&feature_list).unwrap());
-    fs::write(TASKS_PATH, &line).unwrap();
+    fs::write(&*TASKS_PATH, &line).unwrap();
     Ok(())
 }
 
@@ -228,6 +372,21 @@ fn feature_name_list(user_settings: &UserSettings) -> Vec<String> {
         args.push("governance_proposal_tally_results".to_string());
         args.push("governance_proposal_pool".to_string());
         args.push("governance_proposal_params".to_string());
+        args.push("governance_proposal_community_pool".to_string());
+        args.push("governance_proposal_validator_votes".to_string());
+    }
+    if !user_settings.auto_compound.is_empty() {
+        args.push("auto_compound".to_string());
+    }
+    if !user_settings.authz.is_empty() {
+        args.push("authz_grants".to_string());
+    }
+    if !user_settings.vote_mirror.is_empty() {
+        args.push("vote_mirror".to_string());
+    }
+    if !user_settings.watched_addresses.is_empty() {
+        args.push("staking_reminders".to_string());
+        args.push("balance_alerts".to_string());
     }
     args.push("chain_registry".to_string());
     args.push("fraud_detection".to_string());
@@ -245,6 +404,19 @@ pub fn get_feature_list(user_settings: &UserSettings) -> Vec<Feature> {
     features
 }
 
+/// Tasks of features that are configured per chain are only required on the chains they are configured for,
+/// elsewhere they would never be spawned and stay listed as upcoming.
+fn is_configured(user_settings: &UserSettings, task: &TaskSpec) -> bool {
+    let blockchain = task.args.get("blockchain").and_then(|x| x.as_str()).unwrap_or("");
+    match task.kind {
+        TaskType::AutoCompound => user_settings.auto_compound.iter().any(|x| x.blockchain == blockchain),
+        TaskType::AuthzGrants => user_settings.authz_granter(blockchain).is_some(),
+        TaskType::VoteMirror => user_settings.vote_mirror.iter().any(|x| x.blockchain == blockchain),
+        TaskType::StakingSchedule | TaskType::BalanceChanges => user_settings.watched_addresses.iter().any(|x| x.blockchain == blockchain),
+        _ => true,
+    }
+}
+
 pub fn get_requirements(user_settings: &UserSettings) -> Vec<TaskSpec> {
     let mut features: Vec<Feature> = get_feature_list(user_settings);
     let mut req: Vec<TaskSpec> = Vec::new();
@@ -252,6 +424,7 @@ pub fn get_requirements(user_settings: &UserSettings) -> Vec<TaskSpec> {
         let mut no_duplicates = f
             .requirements
             .into_iter()
+            .filter(|x| is_configured(user_settings, x))
             .filter(|x| req.iter().filter(|y| y.name == x.name).count() == 0)
             .collect();
         req.append(&mut no_duplicates);
@@ -261,7 +434,7 @@ pub fn get_requirements(user_settings: &UserSettings) -> Vec<TaskSpec> {
 
 #[cfg(test)]
 mod test {
-
+    use super::*;
     use cosmos_rust_interface::cosmos_rust_package::tokio as tokio;
     // cargo test -- --nocapture
 
@@ -275,4 +448,41 @@ mod test {
         println!("{:?}", super::feature_list());
         Ok(())
     }
+
+    fn task(kind: TaskType, blockchain: &str) -> TaskSpec {
+        TaskSpec {
+            name: format!("{}_{:?}", blockchain, kind),
+            kind,
+            refresh_rate: 600,
+            args: serde_json::json!({ "blockchain": blockchain }),
+        }
+    }
+
+    #[test]
+    fn per_chain_tasks_are_only_required_where_configured() {
+        let user_settings: UserSettings = serde_json::from_value(serde_json::json!({
+            "version": 2,
+            "governance_proposal_notifications": true,
+            "pause_requested": false,
+            "hot_reload": false,
+            "test": true,
+            "auto_compound": [{"blockchain": "osmosis", "denom": "uosmo", "threshold": 1000000, "gas_limit": 400000, "fee": 5000, "gas_budget": 1000000}],
+            "authz": [{"blockchain": "juno", "granter": "juno1granter"}],
+            "vote_mirror": [{"blockchain": "juno", "validator": "junovaloper1validator", "delay": 3600, "fee_denom": "ujuno", "fee": 5000, "gas_limit": 200000}],
+            "watched_addresses": [{"blockchain": "cosmoshub", "address": "cosmos1watched"}]
+        }))
+        .unwrap();
+
+        assert!(is_configured(&user_settings, &task(TaskType::AutoCompound, "osmosis")));
+        assert!(!is_configured(&user_settings, &task(TaskType::AutoCompound, "juno")));
+        assert!(is_configured(&user_settings, &task(TaskType::AuthzGrants, "juno")));
+        assert!(!is_configured(&user_settings, &task(TaskType::AuthzGrants, "osmosis")));
+        assert!(is_configured(&user_settings, &task(TaskType::VoteMirror, "juno")));
+        assert!(!is_configured(&user_settings, &task(TaskType::VoteMirror, "osmosis")));
+        assert!(is_configured(&user_settings, &task(TaskType::StakingSchedule, "cosmoshub")));
+        assert!(!is_configured(&user_settings, &task(TaskType::StakingSchedule, "juno")));
+        assert!(is_configured(&user_settings, &task(TaskType::BalanceChanges, "cosmoshub")));
+        assert!(!is_configured(&user_settings, &task(TaskType::BalanceChanges, "juno")));
+        assert!(is_configured(&user_settings, &task(TaskType::ModuleParams, "juno")));
+    }
 }
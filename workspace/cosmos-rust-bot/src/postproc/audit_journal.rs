@@ -0,0 +1,63 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
+use heck::ToUpperCamelCase;
+
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::control::journal::AuditJournal;
+
+const MAX_ENTRIES: usize = 100;
+
+fn format_amounts(amounts: &[(String, u64)]) -> String {
+    amounts
+        .iter()
+        .map(|(denom, amount)| format!("{} {}", amount, denom))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Makes the latest audit journal entries queryable, kind is the policy decision.
+pub fn audit_journal_entries(journal: &AuditJournal) -> Vec<CosmosRustBotValue> {
+    let entries = journal.entries();
+    let items = entries
+        .iter()
+        .rev()
+        .take(MAX_ENTRIES)
+        .enumerate()
+        .map(|(i, entry)| {
+            let msgs = entry
+                .msgs
+                .iter()
+                .map(|m| {
+                    let mut line = m.type_url.to_owned();
+                    if !m.amounts.is_empty() {
+                        line = format!("{} {}", line, format_amounts(&m.amounts));
+                    }
+                    for address in m.validators.iter().chain(m.recipients.iter()) {
+                        line = format!("{} {}", line, address);
+                    }
+                    line
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            MetaDataItem {
+                kind: entry.decision.to_owned(),
+                state: entry.blockchain.to_upper_camel_case(),
+                value: format!("{}_{}", entry.timestamp, entries.len() - i),
+                summary: format!(
+                    "📒 {} {}{} [{}]\nsigner: {}\nfee: {}{}{}\n\n{}",
+                    entry.blockchain,
+                    entry.decision,
+                    if entry.simulate_only { " (test mode)" } else { "" },
+                    Utc.timestamp(entry.timestamp, 0),
+                    entry.signer,
+                    format_amounts(&entry.fee),
+                    if entry.reason.is_empty() { "".to_string() } else { format!("\nreason: {}", entry.reason) },
+                    entry.txhash.as_ref().map(|h| format!("\ntx: {}", h)).unwrap_or_default(),
+                    msgs
+                ),
+            }
+        })
+        .collect::<Vec<MetaDataItem>>();
+    meta_data_entries("audit_journal", items)
+}
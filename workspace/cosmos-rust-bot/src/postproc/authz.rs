@@ -0,0 +1,51 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
+use heck::ToUpperCamelCase;
+
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::blockchain::authz::{AuthzGrants, AUTHZ_GRANTS_TREE, REQUIRED_MSG_TYPES};
+use crate::store::HistoryStore;
+
+/// Grants expiring within this period are reported as "expiring".
+const EXPIRY_WARNING_PERIOD: i64 = 60 * 60 * 24 * 7;
+
+/// Reports the state of each permission the bot needs: active, expiring, expired or missing.
+pub fn authz_grant_alerts(history_store: &HistoryStore) -> Vec<CosmosRustBotValue> {
+    let now = Utc::now().timestamp();
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    for key in history_store.history_keys(AUTHZ_GRANTS_TREE) {
+        let grants = match history_store.latest::<AuthzGrants>(AUTHZ_GRANTS_TREE, &key) {
+            Some((_, grants)) => grants,
+            None => {
+                continue;
+            }
+        };
+        for msg_type_url in REQUIRED_MSG_TYPES {
+            let expiration = grants
+                .grants
+                .iter()
+                .filter(|g| g.msg_type_url == msg_type_url)
+                .map(|g| g.expiration.unwrap_or(i64::MAX))
+                .max();
+            let (kind, detail) = match expiration {
+                None => ("missing", "not granted".to_string()),
+                Some(i64::MAX) => ("active", "granted without expiration".to_string()),
+                Some(t) if t <= now => ("expired", format!("expired [{}]", Utc.timestamp(t, 0))),
+                Some(t) if t - now <= EXPIRY_WARNING_PERIOD => ("expiring", format!("expires soon [{}]", Utc.timestamp(t, 0))),
+                Some(t) => ("active", format!("expires [{}]", Utc.timestamp(t, 0))),
+            };
+            items.push(MetaDataItem {
+                kind: kind.to_string(),
+                state: grants.blockchain.to_upper_camel_case(),
+                value: format!("{}_{}", key, msg_type_url),
+                summary: format!(
+                    "🔑 {} authz grant {} from {} to {}: {}",
+                    grants.blockchain, msg_type_url, grants.granter, grants.grantee, detail
+                ),
+            });
+        }
+    }
+    meta_data_entries("authz_grants", items)
+}
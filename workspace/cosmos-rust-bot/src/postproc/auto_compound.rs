@@ -0,0 +1,47 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
+use heck::ToUpperCamelCase;
+
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::control::compound::{CompoundReport, AUTO_COMPOUND_TREE};
+use crate::store::HistoryStore;
+
+const MAX_REPORTS: usize = 10;
+
+/// Lists the latest auto-compound transactions per chain, simulated ones in test mode.
+pub fn auto_compound_reports(history_store: &HistoryStore) -> Vec<CosmosRustBotValue> {
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    for blockchain in history_store.history_keys(AUTO_COMPOUND_TREE) {
+        let history = history_store.history::<CompoundReport>(AUTO_COMPOUND_TREE, &blockchain);
+        for (timestamp, report) in history.iter().rev().take(MAX_REPORTS) {
+            let restaked: u64 = report.restake.iter().map(|(_, amount)| amount).sum();
+            let lines = report
+                .restake
+                .iter()
+                .map(|(validator, amount)| format!("{}: {} {}", validator, amount, report.denom))
+                .collect::<Vec<String>>()
+                .join("\n");
+            items.push(MetaDataItem {
+                kind: report.outcome.to_owned(),
+                state: blockchain.to_upper_camel_case(),
+                value: format!("{}_{}", blockchain, timestamp),
+                summary: format!(
+                    "♻️ {} auto-compound {} [{}]\nRestake {} {} (fee {}, gas {}){}{}\n\n{}",
+                    blockchain,
+                    report.outcome,
+                    Utc.timestamp(*timestamp, 0),
+                    restaked,
+                    report.denom,
+                    report.fee,
+                    report.gas_used,
+                    report.txhash.as_ref().map(|h| format!("\ntx: {}", h)).unwrap_or_default(),
+                    if report.note.is_empty() { "".to_string() } else { format!("\n{}", report.note) },
+                    lines
+                ),
+            });
+        }
+    }
+    meta_data_entries("auto_compound", items)
+}
@@ -0,0 +1,73 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
+use heck::ToUpperCamelCase;
+use std::collections::HashMap;
+
+use bot_library::shared::WatchedAddress;
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::blockchain::bank::{above_min_amounts, BalanceSnapshot, IncomingTransfer, BALANCES_TREE, INCOMING_TRANSFERS_TREE};
+use crate::store::HistoryStore;
+
+const MAX_CHANGES: usize = 10;
+
+fn amounts_to_string(amounts: &[(String, String)]) -> String {
+    amounts
+        .iter()
+        .map(|(denom, amount)| format!("{} {}", amount, denom))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Reports the incoming transfers (sender, amount, memo) and the balance changes of the watched addresses.
+/// Changes below the minimum amounts of the address are left out.
+pub fn balance_alerts(history_store: &HistoryStore, watched_addresses: &[WatchedAddress]) -> Vec<CosmosRustBotValue> {
+    let no_minimum: HashMap<String, u64> = HashMap::new();
+    let min_amounts = |blockchain: &str, address: &str| {
+        watched_addresses
+            .iter()
+            .find(|w| w.blockchain == blockchain && w.address == address)
+            .map(|w| &w.min_amounts)
+            .unwrap_or(&no_minimum)
+    };
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    for (key, transfer) in history_store.values::<IncomingTransfer>(INCOMING_TRANSFERS_TREE) {
+        items.push(MetaDataItem {
+            kind: "incoming_transfer".to_string(),
+            state: transfer.blockchain.to_upper_camel_case(),
+            value: key,
+            summary: format!(
+                "📥 {} received {} from {} [{}]{}\ntx: {}",
+                transfer.address,
+                amounts_to_string(&transfer.amount),
+                transfer.sender,
+                transfer.timestamp,
+                if transfer.memo.is_empty() { "".to_string() } else { format!("\nmemo: {}", transfer.memo) },
+                transfer.txhash
+            ),
+        });
+    }
+
+    for key in history_store.history_keys(BALANCES_TREE) {
+        let history = history_store.history::<BalanceSnapshot>(BALANCES_TREE, &key);
+        for (timestamp, snapshot) in history.iter().rev().take(MAX_CHANGES) {
+            if snapshot.changes.is_empty() || !above_min_amounts(&snapshot.changes, min_amounts(&snapshot.blockchain, &snapshot.address)) {
+                continue;
+            }
+            items.push(MetaDataItem {
+                kind: "balance_change".to_string(),
+                state: snapshot.blockchain.to_upper_camel_case(),
+                value: format!("{}_{}", key, timestamp),
+                summary: format!(
+                    "💰 {} balance of {} changed by {} [{}]",
+                    snapshot.blockchain,
+                    snapshot.address,
+                    amounts_to_string(&snapshot.changes),
+                    Utc.timestamp(*timestamp, 0)
+                ),
+            });
+        }
+    }
+    meta_data_entries("balance_alerts", items)
+}
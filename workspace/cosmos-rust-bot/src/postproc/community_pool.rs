@@ -0,0 +1,127 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
+use heck::ToUpperCamelCase;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::blockchain::distribution::{CommunityPool, COMMUNITY_POOL_TREE};
+use crate::blockchain::gov::{CommunityPoolSpendProposalInfo, COMMUNITY_POOL_SPEND_PROPOSALS_TREE};
+use crate::store::HistoryStore;
+
+const OPEN_STATUS: [&str; 2] = ["DepositPeriod", "VotingPeriod"];
+
+fn parse_amount(amount: &str) -> Decimal {
+    Decimal::from_str(amount).unwrap_or(Decimal::ZERO)
+}
+
+fn format_amounts(amounts: &BTreeMap<String, Decimal>) -> String {
+    if amounts.is_empty() {
+        return "-".to_string();
+    }
+    amounts
+        .iter()
+        .map(|(denom, amount)| format!("{} {}", amount.round_dp(0), denom))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn add_amounts<'a>(total: &mut BTreeMap<String, Decimal>, amount: impl IntoIterator<Item = (&'a String, &'a String)>) {
+    for (denom, amount) in amount {
+        *total.entry(denom.to_owned()).or_insert(Decimal::ZERO) += parse_amount(amount);
+    }
+}
+
+/// The requested amount relative to the current community pool balance, per denom.
+pub fn share_of_pool(pool: &CommunityPool, amount: &Vec<(String, String)>) -> Vec<(String, Option<Decimal>)> {
+    amount
+        .iter()
+        .map(|(denom, amount)| {
+            let balance = pool.get(denom).map(|b| parse_amount(b)).unwrap_or(Decimal::ZERO);
+            let share = if balance.is_zero() {
+                None
+            } else {
+                Some((parse_amount(amount) / balance * Decimal::ONE_HUNDRED).round_dp(2))
+            };
+            (denom.to_owned(), share)
+        })
+        .collect()
+}
+
+/// Annotates community pool spend proposals with the share of the pool they request,
+/// together with the running totals of open and passed spend requests per chain.
+pub fn community_pool_spend_context(history_store: &HistoryStore) -> Vec<CosmosRustBotValue> {
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    let mut proposals_by_chain: BTreeMap<String, Vec<CommunityPoolSpendProposalInfo>> = BTreeMap::new();
+    for (_, proposal) in history_store.values::<CommunityPoolSpendProposalInfo>(COMMUNITY_POOL_SPEND_PROPOSALS_TREE) {
+        proposals_by_chain.entry(proposal.blockchain.to_owned()).or_insert_with(Vec::new).push(proposal);
+    }
+
+    for blockchain in history_store.history_keys(COMMUNITY_POOL_TREE) {
+        let history = history_store.tail::<CommunityPool>(COMMUNITY_POOL_TREE, &blockchain, 2);
+        let (_, pool) = match history.last() {
+            Some(latest) => latest.to_owned(),
+            None => {
+                continue;
+            }
+        };
+        let state = blockchain.to_upper_camel_case();
+
+        let mut balance: BTreeMap<String, Decimal> = BTreeMap::new();
+        add_amounts(&mut balance, pool.iter());
+        let mut summary = format!("💰 {} community pool: {}", blockchain, format_amounts(&balance));
+        if history.len() > 1 {
+            let (timestamp, previous) = &history[history.len() - 2];
+            let mut previous_balance: BTreeMap<String, Decimal> = BTreeMap::new();
+            add_amounts(&mut previous_balance, previous.iter());
+            summary = format!("{}\n(previous snapshot [{}]: {})", summary, Utc.timestamp(*timestamp, 0), format_amounts(&previous_balance));
+        }
+        items.push(MetaDataItem {
+            kind: "balance".to_string(),
+            state: state.to_owned(),
+            value: blockchain.to_owned(),
+            summary,
+        });
+
+        let proposals = proposals_by_chain.remove(&blockchain).unwrap_or_default();
+        let mut open_total: BTreeMap<String, Decimal> = BTreeMap::new();
+        let mut passed_total: BTreeMap<String, Decimal> = BTreeMap::new();
+        for proposal in &proposals {
+            if OPEN_STATUS.contains(&proposal.status.as_str()) {
+                add_amounts(&mut open_total, proposal.amount.iter().map(|(d, a)| (d, a)));
+            } else if proposal.status == "Passed" {
+                add_amounts(&mut passed_total, proposal.amount.iter().map(|(d, a)| (d, a)));
+            }
+        }
+
+        for proposal in proposals.iter().filter(|p| OPEN_STATUS.contains(&p.status.as_str())) {
+            let shares = share_of_pool(&pool, &proposal.amount)
+                .into_iter()
+                .map(|(denom, share)| match share {
+                    Some(share) => format!("{}% of the {} pool", share, denom),
+                    None => format!("no {} in the pool", denom),
+                })
+                .collect::<Vec<String>>()
+                .join(", ");
+            items.push(MetaDataItem {
+                kind: "spend".to_string(),
+                state: state.to_owned(),
+                value: proposal.proposal_id.to_string(),
+                summary: format!(
+                    "{} #{} {}\nRequests {} for {}\n\nOpen spend requests: {}\nPassed spend requests: {}",
+                    blockchain,
+                    proposal.proposal_id,
+                    proposal.title,
+                    shares,
+                    proposal.recipient,
+                    format_amounts(&open_total),
+                    format_amounts(&passed_total)
+                ),
+            });
+        }
+    }
+    meta_data_entries("community_pool", items)
+}
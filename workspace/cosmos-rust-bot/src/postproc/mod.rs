@@ -0,0 +1,52 @@
+pub mod audit_journal;
+pub mod authz;
+pub mod balance_alerts;
+pub mod auto_compound;
+pub mod community_pool;
+pub mod params;
+pub mod scorecard;
+pub mod settings;
+pub mod staking_reminders;
+pub mod validator_votes;
+pub mod vote_mirror;
+
+use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
+use cosmos_rust_interface::utils::entry::*;
+
+// Entries derived from the HistoryStore, they use the same MetaData layout as the task meta data,
+// so they can be queried and subscribed to via kind/state filters.
+
+pub struct MetaDataItem {
+    pub kind: String,
+    pub state: String,
+    pub value: String,
+    pub summary: String,
+}
+
+pub fn meta_data_entries(origin: &str, items: Vec<MetaDataItem>) -> Vec<CosmosRustBotValue> {
+    let now = Utc::now().timestamp();
+
+    let mut entries = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            CosmosRustBotValue::Entry(Entry::Value(Value {
+                timestamp: now,
+                origin: origin.to_string(),
+                custom_data: CustomData::MetaData(MetaData {
+                    index: i as i32,
+                    kind: item.kind,
+                    state: item.state,
+                    value: item.value,
+                    summary: item.summary,
+                }),
+                imperative: ValueImperative::Notify,
+            }))
+        })
+        .collect::<Vec<CosmosRustBotValue>>();
+
+    CosmosRustBotValue::add_index(&mut entries, "index", "index");
+    CosmosRustBotValue::add_membership(&mut entries, None, origin);
+    CosmosRustBotValue::add_variants_of_memberships(&mut entries, vec!["kind", "state"]);
+    entries
+}
@@ -0,0 +1,290 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
+use heck::ToUpperCamelCase;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::blockchain::gov::{ParamChange, ParamChangeProposalInfo, PARAM_CHANGE_PROPOSALS_TREE};
+use crate::blockchain::params::{params_key, ModuleParams, PARAMS_TREE};
+use crate::store::HistoryStore;
+
+// param change keys are not consistent across modules (e.g. "MaxValidators", "communitytax", "votingparams")
+fn normalize_key(key: &str) -> String {
+    key.to_lowercase().replace('_', "")
+}
+
+fn normalize_value(value: &str) -> serde_json::Value {
+    match serde_json::from_str::<serde_json::Value>(value) {
+        Ok(serde_json::Value::String(s)) => serde_json::Value::String(s),
+        Ok(serde_json::Value::Number(n)) => serde_json::Value::String(n.to_string()),
+        Ok(serde_json::Value::Bool(b)) => serde_json::Value::String(b.to_string()),
+        Ok(v) => v,
+        Err(_) => serde_json::Value::String(value.to_string()),
+    }
+}
+
+pub fn values_match(a: &str, b: &str) -> bool {
+    match (normalize_value(a), normalize_value(b)) {
+        (serde_json::Value::String(a), serde_json::Value::String(b)) => {
+            match (Decimal::from_str(&a), Decimal::from_str(&b)) {
+                (Ok(a), Ok(b)) => a.normalize() == b.normalize(),
+                _ => a == b,
+            }
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Splits a param change into the fields it touches, object values (e.g. gov "votingparams") are flattened.
+fn changed_fields(change: &ParamChange) -> Vec<(String, String)> {
+    match serde_json::from_str::<serde_json::Value>(&change.value) {
+        Ok(serde_json::Value::Object(map)) => map
+            .into_iter()
+            .map(|(k, v)| (k, v.to_string()))
+            .collect(),
+        _ => vec![(change.key.to_owned(), change.value.to_owned())],
+    }
+}
+
+fn current_value(params: &ModuleParams, field: &str) -> Option<String> {
+    params
+        .iter()
+        .find(|(k, _)| normalize_key(k) == normalize_key(field))
+        .map(|(_, v)| v.to_owned())
+}
+
+pub struct ParamDiff {
+    pub subspace: String,
+    pub field: String,
+    pub current: Option<String>,
+    pub proposed: String,
+}
+
+impl ParamDiff {
+    pub fn is_applied(&self) -> bool {
+        self.current.as_ref().map(|c| values_match(c, &self.proposed)).unwrap_or(false)
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{}/{}: {} → {}",
+            self.subspace,
+            self.field,
+            self.current.as_ref().map(|x| x.as_str()).unwrap_or("(unknown)"),
+            self.proposed
+        )
+    }
+}
+
+pub fn param_diffs(history_store: &HistoryStore, proposal: &ParamChangeProposalInfo) -> Vec<ParamDiff> {
+    let mut diffs = Vec::new();
+    for change in &proposal.changes {
+        let params: Option<ModuleParams> = history_store
+            .latest::<ModuleParams>(PARAMS_TREE, &params_key(&proposal.blockchain, &change.subspace))
+            .map(|(_, p)| p);
+        for (field, proposed) in changed_fields(change) {
+            diffs.push(ParamDiff {
+                subspace: change.subspace.to_owned(),
+                current: params.as_ref().and_then(|p| current_value(p, &field)),
+                field,
+                proposed,
+            });
+        }
+    }
+    diffs
+}
+
+/// True if the params history of the chain shows the change going live: all proposed values match the current
+/// params, and at least one of them did not match an earlier snapshot. Proposals that were already applied before
+/// the params were tracked are not reported, otherwise every old passed proposal would be reported again and again.
+fn went_live(history_store: &HistoryStore, blockchain: &str, diffs: &[ParamDiff]) -> bool {
+    !diffs.is_empty()
+        && diffs.iter().all(|d| d.is_applied())
+        && diffs.iter().any(|d| {
+            history_store
+                .history::<ModuleParams>(PARAMS_TREE, &params_key(blockchain, &d.subspace))
+                .iter()
+                .any(|(_, params)| !current_value(params, &d.field).map(|c| values_match(&c, &d.proposed)).unwrap_or(false))
+        })
+}
+
+/// Renders parameter change proposals as before/after diffs against the current on-chain params.
+/// Passed proposals whose changes went live while the params were tracked additionally get a confirmation entry.
+pub fn param_change_diffs(history_store: &HistoryStore) -> Vec<CosmosRustBotValue> {
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    for (_, proposal) in history_store.values::<ParamChangeProposalInfo>(PARAM_CHANGE_PROPOSALS_TREE) {
+        let diffs = param_diffs(history_store, &proposal);
+        let state = proposal.blockchain.to_upper_camel_case();
+        let value = proposal.proposal_id.to_string();
+        let lines = diffs.iter().map(|d| d.summary()).collect::<Vec<String>>().join("\n");
+
+        if proposal.status == "VotingPeriod" {
+            items.push(MetaDataItem {
+                kind: "diff".to_string(),
+                state,
+                value,
+                summary: format!("🗳 {} #{} {}\n\n{}", proposal.blockchain, proposal.proposal_id, proposal.title, lines),
+            });
+        } else if proposal.status == "Passed" && went_live(history_store, &proposal.blockchain, &diffs) {
+            items.push(MetaDataItem {
+                kind: "applied".to_string(),
+                state,
+                value,
+                summary: format!("✅ {} #{} {}\nThe parameter change is now live on-chain:\n\n{}", proposal.blockchain, proposal.proposal_id, proposal.title, lines),
+            });
+        }
+    }
+    meta_data_entries("param_changes", items)
+}
+
+/// Returns (field, old value, new value) for every field that differs between two snapshots.
+pub fn changed_params(old: &ModuleParams, new: &ModuleParams) -> Vec<(String, Option<String>, Option<String>)> {
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    fields
+        .into_iter()
+        .filter(|f| old.get(*f) != new.get(*f))
+        .map(|f| (f.to_owned(), old.get(f).cloned(), new.get(f).cloned()))
+        .collect()
+}
+
+/// Compares consecutive params snapshots of each chain and module and reports every change.
+pub fn params_change_alerts(history_store: &HistoryStore) -> Vec<CosmosRustBotValue> {
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    for key in history_store.history_keys(PARAMS_TREE) {
+        let history = history_store.history::<ModuleParams>(PARAMS_TREE, &key);
+        let (blockchain, module) = match key.rsplit_once('_') {
+            Some(x) => x,
+            None => {
+                continue;
+            }
+        };
+        for window in history.windows(2) {
+            let (_, old) = &window[0];
+            let (timestamp, new) = &window[1];
+            let changes = changed_params(old, new);
+            if changes.is_empty() {
+                continue;
+            }
+            let lines = changes
+                .iter()
+                .map(|(field, old, new)| {
+                    format!(
+                        "{}/{}: {} → {}",
+                        module,
+                        field,
+                        old.as_ref().map(|x| x.as_str()).unwrap_or("(none)"),
+                        new.as_ref().map(|x| x.as_str()).unwrap_or("(none)")
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            items.push(MetaDataItem {
+                kind: "changed".to_string(),
+                state: blockchain.to_upper_camel_case(),
+                value: format!("{}_{}", module, timestamp),
+                summary: format!("⚙️ {} {} params changed [{}]\n\n{}", blockchain, module, Utc.timestamp(*timestamp, 0), lines),
+            });
+        }
+    }
+    meta_data_entries("params_history", items)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmos_rust_interface::utils::entry::{CustomData, Entry, Value};
+
+    #[test]
+    fn values_match_decimals_and_quotes() {
+        assert!(values_match("\"0.334000000000000000\"", "0.334"));
+        assert!(values_match("\"100\"", "100"));
+        assert!(!values_match("\"0.4\"", "0.334"));
+        assert!(values_match(
+            r#"[{"denom":"uatom","amount":"250000000"}]"#,
+            r#"[{"amount":"250000000","denom":"uatom"}]"#
+        ));
+    }
+
+    #[test]
+    fn changed_params_reports_old_and_new() {
+        let old: ModuleParams = [("quorum".to_string(), "0.4".to_string()), ("threshold".to_string(), "0.5".to_string())].into_iter().collect();
+        let new: ModuleParams = [("quorum".to_string(), "0.334".to_string()), ("threshold".to_string(), "0.5".to_string())].into_iter().collect();
+        assert_eq!(
+            changed_params(&old, &new),
+            vec![("quorum".to_string(), Some("0.4".to_string()), Some("0.334".to_string()))]
+        );
+    }
+
+    #[test]
+    fn went_live_needs_an_earlier_snapshot_with_the_old_value() {
+        let history_store = HistoryStore::new(sled::Config::new().temporary(true).open().unwrap());
+        let snapshot = |quorum: &str| -> ModuleParams { [("quorum".to_string(), quorum.to_string())].into_iter().collect() };
+        let diffs = || {
+            vec![ParamDiff {
+                subspace: "gov".to_string(),
+                field: "quorum".to_string(),
+                current: Some("0.334".to_string()),
+                proposed: "0.334".to_string(),
+            }]
+        };
+
+        // applied before tracking started
+        history_store.insert(PARAMS_TREE, &format!("{}/{:020}", params_key("osmosis", "gov"), 1), &snapshot("0.334")).unwrap();
+        assert!(!went_live(&history_store, "osmosis", &diffs()));
+
+        // applied while tracked
+        history_store.insert(PARAMS_TREE, &format!("{}/{:020}", params_key("juno", "gov"), 1), &snapshot("0.4")).unwrap();
+        history_store.insert(PARAMS_TREE, &format!("{}/{:020}", params_key("juno", "gov"), 2), &snapshot("0.334")).unwrap();
+        assert!(went_live(&history_store, "juno", &diffs()));
+    }
+
+    /// The changed lines of the "changed" entries.
+    fn alerts(history_store: &HistoryStore) -> Vec<String> {
+        params_change_alerts(history_store)
+            .iter()
+            .filter_map(|entry| match entry {
+                CosmosRustBotValue::Entry(Entry::Value(Value {
+                    custom_data: CustomData::MetaData(meta_data),
+                    ..
+                })) if meta_data.kind == "changed" => meta_data.summary.split("\n\n").nth(1).map(|x| x.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_snapshots_do_not_alert() {
+        let history_store = HistoryStore::new(sled::Config::new().temporary(true).open().unwrap());
+        let snapshot = |quorum: &str| -> ModuleParams {
+            [
+                ("voting_period".to_string(), "1209600000000000".to_string()),
+                ("quorum".to_string(), quorum.to_string()),
+                ("threshold".to_string(), "0.5".to_string()),
+                ("veto_threshold".to_string(), "0.334".to_string()),
+                ("min_deposit".to_string(), r#"[{"amount":"250000000","denom":"uatom"}]"#.to_string()),
+                ("max_deposit_period".to_string(), "1209600000000000".to_string()),
+            ]
+            .into_iter()
+            .collect()
+        };
+        let key = params_key("cosmoshub", "gov");
+        history_store.insert(PARAMS_TREE, &format!("{}/{:020}", key, 1), &snapshot("0.4")).unwrap();
+        history_store.insert(PARAMS_TREE, &format!("{}/{:020}", key, 2), &snapshot("0.4")).unwrap();
+        assert!(alerts(&history_store).is_empty());
+
+        history_store.insert(PARAMS_TREE, &format!("{}/{:020}", key, 3), &snapshot("0.334")).unwrap();
+        assert_eq!(alerts(&history_store), vec!["gov/quorum: 0.4 → 0.334".to_string()]);
+    }
+
+    #[test]
+    fn normalize_key_across_modules() {
+        assert_eq!(normalize_key("MaxValidators"), normalize_key("max_validators"));
+        assert_eq!(normalize_key("communitytax"), normalize_key("community_tax"));
+    }
+}
@@ -0,0 +1,146 @@
+use heck::ToUpperCamelCase;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::blockchain::gov::{ProposalValidatorVotes, VALIDATOR_VOTES_TREE};
+use crate::store::HistoryStore;
+
+const TOP_VALIDATORS: usize = 20;
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ValidatorScore {
+    pub operator_address: String,
+    pub moniker: String,
+    /// finished proposals the validator was eligible to vote on (in the active set).
+    pub proposals: usize,
+    pub votes: usize,
+    pub votes_with_majority: usize,
+    pub participation_rate: f64,
+    pub majority_rate: f64,
+    /// average seconds between the voting_start_time of the proposal and the block time of the vote.
+    pub average_latency: Option<i64>,
+}
+
+#[derive(Default)]
+struct Tally {
+    moniker: String,
+    proposals: usize,
+    votes: usize,
+    votes_with_majority: usize,
+    latencies: Vec<i64>,
+}
+
+fn rate(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        (part as f64 / total as f64 * 10000.0).round() / 100.0
+    }
+}
+
+/// Ranks the validators of each chain by their governance participation on finished proposals.
+pub fn validator_scorecards(history_store: &HistoryStore) -> BTreeMap<String, Vec<ValidatorScore>> {
+    let mut tallies: BTreeMap<String, BTreeMap<String, Tally>> = BTreeMap::new();
+
+    for (_, proposal) in history_store.values::<ProposalValidatorVotes>(VALIDATOR_VOTES_TREE) {
+        let majority = match &proposal.final_majority {
+            Some(majority) => majority.to_owned(),
+            None => {
+                continue;
+            }
+        };
+        let chain = tallies.entry(proposal.blockchain.to_owned()).or_insert_with(BTreeMap::new);
+        for vote in proposal.votes.iter() {
+            let tally = chain.entry(vote.validator.operator_address.to_owned()).or_insert_with(Tally::default);
+            tally.moniker = vote.validator.moniker.to_owned();
+            tally.proposals += 1;
+            if let Some(option) = &vote.option {
+                tally.votes += 1;
+                if option == &majority {
+                    tally.votes_with_majority += 1;
+                }
+                if let Some(voted_at) = vote.voted_at {
+                    tally.latencies.push((voted_at - proposal.voting_start_time).max(0));
+                }
+            }
+        }
+    }
+
+    tallies
+        .into_iter()
+        .map(|(blockchain, chain)| {
+            let mut scores = chain
+                .into_iter()
+                .map(|(operator_address, t)| ValidatorScore {
+                    operator_address,
+                    moniker: t.moniker,
+                    proposals: t.proposals,
+                    votes: t.votes,
+                    votes_with_majority: t.votes_with_majority,
+                    participation_rate: rate(t.votes, t.proposals),
+                    majority_rate: rate(t.votes_with_majority, t.votes),
+                    average_latency: if t.latencies.is_empty() {
+                        None
+                    } else {
+                        Some(t.latencies.iter().sum::<i64>() / t.latencies.len() as i64)
+                    },
+                })
+                .collect::<Vec<ValidatorScore>>();
+            scores.sort_by(|a, b| {
+                b.participation_rate
+                    .partial_cmp(&a.participation_rate)
+                    .unwrap()
+                    .then(b.majority_rate.partial_cmp(&a.majority_rate).unwrap())
+                    .then(a.average_latency.unwrap_or(i64::MAX).cmp(&b.average_latency.unwrap_or(i64::MAX)))
+            });
+            (blockchain, scores)
+        })
+        .collect()
+}
+
+pub fn validator_scorecard_entries(scorecards: &BTreeMap<String, Vec<ValidatorScore>>) -> Vec<CosmosRustBotValue> {
+    let mut items: Vec<MetaDataItem> = Vec::new();
+    for (blockchain, scores) in scorecards {
+        let lines = scores
+            .iter()
+            .take(TOP_VALIDATORS)
+            .enumerate()
+            .map(|(i, s)| {
+                format!(
+                    "{}. {} - participation {}% ({}/{}), with majority {}%, avg. latency {}",
+                    i + 1,
+                    s.moniker,
+                    s.participation_rate,
+                    s.votes,
+                    s.proposals,
+                    s.majority_rate,
+                    s.average_latency.map(|l| format!("{}h", l / 3600)).unwrap_or("-".to_string())
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        items.push(MetaDataItem {
+            kind: "scorecard".to_string(),
+            state: blockchain.to_upper_camel_case(),
+            value: blockchain.to_owned(),
+            summary: format!("🏛 {} validator governance scorecard\n\n{}", blockchain, lines),
+        });
+    }
+    meta_data_entries("validator_scorecard", items)
+}
+
+/// Writes the scorecards to {path}validator_scorecard_{blockchain}.json, served by cosmos-rust-server.
+pub fn export_validator_scorecards(scorecards: &BTreeMap<String, Vec<ValidatorScore>>, path: &str) -> anyhow::Result<()> {
+    fs::create_dir_all(path)?;
+    for (blockchain, scores) in scorecards {
+        fs::write(
+            format!("{}validator_scorecard_{}.json", path, blockchain),
+            serde_json::to_string(scores)?,
+        )?;
+    }
+    Ok(())
+}
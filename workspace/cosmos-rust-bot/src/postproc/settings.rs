@@ -0,0 +1,19 @@
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use bot_library::shared::SettingsError;
+
+use super::{meta_data_entries, MetaDataItem};
+
+/// Published next to the task errors, so `errors` in the Telegram chat shows why a settings change was not applied.
+pub fn settings_validation_errors(validation_error: Option<SettingsError>) -> Vec<CosmosRustBotValue> {
+    let items = validation_error
+        .into_iter()
+        .map(|err| MetaDataItem {
+            kind: "error".to_string(),
+            state: "settings".to_string(),
+            value: err.path.to_owned(),
+            summary: format!("⚠️ {}\nThe last valid settings stay in use until the file is fixed.", err),
+        })
+        .collect();
+    meta_data_entries("task_meta_data_errors", items)
+}
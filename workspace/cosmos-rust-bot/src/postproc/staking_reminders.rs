@@ -0,0 +1,75 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
+use heck::ToUpperCamelCase;
+
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::blockchain::staking::{StakingSchedule, STAKING_SCHEDULE_TREE};
+use crate::store::HistoryStore;
+
+/// "pending", "reminder" once the completion is within the lead time, "completed" afterwards.
+fn schedule_state(completion_time: i64, reminder_lead_time: i64, now: i64) -> &'static str {
+    if completion_time <= now {
+        "completed"
+    } else if completion_time - now <= reminder_lead_time {
+        "reminder"
+    } else {
+        "pending"
+    }
+}
+
+/// Reports when unbonded funds become liquid and when redelegating from a validator is possible again.
+/// Subscribe to "reminder" or "completed" to get notified.
+pub fn staking_reminders(history_store: &HistoryStore) -> Vec<CosmosRustBotValue> {
+    let now = Utc::now().timestamp();
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    for (key, schedule) in history_store.values::<StakingSchedule>(STAKING_SCHEDULE_TREE) {
+        let state = schedule.blockchain.to_upper_camel_case();
+        for entry in schedule.unbondings.iter() {
+            let kind = schedule_state(entry.completion_time, schedule.reminder_lead_time, now);
+            let detail = match kind {
+                "completed" => "are liquid",
+                _ => "become liquid",
+            };
+            items.push(MetaDataItem {
+                kind: kind.to_string(),
+                state: state.to_owned(),
+                value: format!("{}_unbonding_{}_{}", key, entry.validator, entry.completion_time),
+                summary: format!(
+                    "⏳ {} unbonding of {} from {} for {}: the funds {} [{}]",
+                    schedule.blockchain,
+                    entry.balance,
+                    entry.validator,
+                    schedule.address,
+                    detail,
+                    Utc.timestamp(entry.completion_time, 0)
+                ),
+            });
+        }
+        for entry in schedule.redelegations.iter() {
+            let kind = schedule_state(entry.completion_time, schedule.reminder_lead_time, now);
+            let detail = match kind {
+                "completed" => "is possible again",
+                _ => "is locked until",
+            };
+            items.push(MetaDataItem {
+                kind: kind.to_string(),
+                state: state.to_owned(),
+                value: format!("{}_redelegation_{}_{}_{}", key, entry.validator_src, entry.validator_dst, entry.completion_time),
+                summary: format!(
+                    "🔁 {} redelegation of {} from {} to {} for {}: redelegating from {} {} [{}]",
+                    schedule.blockchain,
+                    entry.balance,
+                    entry.validator_src,
+                    entry.validator_dst,
+                    schedule.address,
+                    entry.validator_dst,
+                    detail,
+                    Utc.timestamp(entry.completion_time, 0)
+                ),
+            });
+        }
+    }
+    meta_data_entries("staking_reminders", items)
+}
@@ -0,0 +1,160 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
+use heck::ToUpperCamelCase;
+
+use bot_library::shared::vote_preferences::{against_kind, votes_kind};
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::blockchain::gov::{ProposalValidatorVotes, VALIDATOR_VOTES_TREE};
+use crate::store::HistoryStore;
+
+const TOP_VALIDATORS: usize = 20;
+
+fn vote_emoji(option: &Option<String>) -> &'static str {
+    match option.as_ref().map(|x| x.as_str()) {
+        Some("Yes") => "🟢",
+        Some("No") => "🔴",
+        Some("NoWithVeto") => "❌",
+        Some("Abstain") => "⚪",
+        _ => "⏳",
+    }
+}
+
+/// Lists how the top validators voted on each proposal in voting period (kind = votes_kind(proposal_id)).
+/// Votes of validators that differ from the preference of a delegator are published with
+/// kind = against_kind(proposal_id, delegator), so the delegator can subscribe to them.
+/// Finished proposals are kept in the store for the scorecard, they are not listed.
+pub fn validator_vote_breakdown(history_store: &HistoryStore) -> Vec<CosmosRustBotValue> {
+    let now = Utc::now().timestamp();
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    for (_, proposal) in history_store.values::<ProposalValidatorVotes>(VALIDATOR_VOTES_TREE) {
+        if proposal.final_majority.is_some() || proposal.voting_end_time <= now {
+            continue;
+        }
+        let state = proposal.blockchain.to_upper_camel_case();
+        let lines = proposal
+            .votes
+            .iter()
+            .take(TOP_VALIDATORS)
+            .enumerate()
+            .map(|(i, v)| {
+                format!(
+                    "{}. {} {} ({}%): {}",
+                    i + 1,
+                    vote_emoji(&v.option),
+                    v.validator.moniker,
+                    v.voting_power,
+                    v.option.as_ref().map(|x| x.as_str()).unwrap_or("Did not vote")
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        items.push(MetaDataItem {
+            kind: votes_kind(proposal.proposal_id),
+            state: state.to_owned(),
+            value: proposal.proposal_id.to_string(),
+            summary: format!("🗳 {} #{} - top {} validators\n\n{}", proposal.blockchain, proposal.proposal_id, TOP_VALIDATORS, lines),
+        });
+
+        for delegator in proposal.delegators.iter() {
+            for vote in proposal.votes.iter().filter(|v| delegator.validators.contains(&v.validator.operator_address)) {
+                match &vote.option {
+                    Some(option) if option != &delegator.preference.option => {
+                        items.push(MetaDataItem {
+                            kind: against_kind(proposal.proposal_id, &delegator.preference.delegator),
+                            state: state.to_owned(),
+                            value: vote.validator.operator_address.to_owned(),
+                            summary: format!(
+                                "{} {} voted {} on {} #{}, you prefer {}",
+                                vote_emoji(&vote.option),
+                                vote.validator.moniker,
+                                option,
+                                proposal.blockchain,
+                                proposal.proposal_id,
+                                delegator.preference.option
+                            ),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    meta_data_entries("validator_votes", items)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::blockchain::gov::{proposal_key, DelegatorPreference, ValidatorVote};
+    use crate::blockchain::staking::ValidatorInfo;
+    use bot_library::shared::vote_preferences::VotePreference;
+    use cosmos_rust_interface::utils::entry::{CustomData, Entry, Value};
+
+    fn vote(operator_address: &str, moniker: &str, option: Option<&str>) -> ValidatorVote {
+        ValidatorVote {
+            validator: ValidatorInfo {
+                operator_address: operator_address.to_string(),
+                account_address: "".to_string(),
+                moniker: moniker.to_string(),
+                tokens: "100".to_string(),
+            },
+            voting_power: "10".to_string(),
+            option: option.map(|x| x.to_string()),
+            voted_at: None,
+        }
+    }
+
+    /// The summaries of the entries a kind/state filter selects, like the query of the telegram bot.
+    fn query(entries: &[CosmosRustBotValue], kind: &str, state: &str) -> Vec<String> {
+        entries
+            .iter()
+            .filter_map(|entry| match entry {
+                CosmosRustBotValue::Entry(Entry::Value(Value {
+                    custom_data: CustomData::MetaData(meta_data),
+                    ..
+                })) if meta_data.kind == kind && meta_data.state == state => Some(meta_data.summary.to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn votes_and_votes_against_the_preference_are_found_by_kind_and_state() {
+        let history_store = HistoryStore::new(sled::Config::new().temporary(true).open().unwrap());
+        let now = Utc::now().timestamp();
+        let record = |proposal_id: u64, voting_end_time: i64| ProposalValidatorVotes {
+            blockchain: "osmosis".to_string(),
+            proposal_id,
+            voting_start_time: now - 100,
+            voting_end_time,
+            votes: vec![vote("osmovaloper1a", "Alpha", Some("No")), vote("osmovaloper1b", "Beta", Some("Yes"))],
+            final_majority: None,
+            delegators: vec![DelegatorPreference {
+                preference: VotePreference {
+                    blockchain: "osmosis".to_string(),
+                    proposal_id,
+                    delegator: "osmo1delegator".to_string(),
+                    option: "Yes".to_string(),
+                },
+                validators: vec!["osmovaloper1a".to_string(), "osmovaloper1b".to_string()],
+            }],
+        };
+        history_store.insert(VALIDATOR_VOTES_TREE, &proposal_key("osmosis", 7), &record(7, now + 1000)).unwrap();
+        history_store.insert(VALIDATOR_VOTES_TREE, &proposal_key("osmosis", 6), &record(6, now - 10)).unwrap();
+
+        let entries = validator_vote_breakdown(&history_store);
+
+        let votes = query(&entries, &votes_kind(7), "Osmosis");
+        assert_eq!(votes.len(), 1);
+        assert!(votes[0].contains("Alpha (10%): No"));
+
+        let against = query(&entries, &against_kind(7, "osmo1delegator"), "Osmosis");
+        assert_eq!(against, vec!["🔴 Alpha voted No on osmosis #7, you prefer Yes".to_string()]);
+
+        // the voting period of #6 ended
+        assert!(query(&entries, &votes_kind(6), "Osmosis").is_empty());
+        assert!(query(&entries, &against_kind(6, "osmo1delegator"), "Osmosis").is_empty());
+    }
+}
@@ -0,0 +1,45 @@
+use cosmos_rust_interface::cosmos_rust_package::chrono::{TimeZone, Utc};
+use heck::ToUpperCamelCase;
+
+use cosmos_rust_interface::utils::entry::CosmosRustBotValue;
+
+use super::{meta_data_entries, MetaDataItem};
+use crate::control::vote_mirror::{MirroredVote, VOTE_MIRROR_TREE};
+use crate::store::HistoryStore;
+
+/// Lists the mirrored votes, pending ones include the command to veto them before they are cast.
+pub fn vote_mirror_entries(history_store: &HistoryStore) -> Vec<CosmosRustBotValue> {
+    let mut items: Vec<MetaDataItem> = Vec::new();
+
+    for (key, vote) in history_store.values::<MirroredVote>(VOTE_MIRROR_TREE) {
+        let header = format!(
+            "🪞 {} #{} {}\n{} voted {}",
+            vote.blockchain, vote.proposal_id, vote.title, vote.validator, vote.option
+        );
+        let detail = match vote.status.as_str() {
+            "pending" => format!(
+                "The same vote is cast from {} after [{}].\nVeto: /veto_vote_{}_id{}",
+                vote.voter,
+                Utc.timestamp(vote.cast_after, 0),
+                vote.blockchain,
+                vote.proposal_id
+            ),
+            "excluded" => format!("Not mirrored, {} is excluded.", vote.proposal_type),
+            "skipped" => "Not mirrored, it could not be vetoed in time.".to_string(),
+            status => format!("{} from {}", status, vote.voter),
+        };
+        items.push(MetaDataItem {
+            kind: vote.status.to_owned(),
+            state: vote.blockchain.to_upper_camel_case(),
+            value: key,
+            summary: format!(
+                "{}\n{}{}{}",
+                header,
+                detail,
+                vote.txhash.as_ref().map(|h| format!("\ntx: {}", h)).unwrap_or_default(),
+                if vote.note.is_empty() { "".to_string() } else { format!("\n{}", vote.note) }
+            ),
+        });
+    }
+    meta_data_entries("vote_mirror", items)
+}
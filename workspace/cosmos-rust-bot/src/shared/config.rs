@@ -0,0 +1,233 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+// Paths and sockets shared by cosmos-rust-bot, the telegram bot and cosmos-rust-server.
+// Each value comes from the CLI flag, else the environment variable, else the config file, else the default.
+// Several instances can run side by side on one host, each with its own --data-dir.
+
+pub const DEFAULT_CONFIG_PATH: &str = "./cosmos-rust-bot-config.json";
+const CONFIG_PATH_FLAG: &str = "config";
+const CONFIG_PATH_ENV: &str = "CRB_CONFIG";
+
+/// (CLI flag, environment variable)
+const OPTIONS: [(&str, &str); 9] = [
+    ("data-dir", "CRB_DATA_DIR"),
+    ("settings", "CRB_SETTINGS_PATH"),
+    ("query-socket", "CRB_QUERY_SOCKET"),
+    ("notification-socket", "CRB_NOTIFICATION_SOCKET"),
+    ("remote-signer-socket", "CRB_REMOTE_SIGNER_SOCKET"),
+    ("chain-registry", "CRB_CHAIN_REGISTRY"),
+    ("metrics-listen", "CRB_METRICS_LISTEN"),
+    ("telegram-metrics-listen", "CRB_TELEGRAM_METRICS_LISTEN"),
+    ("log-format", "CRB_LOG_FORMAT"),
+];
+
+static LOADED_CONFIG: OnceLock<Config> = OnceLock::new();
+
+lazy_static! {
+    /// The config loaded by init_config, the default if it was not called (e.g. in tests).
+    pub static ref CONFIG: Config = LOADED_CONFIG.get().cloned().unwrap_or_default();
+}
+
+/// Loads the config from the arguments of the running binary and makes it available as CONFIG.
+/// Called first thing in main, so an invalid config is reported instead of panicking on the first use of CONFIG.
+/// Returns the config and the arguments that are not config flags.
+pub fn init_config() -> anyhow::Result<(Config, Vec<String>)> {
+    let (config, args) = Config::from_args(&std::env::args().collect::<Vec<String>>())?;
+    LOADED_CONFIG.set(config.clone()).map_err(|_| anyhow::anyhow!("Error: The config is already loaded"))?;
+    Ok((config, args))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    /// databases, keystore, journals and everything else the bot writes.
+    pub data_dir: String,
+    /// defaults to <data_dir>/cosmos-rust-bot.json
+    pub settings_path: Option<String>,
+    /// defaults to <data_dir>/cosmos_rust_bot_query_socket
+    pub query_socket: Option<String>,
+    /// defaults to <data_dir>/cosmos_rust_bot_notification_socket
+    pub notification_socket: Option<String>,
+    /// if set, the bot signs via the remote signer listening on this socket instead of unlocking the keystore.
+    pub remote_signer_socket: Option<String>,
+    pub chain_registry: String,
+    /// address of the metrics and health endpoints of cosmos-rust-bot (e.g. "127.0.0.1:9464"), disabled if not set.
+    pub metrics_listen: Option<String>,
+    /// same for the telegram bot, which owns the notification queue.
+    pub telegram_metrics_listen: Option<String>,
+    /// "text" or "json", see logging::init_logging.
+    pub log_format: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_dir: "./tmp".to_string(),
+            settings_path: None,
+            query_socket: None,
+            notification_socket: None,
+            remote_signer_socket: None,
+            chain_registry: "./chain-registry".to_string(),
+            metrics_listen: None,
+            telegram_metrics_listen: None,
+            log_format: "text".to_string(),
+        }
+    }
+}
+
+/// Returns the value of a flag given as `--name value` or `--name=value` and the arguments without it.
+fn take_flag(args: Vec<String>, name: &str) -> anyhow::Result<(Option<String>, Vec<String>)> {
+    let flag = format!("--{}", name);
+    let mut value = None;
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            value = Some(args.next().ok_or(anyhow::anyhow!("Error: Missing value for {}", flag))?);
+        } else if let Some(v) = arg.strip_prefix(&format!("{}=", flag)) {
+            value = Some(v.to_string());
+        } else {
+            rest.push(arg);
+        }
+    }
+    Ok((value, rest))
+}
+
+impl Config {
+    /// Reads the config file (--config, CRB_CONFIG or ./cosmos-rust-bot-config.json, a missing default file is fine),
+    /// then applies the environment variables and the CLI flags. Returns the arguments that are not config flags.
+    pub fn from_args(args: &[String]) -> anyhow::Result<(Config, Vec<String>)> {
+        Config::from_args_and_env(args, |name| std::env::var(name).ok())
+    }
+
+    /// Same as from_args with the environment variables looked up by `env`.
+    fn from_args_and_env(args: &[String], env: impl Fn(&str) -> Option<String>) -> anyhow::Result<(Config, Vec<String>)> {
+        let (config_path, mut rest) = take_flag(args.to_vec(), CONFIG_PATH_FLAG)?;
+        let mut config = match config_path.or(env(CONFIG_PATH_ENV)) {
+            Some(path) => Config::load(&path)?,
+            None => match Config::load(DEFAULT_CONFIG_PATH) {
+                Err(err) if err.downcast_ref::<io::Error>().map(|e| e.kind()) == Some(io::ErrorKind::NotFound) => {
+                    Config::default()
+                }
+                result => result?,
+            },
+        };
+        for (flag, name) in OPTIONS {
+            if let Some(value) = env(name) {
+                config.set(flag, value);
+            }
+            let (value, remaining) = take_flag(rest, flag)?;
+            rest = remaining;
+            if let Some(value) = value {
+                config.set(flag, value);
+            }
+        }
+        Ok((config, rest))
+    }
+
+    pub fn load(path: &str) -> anyhow::Result<Config> {
+        let file = fs::read_to_string(path)?;
+        serde_json::from_str(&file).map_err(|err| anyhow::anyhow!("Error: Invalid config {}: {}", path, err))
+    }
+
+    fn set(&mut self, flag: &str, value: String) {
+        match flag {
+            "data-dir" => self.data_dir = value,
+            "settings" => self.settings_path = Some(value),
+            "query-socket" => self.query_socket = Some(value),
+            "notification-socket" => self.notification_socket = Some(value),
+            "remote-signer-socket" => self.remote_signer_socket = Some(value),
+            "chain-registry" => self.chain_registry = value,
+            "metrics-listen" => self.metrics_listen = Some(value),
+            "telegram-metrics-listen" => self.telegram_metrics_listen = Some(value),
+            "log-format" => self.log_format = value,
+            _ => {}
+        }
+    }
+
+    /// A file or directory inside the data directory.
+    pub fn data_path(&self, name: &str) -> String {
+        Path::new(&self.data_dir).join(name).to_string_lossy().to_string()
+    }
+
+    pub fn settings_path(&self) -> String {
+        self.settings_path.clone().unwrap_or(self.data_path("cosmos-rust-bot.json"))
+    }
+
+    pub fn settings_history_path(&self) -> String {
+        self.data_path("cosmos-rust-bot-settings-history.jsonl")
+    }
+
+    pub fn query_socket(&self) -> String {
+        self.query_socket.clone().unwrap_or(self.data_path("cosmos_rust_bot_query_socket"))
+    }
+
+    pub fn notification_socket(&self) -> String {
+        self.notification_socket.clone().unwrap_or(self.data_path("cosmos_rust_bot_notification_socket"))
+    }
+
+    /// where `keys serve-signer` listens.
+    pub fn signer_socket(&self) -> String {
+        self.remote_signer_socket.clone().unwrap_or(self.data_path("cosmos-rust-bot-signer.sock"))
+    }
+
+    /// served by cosmos-rust-server as /static/
+    pub fn public_path(&self) -> String {
+        format!("{}/", self.data_path("public"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Config::from_args_and_env without any CRB_* variables, whatever the environment of the test run.
+    fn from_args(args: &[String]) -> anyhow::Result<(Config, Vec<String>)> {
+        Config::from_args_and_env(args, |_| None)
+    }
+
+    #[test]
+    fn cli_flags_override_the_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json").to_string_lossy().to_string();
+        fs::write(&path, r#"{"data_dir": "/var/lib/crb-a", "query_socket": "/run/crb-a.sock"}"#).unwrap();
+
+        let args: Vec<String> = ["cosmos-rust-bot", "--config", &path, "keys", "--data-dir=/var/lib/crb-b", "accounts"]
+            .iter()
+            .map(|x| x.to_string())
+            .collect();
+        let (config, rest) = from_args(&args).unwrap();
+        assert_eq!(rest, vec!["cosmos-rust-bot", "keys", "accounts"]);
+        assert_eq!(config.data_dir, "/var/lib/crb-b");
+        assert_eq!(config.query_socket(), "/run/crb-a.sock");
+        assert_eq!(config.notification_socket(), "/var/lib/crb-b/cosmos_rust_bot_notification_socket");
+
+        fs::write(&path, r#"{"data_dri": "/var/lib/crb-a"}"#).unwrap();
+        assert!(from_args(&args).is_err());
+        assert!(from_args(&["cosmos-rust-bot".to_string(), "--data-dir".to_string()]).is_err());
+    }
+
+    #[test]
+    fn environment_variables_override_the_config_file_and_flags_override_both() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json").to_string_lossy().to_string();
+        fs::write(&path, r#"{"data_dir": "/var/lib/crb-a", "log_format": "json"}"#).unwrap();
+        let env = |name: &str| match name {
+            "CRB_CONFIG" => Some(path.to_owned()),
+            "CRB_DATA_DIR" => Some("/var/lib/crb-env".to_string()),
+            "CRB_QUERY_SOCKET" => Some("/run/crb-env.sock".to_string()),
+            _ => None,
+        };
+
+        let args: Vec<String> = ["cosmos-rust-bot", "--query-socket", "/run/crb-flag.sock"].iter().map(|x| x.to_string()).collect();
+        let (config, _) = Config::from_args_and_env(&args, env).unwrap();
+        assert_eq!(config.data_dir, "/var/lib/crb-env");
+        assert_eq!(config.query_socket(), "/run/crb-flag.sock");
+        assert_eq!(config.log_format, "json");
+    }
+}
@@ -0,0 +1,90 @@
+use std::str::FromStr;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+// Logging of cosmos-rust-bot and the telegram bot goes through `tracing`, `log` records (e.g. of the interface crate)
+// are forwarded. RUST_LOG sets the levels (only errors by default, like env_logger).
+// With --log-format json each line is a JSON object with the fields of the event and of all spans it happened in:
+// task (task, kind, chain, attempt), command (command_id, user_hash), query (user_hash), query_socket (request)
+// and notification (user_hash, command_id of the command it answers).
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(anyhow::anyhow!("Error: Invalid log format {}, expected text or json", s)),
+        }
+    }
+}
+
+fn subscriber<W>(format: LogFormat, filter: EnvFilter, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_writer(writer);
+    match format {
+        LogFormat::Text => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(builder.json().with_current_span(true).with_span_list(true).finish()),
+    }
+}
+
+/// Installs the global subscriber, to be called once at startup.
+pub fn init_logging(format: &str) -> anyhow::Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("error"));
+    subscriber(format.parse()?, filter, std::io::stderr)
+        .try_init()
+        .map_err(|err| anyhow::anyhow!("Error: Failed to initialize logging: {}", err))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn json_lines_carry_the_span_fields() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = subscriber(LogFormat::Json, EnvFilter::new("info"), move || writer.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("task", task = "osmosis_governance_proposals", chain = "osmosis", attempt = 2);
+            let _enter = span.enter();
+            tracing::error!(error = "timeout", "task failed");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(line["fields"]["message"], "task failed");
+        assert_eq!(line["span"]["name"], "task");
+        assert_eq!(line["span"]["task"], "osmosis_governance_proposals");
+        assert_eq!(line["spans"][0]["attempt"], 2);
+
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Metrics in the Prometheus text format, served together with the health checks by spawn_metrics_server:
+// GET /metrics, GET /healthz (the process is up) and GET /readyz (the chain registry is resolved).
+// Each process (cosmos-rust-bot, the telegram bot) has its own registry and listener.
+
+lazy_static! {
+    pub static ref METRICS: Metrics = Metrics::new();
+}
+
+/// A scraper that does not send its request within this time is disconnected.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricType {
+    Gauge,
+    Counter,
+    /// rendered as `<name>_sum` and `<name>_count`, without quantiles.
+    Summary,
+}
+
+impl MetricType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricType::Gauge => "gauge",
+            MetricType::Counter => "counter",
+            MetricType::Summary => "summary",
+        }
+    }
+}
+
+/// The value of a gauge or counter, the sum and the number of observations of a summary.
+#[derive(Default)]
+struct Sample {
+    value: f64,
+    count: u64,
+}
+
+struct Family {
+    help: String,
+    kind: MetricType,
+    /// rendered label set, e.g. `{chain="osmosis"}` -> sample
+    values: BTreeMap<String, Sample>,
+}
+
+#[derive(Clone)]
+pub struct Metrics {
+    families: Arc<Mutex<BTreeMap<String, Family>>>,
+    ready: Arc<AtomicBool>,
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return "".to_string();
+    }
+    let labels = labels
+        .iter()
+        .map(|(key, value)| format!("{}=\"{}\"", key, value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+        .collect::<Vec<String>>()
+        .join(",");
+    format!("{{{}}}", labels)
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            families: Arc::new(Mutex::new(BTreeMap::new())),
+            ready: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn update(&self, name: &str, help: &str, kind: MetricType, labels: &[(&str, &str)], f: impl FnOnce(&mut Sample)) {
+        let mut families = self.families.lock().unwrap();
+        let family = families.entry(name.to_string()).or_insert(Family {
+            help: help.to_string(),
+            kind,
+            values: BTreeMap::new(),
+        });
+        f(family.values.entry(render_labels(labels)).or_default());
+    }
+
+    pub fn set_gauge(&self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        self.update(name, help, MetricType::Gauge, labels, |x| x.value = value);
+    }
+
+    pub fn inc_counter(&self, name: &str, help: &str, labels: &[(&str, &str)], by: f64) {
+        self.update(name, help, MetricType::Counter, labels, |x| x.value += by);
+    }
+
+    /// Adds an observation (e.g. a duration in seconds) to a summary.
+    pub fn observe(&self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        self.update(name, help, MetricType::Summary, labels, |x| {
+            x.value += value;
+            x.count += 1;
+        });
+    }
+
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::SeqCst);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    pub fn render(&self) -> String {
+        let families = self.families.lock().unwrap();
+        let mut text = String::new();
+        for (name, family) in families.iter() {
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, family.help, name, family.kind.as_str()));
+            for (labels, sample) in family.values.iter() {
+                match family.kind {
+                    MetricType::Summary => {
+                        text.push_str(&format!("{}_sum{} {}\n", name, labels, sample.value));
+                        text.push_str(&format!("{}_count{} {}\n", name, labels, sample.count));
+                    }
+                    _ => text.push_str(&format!("{}{} {}\n", name, labels, sample.value)),
+                }
+            }
+        }
+        text
+    }
+
+    /// (status line, body) for the path of a GET request.
+    fn respond(&self, path: &str) -> (&'static str, String) {
+        match path {
+            "/metrics" => ("200 OK", self.render()),
+            "/healthz" => ("200 OK", "ok\n".to_string()),
+            "/readyz" if self.is_ready() => ("200 OK", "ready\n".to_string()),
+            "/readyz" => ("503 Service Unavailable", "chain registry not resolved\n".to_string()),
+            _ => ("404 Not Found", "not found\n".to_string()),
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+/// Answers one request, the connection is closed afterwards.
+fn handle_connection(metrics: &Metrics, mut stream: TcpStream) {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT)).ok();
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT)).ok();
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() {
+        return;
+    }
+    // the headers are not used, but are read so the connection closes cleanly.
+    let mut header = String::new();
+    while reader.read_line(&mut header).map(|n| n > 0).unwrap_or(false) && header.trim() != "" {
+        header.clear();
+    }
+    // e.g. "GET /metrics HTTP/1.1"
+    let (status, body) = match line.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["GET", path, _] => metrics.respond(path.split('?').next().unwrap_or(path)),
+        _ => ("405 Method Not Allowed", "only GET is supported\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).ok();
+}
+
+/// Serves the metrics and health checks on `address` (e.g. "127.0.0.1:9464").
+/// Each connection is handled in its own thread, so a slow client does not block the health checks.
+pub fn spawn_metrics_server(address: &str, metrics: &Metrics) -> anyhow::Result<std::thread::JoinHandle<()>> {
+    let listener = TcpListener::bind(address)?;
+    let metrics = metrics.clone();
+    Ok(std::thread::spawn(move || {
+        for stream in listener.incoming().filter_map(|x| x.ok()) {
+            let metrics = metrics.clone();
+            std::thread::spawn(move || handle_connection(&metrics, stream));
+        }
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpStream;
+
+    fn get(address: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn metrics_and_readiness_are_served() {
+        let metrics = Metrics::new();
+        metrics.set_gauge("crb_tasks", "Tasks by state.", &[("state", "Resolved")], 3.0);
+        metrics.inc_counter("crb_task_failures_total", "Failed tasks by chain.", &[("chain", "osmosis")], 1.0);
+        metrics.inc_counter("crb_task_failures_total", "Failed tasks by chain.", &[("chain", "osmosis")], 1.0);
+        metrics.observe("crb_task_duration_seconds", "Run time of finished tasks.", &[("kind", "gov")], 1.5);
+        metrics.observe("crb_task_duration_seconds", "Run time of finished tasks.", &[("kind", "gov")], 0.5);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        spawn_metrics_server(&address, &metrics).unwrap();
+
+        // an idle connection does not block the others.
+        let _idle = TcpStream::connect(&address).unwrap();
+
+        let response = get(&address, "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("# TYPE crb_task_failures_total counter\n"));
+        assert!(response.contains("crb_task_failures_total{chain=\"osmosis\"} 2\n"));
+        assert!(response.contains("crb_tasks{state=\"Resolved\"} 3\n"));
+        assert!(response.contains("# TYPE crb_task_duration_seconds summary\n"));
+        assert!(response.contains("crb_task_duration_seconds_sum{kind=\"gov\"} 2\ncrb_task_duration_seconds_count{kind=\"gov\"} 2\n"));
+
+        assert!(get(&address, "/healthz").starts_with("HTTP/1.1 200 OK"));
+        assert!(get(&address, "/readyz").starts_with("HTTP/1.1 503"));
+        metrics.set_ready(true);
+        assert!(get(&address, "/readyz").starts_with("HTTP/1.1 200 OK"));
+        assert!(get(&address, "/unknown").starts_with("HTTP/1.1 404"));
+    }
+}
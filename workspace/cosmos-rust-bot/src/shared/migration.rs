@@ -0,0 +1,104 @@
+use serde_json::Value;
+use std::fs;
+
+use super::write_atomically;
+
+// Versioned JSON files (settings, feature list) are upgraded step by step when they are loaded.
+// migrations[n] upgrades a document from version n to n + 1, a document without "version" is version 0.
+// The current version is the number of migrations, new fields with a default do not need a migration.
+
+pub type Migration = fn(&mut Value);
+
+pub fn version_of(value: &Value) -> u64 {
+    value.get("version").and_then(|v| v.as_u64()).unwrap_or(0)
+}
+
+/// Applies the outstanding migrations, returns the version the document had before.
+pub fn migrate(value: &mut Value, migrations: &[Migration]) -> anyhow::Result<u64> {
+    let from = version_of(value);
+    let current = migrations.len() as u64;
+    if from > current {
+        return Err(anyhow::anyhow!(
+            "Error: version {} is newer than this build supports (version {})",
+            from,
+            current
+        ));
+    }
+    for (version, migration) in migrations.iter().enumerate().skip(from as usize) {
+        migration(value);
+        if let Some(object) = value.as_object_mut() {
+            object.insert("version".to_string(), Value::from(version as u64 + 1));
+        }
+    }
+    Ok(from)
+}
+
+/// Migrates the file in place, the original is kept as `<path>.v<version>.bak`.
+pub fn migrate_file(path: &str, file: &str, migrations: &[Migration]) -> anyhow::Result<Value> {
+    let mut value: Value = serde_json::from_str(file)?;
+    let from = migrate(&mut value, migrations)?;
+    if from < migrations.len() as u64 {
+        fs::write(format!("{}.v{}.bak", path, from), file)?;
+        write_atomically(path, &serde_json::to_string_pretty(&value)?)?;
+    }
+    Ok(value)
+}
+
+/// v0 -> v1: drops the Terra-era `terra_wallet_address` (the address is derived from the key)
+/// and the `remove` flag that deleted the settings file after loading it.
+fn settings_v0_drop_terra_leftovers(value: &mut Value) {
+    if let Some(object) = value.as_object_mut() {
+        object.remove("terra_wallet_address");
+        object.remove("remove");
+    }
+}
+
+/// v1 -> v2: drops the `prefix` of the authz and auto_compound entries, the account prefix comes from the chain registry.
+fn settings_v1_drop_account_prefixes(value: &mut Value) {
+    for field in ["authz", "auto_compound"] {
+        if let Some(entries) = value.get_mut(field).and_then(|x| x.as_array_mut()) {
+            for entry in entries.iter_mut().filter_map(|x| x.as_object_mut()) {
+                entry.remove("prefix");
+            }
+        }
+    }
+}
+
+pub const SETTINGS_MIGRATIONS: [Migration; 2] = [settings_v0_drop_terra_leftovers, settings_v1_drop_account_prefixes];
+pub const SETTINGS_VERSION: u64 = SETTINGS_MIGRATIONS.len() as u64;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn old_settings_file_is_migrated_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json").to_string_lossy().to_string();
+        let file = r#"{"governance_proposal_notifications": true, "pause_requested": false, "hot_reload": false, "remove": false, "test": true, "terra_wallet_address": null}"#;
+
+        let value = migrate_file(&path, file, &SETTINGS_MIGRATIONS).unwrap();
+        assert_eq!(version_of(&value), SETTINGS_VERSION);
+        assert!(value.get("remove").is_none());
+        assert_eq!(fs::read_to_string(format!("{}.v0.bak", path)).unwrap(), file);
+        assert_eq!(serde_json::from_str::<Value>(&fs::read_to_string(&path).unwrap()).unwrap(), value);
+
+        let newer = serde_json::json!({"version": SETTINGS_VERSION + 1}).to_string();
+        assert!(migrate_file(&path, &newer, &SETTINGS_MIGRATIONS).is_err());
+    }
+
+    #[test]
+    fn account_prefixes_are_dropped_from_authz_and_auto_compound() {
+        let mut value = serde_json::json!({
+            "version": 1,
+            "authz": [{"blockchain": "cosmoshub", "prefix": "cosmos", "granter": "cosmos1granter"}],
+            "auto_compound": [{"blockchain": "cosmoshub", "prefix": "cosmos", "denom": "uatom", "threshold": 1000000, "gas_limit": 400000, "fee": 5000, "gas_budget": 1000000}]
+        });
+
+        assert_eq!(migrate(&mut value, &SETTINGS_MIGRATIONS).unwrap(), 1);
+        assert_eq!(version_of(&value), 2);
+        assert_eq!(value["authz"][0], serde_json::json!({"blockchain": "cosmoshub", "granter": "cosmos1granter"}));
+        assert!(value["auto_compound"][0].get("prefix").is_none());
+        assert_eq!(value["auto_compound"][0]["denom"], "uatom");
+    }
+}
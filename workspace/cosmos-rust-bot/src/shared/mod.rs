@@ -4,34 +4,164 @@ use rust_decimal::Decimal;
 use std::str::FromStr;
 use std::fs;
 use std::io;
+use std::io::Write;
 use std::hash::{Hash};
+use std::collections::HashMap;
+use std::fmt;
 use serde_json::{Value};
 
-// todo: add functionality to add/remove/edit settings in-memory & the settings file on disk (load, edit, save).
+pub mod config;
+pub mod logging;
+pub mod metrics;
+pub mod migration;
+pub mod settings;
+pub mod vote_preferences;
+pub mod whitelist;
+
+// Changing the settings at runtime (in-memory & on disk) is done via settings::SettingsService.
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct UserSettings {
+    /// schema version, older files are upgraded by migration::SETTINGS_MIGRATIONS when loaded.
+    pub version: u64,
     pub governance_proposal_notifications: bool,
     pub pause_requested: bool,
     pub hot_reload: bool,
-    pub remove: bool,
     pub test: bool,
-    pub terra_wallet_address: Option<String>,
+    #[serde(default)]
+    pub auto_compound: Vec<AutoCompoundSettings>,
+    #[serde(default)]
+    pub authz: Vec<AuthzSettings>,
+    #[serde(default)]
+    pub policy: PolicySettings,
+    #[serde(default)]
+    pub vote_mirror: Vec<VoteMirrorSettings>,
+    #[serde(default)]
+    pub watched_addresses: Vec<WatchedAddress>,
+}
+
+/// Hard limits checked before any transaction is simulated or broadcast.
+/// Amounts are per denom in the smallest unit. A denom without a limit is denied, unless a limit for "*" is set.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PolicySettings {
+    /// message type urls the bot may sign, nothing is allowed if empty.
+    #[serde(default)]
+    pub allowed_msg_types: Vec<String>,
+    #[serde(default)]
+    pub max_amount_per_msg: HashMap<String, u64>,
+    /// rolling 24h window over all broadcast transactions.
+    #[serde(default)]
+    pub max_amount_per_day: HashMap<String, u64>,
+    /// None allows any recipient.
+    #[serde(default)]
+    pub allowed_recipients: Option<Vec<String>>,
+    /// None allows any validator.
+    #[serde(default)]
+    pub allowed_validators: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_fee: HashMap<String, u64>,
+}
+
+/// The bot acts on behalf of the granter (the owner's account) as authz grantee, signing with its own key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AuthzSettings {
+    pub blockchain: String,
+    pub granter: String,
+}
+
+/// Claims the staking rewards on a chain and delegates them again once they pass the threshold.
+/// All amounts are in the smallest unit of the staking denom.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AutoCompoundSettings {
+    pub blockchain: String,
+    /// staking denom, e.g. "uatom"
+    pub denom: String,
+    pub threshold: u64,
+    pub gas_limit: u64,
+    /// fee paid per transaction
+    pub fee: u64,
+    /// total fees that may be spent on compounding
+    pub gas_budget: u64,
+}
+
+/// Casts the same vote as the followed validator on every proposal in voting period.
+/// Via authz if a granter is configured for the chain, otherwise from the bot's own account.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VoteMirrorSettings {
+    pub blockchain: String,
+    /// operator address of the validator to follow
+    pub validator: String,
+    /// seconds between seeing the validator's vote and casting it, the time left to veto.
+    pub delay: i64,
+    /// proposal content type urls that are never mirrored, e.g. "/cosmos.upgrade.v1beta1.SoftwareUpgradeProposal"
+    #[serde(default)]
+    pub excluded_proposal_types: Vec<String>,
+    /// telegram user hashes that may veto, nobody may veto if empty.
+    #[serde(default)]
+    pub veto_users: Vec<u64>,
+    pub fee_denom: String,
+    pub fee: u64,
+    pub gas_limit: u64,
+}
+
+fn default_reminder_lead_time() -> i64 {
+    60 * 60 * 24
+}
+
+/// Reports every amount, dust included, unless min_amounts are configured.
+fn default_min_amounts() -> HashMap<String, u64> {
+    [("*".to_string(), 1)].into_iter().collect()
+}
+
+/// An address the bot keeps an eye on, no key is needed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WatchedAddress {
+    pub blockchain: String,
+    pub address: String,
+    /// seconds before an unbonding or redelegation completes to send a reminder.
+    #[serde(default = "default_reminder_lead_time")]
+    pub reminder_lead_time: i64,
+    /// smallest amount per denom that is reported as balance change or incoming transfer, to filter out dust spam.
+    /// Denoms without a minimum are not reported, a minimum for "*" applies to every other denom.
+    #[serde(default = "default_min_amounts")]
+    pub min_amounts: HashMap<String, u64>,
 }
 
 impl Default for UserSettings {
     fn default() -> UserSettings {
         UserSettings {
+            version: migration::SETTINGS_VERSION,
             governance_proposal_notifications: true,
             pause_requested: false,
             hot_reload: false,
-            remove: false,
             test: true,
-            terra_wallet_address: None,
+            auto_compound: Vec::new(),
+            authz: Vec::new(),
+            policy: PolicySettings::default(),
+            vote_mirror: Vec::new(),
+            watched_addresses: Vec::new(),
         }
     }
 }
 
+impl UserSettings {
+    pub fn authz_granter(&self, blockchain: &str) -> Option<&AuthzSettings> {
+        self.authz.iter().find(|x| x.blockchain == blockchain)
+    }
+
+    pub fn may_veto(&self, blockchain: &str, user_hash: u64) -> bool {
+        self.vote_mirror
+            .iter()
+            .any(|x| x.blockchain == blockchain && x.veto_users.contains(&user_hash))
+    }
+}
+
 pub fn get_input(prompt: &str) -> String {
     println!("{}", prompt);
     let mut input = String::new();
@@ -42,37 +172,102 @@ pub fn get_input(prompt: &str) -> String {
     input.trim().to_string()
 }
 
-pub fn load_asset_whitelist(path: &str) -> Value {
-    serde_json::json!({
-        "contracts": serde_json::from_str::<Value>(&fs::read_to_string(format!("{}contracts.json", path)).unwrap()).unwrap(),
-        "pairs_dex": serde_json::from_str::<Value>(&fs::read_to_string(format!("{}pairs.dex.json", path)).unwrap()).unwrap(),
-        "pairs": serde_json::from_str::<Value>(&fs::read_to_string(format!("{}pairs.json", path)).unwrap()).unwrap(),
-        "tokens": serde_json::from_str::<Value>(&fs::read_to_string(format!("{}tokens.json", path)).unwrap()).unwrap(),
-        "custom": serde_json::from_str::<Value>(&fs::read_to_string(format!("{}custom.json", path)).unwrap()).unwrap(),
-    })
+/// Writes to a temporary file next to the target and renames it, readers never see a partial file.
+pub fn write_atomically(path: &str, contents: &str) -> io::Result<()> {
+    let tmp_path = format!("{}.tmp", path);
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
 }
 
-pub fn load_user_settings(path: &str) -> UserSettings {
-    let user_settings: UserSettings = match fs::read_to_string(path) {
-        Ok(file) => {
-            match serde_json::from_str(&file) {
-                Ok(res) => {
-                    res
-                }
-                Err(err) => {
-                    println!("{:?}", err);
-                    Default::default()
+/// A setting that was rejected, path is the field, e.g. "policy.max_fee.uatom".
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingsError {
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() || self.path == "." {
+            write!(f, "Invalid settings: {}", self.message)
+        } else {
+            write!(f, "Invalid setting {}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl SettingsError {
+    fn new(path: &str, message: &str) -> SettingsError {
+        SettingsError {
+            path: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl UserSettings {
+    /// Checks what serde can not, e.g. one entry per chain.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.version != migration::SETTINGS_VERSION {
+            return Err(SettingsError::new("version", &format!("expected {}", migration::SETTINGS_VERSION)));
+        }
+        fn unique<'a>(field: &str, blockchains: impl Iterator<Item = &'a String>) -> Result<(), SettingsError> {
+            let mut seen: Vec<&String> = Vec::new();
+            for (i, blockchain) in blockchains.enumerate() {
+                if seen.contains(&blockchain) {
+                    return Err(SettingsError::new(&format!("{}[{}].blockchain", field, i), &format!("{} is configured twice", blockchain)));
                 }
+                seen.push(blockchain);
+            }
+            Ok(())
+        }
+        unique("auto_compound", self.auto_compound.iter().map(|x| &x.blockchain))?;
+        unique("authz", self.authz.iter().map(|x| &x.blockchain))?;
+        unique("vote_mirror", self.vote_mirror.iter().map(|x| &x.blockchain))?;
+        for (i, x) in self.auto_compound.iter().enumerate() {
+            if x.fee > x.gas_budget {
+                return Err(SettingsError::new(&format!("auto_compound[{}].fee", i), "exceeds the gas_budget"));
             }
         }
+        for (i, x) in self.vote_mirror.iter().enumerate() {
+            if x.delay < 0 {
+                return Err(SettingsError::new(&format!("vote_mirror[{}].delay", i), "must not be negative"));
+            }
+        }
+        for (i, x) in self.watched_addresses.iter().enumerate() {
+            if x.reminder_lead_time < 0 {
+                return Err(SettingsError::new(&format!("watched_addresses[{}].reminder_lead_time", i), "must not be negative"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Unknown fields and invalid values are rejected, the error names the field.
+pub fn parse_user_settings(value: Value) -> Result<UserSettings, SettingsError> {
+    let user_settings: UserSettings = serde_path_to_error::deserialize(value)
+        .map_err(|err| SettingsError::new(&err.path().to_string(), &err.inner().to_string()))?;
+    user_settings.validate()?;
+    Ok(user_settings)
+}
+
+/// The defaults are only used if there is no settings file, an invalid file is an error.
+/// Files of an older schema version are migrated (with a backup) first.
+pub fn load_user_settings(path: &str) -> Result<UserSettings, SettingsError> {
+    let file = match fs::read_to_string(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(Default::default());
+        }
         Err(err) => {
-            println!("{:?}", err);
-            Default::default()
+            return Err(SettingsError::new("", &format!("{}: {}", path, err)));
         }
     };
-    if user_settings.remove {
-        let res = fs::remove_file(path);
-        println!("{:?}", res);
-    }
-    user_settings
+    let value = migration::migrate_file(path, &file, &migration::SETTINGS_MIGRATIONS)
+        .map_err(|err| SettingsError::new("", &format!("{}: {}", path, err)))?;
+    parse_user_settings(value)
 }
@@ -0,0 +1,415 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{load_user_settings, parse_user_settings, write_atomically, SettingsError, UserSettings};
+
+// Settings can be changed while the bot runs (Telegram admin chat, HTTP, CLI) without hand-editing the file.
+// Every change is validated by deserializing it into UserSettings, written atomically and recorded in the history.
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SettingsChange {
+    pub timestamp: i64,
+    /// who made the change, e.g. "telegram:<user_hash>", "http:<user_hash>" or "file"
+    pub origin: String,
+    /// top level setting that changed
+    pub key: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SettingsRequest {
+    /// JSON pointer, e.g. "/governance_proposal_notifications", None returns all settings.
+    Get(Option<String>),
+    /// JSON pointer, value, origin
+    Set(String, Value, String),
+    /// JSON merge patch (RFC 7396), origin
+    Patch(Value, String),
+    /// the latest n changes
+    History(usize),
+}
+
+/// Settings requests share the query socket with the entry queries, `{"Settings": <SettingsRequest>}`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum QuerySocketRequest {
+    Settings(SettingsRequest),
+}
+
+/// A request on the query socket that starts with this is a settings request.
+const SETTINGS_REQUEST_PREFIX: &[u8] = b"{\"Settings\":";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum SettingsResponse {
+    Value(Value),
+    History(Vec<SettingsChange>),
+    Error(String),
+}
+
+#[derive(Clone)]
+pub struct SettingsService {
+    path: String,
+    history_path: String,
+    settings: Arc<RwLock<UserSettings>>,
+    version: Arc<AtomicU64>,
+    /// set if the settings file could not be reloaded, the last valid settings stay in use.
+    validation_error: Arc<RwLock<Option<SettingsError>>>,
+}
+
+fn now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+fn merge_patch(target: &mut Value, patch: &Value) {
+    match (target.as_object_mut(), patch.as_object()) {
+        (Some(target), Some(patch)) => {
+            for (k, v) in patch {
+                if v.is_null() {
+                    target.remove(k);
+                } else {
+                    merge_patch(target.entry(k.to_owned()).or_insert(Value::Null), v);
+                }
+            }
+        }
+        _ => {
+            *target = patch.clone();
+        }
+    }
+}
+
+fn changes(before: &Value, after: &Value, origin: &str) -> Vec<SettingsChange> {
+    let empty = serde_json::Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+    let timestamp = now();
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|k| before.get(*k) != after.get(*k))
+        .map(|k| SettingsChange {
+            timestamp,
+            origin: origin.to_string(),
+            key: k.to_owned(),
+            before: before.get(k).cloned().unwrap_or(Value::Null),
+            after: after.get(k).cloned().unwrap_or(Value::Null),
+        })
+        .collect()
+}
+
+pub fn save_user_settings(path: &str, settings: &UserSettings) -> anyhow::Result<()> {
+    write_atomically(path, &serde_json::to_string_pretty(settings)?)?;
+    Ok(())
+}
+
+impl SettingsService {
+    pub fn load(path: &str, history_path: &str) -> Result<SettingsService, SettingsError> {
+        Ok(SettingsService {
+            path: path.to_string(),
+            history_path: history_path.to_string(),
+            settings: Arc::new(RwLock::new(load_user_settings(path)?)),
+            version: Arc::new(AtomicU64::new(0)),
+            validation_error: Arc::new(RwLock::new(None)),
+        })
+    }
+
+    pub fn get(&self) -> UserSettings {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// Incremented on every change, the main loop restarts its tasks when it differs.
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    pub fn get_value(&self, pointer: &str) -> anyhow::Result<Value> {
+        serde_json::to_value(self.get())?
+            .pointer(pointer)
+            .cloned()
+            .ok_or(anyhow::anyhow!("Error: Unknown setting: {}", pointer))
+    }
+
+    pub fn validation_error(&self) -> Option<SettingsError> {
+        self.validation_error.read().unwrap().clone()
+    }
+
+    pub fn set(&self, settings: UserSettings, origin: &str) -> anyhow::Result<()> {
+        settings.validate()?;
+        let mut current = self.settings.write().unwrap();
+        let changes = changes(&serde_json::to_value(&*current)?, &serde_json::to_value(&settings)?, origin);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        save_user_settings(&self.path, &settings)?;
+        self.append_history(&changes)?;
+        *current = settings;
+        self.version.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn set_value(&self, pointer: &str, value: Value, origin: &str) -> anyhow::Result<UserSettings> {
+        let mut settings = serde_json::to_value(self.get())?;
+        *settings
+            .pointer_mut(pointer)
+            .ok_or(anyhow::anyhow!("Error: Unknown setting: {}", pointer))? = value;
+        let settings = parse_user_settings(settings)?;
+        self.set(settings.clone(), origin)?;
+        Ok(settings)
+    }
+
+    pub fn patch(&self, patch: &Value, origin: &str) -> anyhow::Result<UserSettings> {
+        let mut settings = serde_json::to_value(self.get())?;
+        merge_patch(&mut settings, patch);
+        let settings = parse_user_settings(settings)?;
+        self.set(settings.clone(), origin)?;
+        Ok(settings)
+    }
+
+    /// Picks up changes made to the file by hand. An invalid (or missing) file is reported and the last valid settings are kept.
+    pub fn reload(&self) -> anyhow::Result<()> {
+        let result = if Path::new(&self.path).exists() {
+            load_user_settings(&self.path)
+        } else {
+            // load_user_settings falls back to the defaults, e.g. while an editor replaces the file.
+            Err(SettingsError::new("", &format!("{}: not found", self.path)))
+        };
+        *self.validation_error.write().unwrap() = result.as_ref().err().cloned();
+        self.set(result?, "file")
+    }
+
+    fn append_history(&self, changes: &[SettingsChange]) -> anyhow::Result<()> {
+        let mut file = fs::OpenOptions::new().append(true).create(true).open(&self.history_path)?;
+        for change in changes {
+            file.write_all(format!("{}\n", serde_json::to_string(change)?).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Returns the latest changes, oldest first.
+    pub fn history(&self, n: usize) -> Vec<SettingsChange> {
+        let history: Vec<SettingsChange> = match fs::read_to_string(&self.history_path) {
+            Ok(file) => file
+                .lines()
+                .filter_map(|line| serde_json::from_str::<SettingsChange>(line).ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        history[history.len().saturating_sub(n)..].to_vec()
+    }
+
+    pub fn handle(&self, request: SettingsRequest) -> SettingsResponse {
+        let result = match request {
+            SettingsRequest::Get(None) => serde_json::to_value(self.get()).map_err(anyhow::Error::from),
+            SettingsRequest::Get(Some(pointer)) => self.get_value(&pointer),
+            SettingsRequest::Set(pointer, value, origin) => self
+                .set_value(&pointer, value, &origin)
+                .and_then(|s| Ok(serde_json::to_value(s)?)),
+            SettingsRequest::Patch(patch, origin) => self.patch(&patch, &origin).and_then(|s| Ok(serde_json::to_value(s)?)),
+            SettingsRequest::History(n) => {
+                return SettingsResponse::History(self.history(n));
+            }
+        };
+        match result {
+            Ok(value) => SettingsResponse::Value(value),
+            Err(err) => SettingsResponse::Error(err.to_string()),
+        }
+    }
+}
+
+/// Sends a settings request (e.g. `{"Get": "/pause_requested"}`) via the query socket, returns the SettingsResponse as json.
+pub fn client_send_settings_request(socket_path: &str, request: Value) -> anyhow::Result<Value> {
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.write_all(format!("{}\n", serde_json::json!({ "Settings": request })).as_bytes())?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+#[tracing::instrument(name = "query_socket", skip_all, fields(request = tracing::field::Empty), err(Debug))]
+fn handle_query_socket_connection(stream: UnixStream, store_socket_path: &str, service: &SettingsService) -> anyhow::Result<()> {
+    // only reads as much as is needed to tell the requests apart, the query request format is up to the store.
+    let mut prefix: Vec<u8> = Vec::new();
+    let mut byte = [0u8; 1];
+    while prefix.len() < SETTINGS_REQUEST_PREFIX.len() && SETTINGS_REQUEST_PREFIX.starts_with(&prefix) {
+        if (&stream).read(&mut byte)? == 0 {
+            break;
+        }
+        prefix.push(byte[0]);
+    }
+    if prefix == SETTINGS_REQUEST_PREFIX {
+        tracing::Span::current().record("request", "settings");
+        let mut line = String::from_utf8_lossy(&prefix).to_string();
+        BufReader::new(&stream).read_line(&mut line)?;
+        let response = match serde_json::from_str::<QuerySocketRequest>(&line) {
+            Ok(QuerySocketRequest::Settings(request)) => service.handle(request),
+            Err(err) => SettingsResponse::Error(err.to_string()),
+        };
+        if let SettingsResponse::Error(err) = &response {
+            tracing::warn!(error = %err, "settings request failed");
+        }
+        (&stream).write_all(format!("{}\n", serde_json::to_string(&response)?).as_bytes())?;
+        return Ok(());
+    }
+
+    tracing::Span::current().record("request", "query");
+    let mut upstream = UnixStream::connect(store_socket_path)?;
+    upstream.write_all(&prefix)?;
+    let mut upstream_reader = upstream.try_clone()?;
+    let mut client_writer = stream.try_clone()?;
+    let responses = std::thread::spawn(move || {
+        io::copy(&mut upstream_reader, &mut client_writer).ok();
+        // the store is done, this also ends the copy of the request below.
+        client_writer.shutdown(Shutdown::Both).ok();
+    });
+    io::copy(&mut &stream, &mut upstream).ok();
+    upstream.shutdown(Shutdown::Write).ok();
+    responses.join().ok();
+    Ok(())
+}
+
+/// Serves the query socket: settings requests are handled by the settings service, everything else is passed
+/// on unchanged to the query server of the entry store, which listens on store_socket_path.
+pub fn spawn_query_socket_server(socket_path: &str, store_socket_path: &str, service: &SettingsService) -> anyhow::Result<std::thread::JoinHandle<()>> {
+    if Path::new(socket_path).exists() {
+        fs::remove_file(socket_path)?;
+    }
+    let listener = UnixListener::bind(socket_path)?;
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(socket_path, fs::Permissions::from_mode(0o600))?;
+    }
+    let service = service.clone();
+    let store_socket_path = store_socket_path.to_string();
+    Ok(std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => {
+                    continue;
+                }
+            };
+            let service = service.clone();
+            let store_socket_path = store_socket_path.to_owned();
+            std::thread::spawn(move || {
+                handle_query_socket_connection(stream, &store_socket_path, &service).ok();
+            });
+        }
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_and_patch_are_validated_saved_and_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json").to_string_lossy().to_string();
+        let history_path = dir.path().join("history.jsonl").to_string_lossy().to_string();
+        save_user_settings(&path, &UserSettings::default()).unwrap();
+
+        let service = SettingsService::load(&path, &history_path).unwrap();
+        service.set_value("/pause_requested", Value::Bool(true), "test").unwrap();
+        assert!(service.get().pause_requested);
+        assert!(service.set_value("/pause_requested", Value::from("yes"), "test").is_err());
+        assert!(service.set_value("/unknown", Value::Bool(true), "test").is_err());
+
+        service
+            .patch(&serde_json::json!({"governance_proposal_notifications": false}), "test")
+            .unwrap();
+        assert!(!load_user_settings(&path).unwrap().governance_proposal_notifications);
+        assert_eq!(service.version(), 2);
+
+        let history = service.history(10);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].key, "pause_requested");
+        assert_eq!(history[1].after, Value::Bool(false));
+    }
+
+    #[test]
+    fn invalid_file_is_reported_and_last_valid_settings_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json").to_string_lossy().to_string();
+        let history_path = dir.path().join("history.jsonl").to_string_lossy().to_string();
+        save_user_settings(&path, &UserSettings::default()).unwrap();
+        let service = SettingsService::load(&path, &history_path).unwrap();
+
+        let mut settings = serde_json::to_value(UserSettings::default()).unwrap();
+        settings["pause_requested"] = Value::Bool(true);
+        settings["governance_proposal_notificatons"] = Value::Bool(false);
+        fs::write(&path, settings.to_string()).unwrap();
+
+        assert!(service.reload().is_err());
+        assert!(!service.get().pause_requested);
+        let err = service.validation_error().unwrap();
+        assert!(err.message.contains("governance_proposal_notificatons"));
+        assert!(SettingsService::load(&path, &history_path).is_err());
+
+        settings.as_object_mut().unwrap().remove("governance_proposal_notificatons");
+        settings["policy"] = serde_json::json!({"max_fee": {"uatom": "ten"}});
+        fs::write(&path, settings.to_string()).unwrap();
+        assert!(service.reload().is_err());
+        assert_eq!(service.validation_error().unwrap().path, "policy.max_fee.uatom");
+
+        // a missing file is not replaced by the defaults.
+        service.set_value("/pause_requested", Value::Bool(true), "test").unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(service.reload().is_err());
+        assert!(service.get().pause_requested);
+        assert!(service.validation_error().unwrap().message.contains("not found"));
+    }
+
+    #[test]
+    fn watched_addresses_report_every_denom_by_default() {
+        let mut settings = serde_json::to_value(UserSettings::default()).unwrap();
+        settings["watched_addresses"] = serde_json::json!([
+            {"blockchain": "osmosis", "address": "osmo1..."},
+            {"blockchain": "juno", "address": "juno1...", "min_amounts": {"ujuno": 1000000}}
+        ]);
+        let user_settings = parse_user_settings(settings).unwrap();
+        assert_eq!(user_settings.watched_addresses[0].min_amounts.get("*"), Some(&1));
+        assert_eq!(user_settings.watched_addresses[1].min_amounts.get("*"), None);
+    }
+
+    #[test]
+    fn settings_requests_are_answered_and_other_queries_are_passed_to_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json").to_string_lossy().to_string();
+        let history_path = dir.path().join("history.jsonl").to_string_lossy().to_string();
+        let socket_path = dir.path().join("query_socket").to_string_lossy().to_string();
+        let store_socket_path = dir.path().join("query_socket.store").to_string_lossy().to_string();
+        save_user_settings(&path, &UserSettings::default()).unwrap();
+        let service = SettingsService::load(&path, &history_path).unwrap();
+
+        // stands in for the query server of the entry store.
+        let store = UnixListener::bind(&store_socket_path).unwrap();
+        std::thread::spawn(move || {
+            for stream in store.incoming() {
+                let stream = stream.unwrap();
+                let mut line = String::new();
+                BufReader::new(&stream).read_line(&mut line).unwrap();
+                (&stream).write_all(format!("store: {}", line).as_bytes()).unwrap();
+            }
+        });
+        spawn_query_socket_server(&socket_path, &store_socket_path, &service).unwrap();
+
+        let response = client_send_settings_request(&socket_path, serde_json::json!({"Set": ["/pause_requested", true, "test"]})).unwrap();
+        assert_eq!(response["Value"]["pause_requested"], Value::Bool(true));
+        assert!(service.get().pause_requested);
+        let response = client_send_settings_request(&socket_path, serde_json::json!({"Get": "/unknown"})).unwrap();
+        assert!(response.get("Error").is_some());
+
+        let mut stream = UnixStream::connect(&socket_path).unwrap();
+        stream.write_all(b"{\"query_part\": 1}\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "store: {\"query_part\": 1}\n");
+    }
+}
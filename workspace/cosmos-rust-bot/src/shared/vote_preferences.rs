@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// Vote preferences of delegators, written by the telegram bot (`my vote ..`) and read by the validator votes
+// task of cosmos-rust-bot, which looks up the delegations of the delegator. Each preference is one file in
+// <data_dir>/vote_preferences, named after blockchain, proposal and delegator.
+// Votes of a delegated validator that differ from the preference are published in the validator_votes index
+// with kind = against_kind(..), state = blockchain (UpperCamelCase).
+
+pub const VOTE_PREFERENCES_DIR: &str = "vote_preferences";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VotePreference {
+    pub blockchain: String,
+    pub proposal_id: u64,
+    pub delegator: String,
+    /// "Yes", "No", "NoWithVeto" or "Abstain"
+    pub option: String,
+}
+
+/// Kind of the entry listing how the top validators voted on the proposal.
+pub fn votes_kind(proposal_id: u64) -> String {
+    format!("votes_{}", proposal_id)
+}
+
+/// Kind of the entries reporting votes of validators the delegator delegates to against their preference.
+pub fn against_kind(proposal_id: u64, delegator: &str) -> String {
+    format!("against_{}_{}", proposal_id, delegator)
+}
+
+fn file_name(blockchain: &str, proposal_id: u64, delegator: &str) -> String {
+    format!("{}_{}_{}.json", blockchain, proposal_id, delegator)
+}
+
+pub fn write_preference(dir: &str, preference: &VotePreference) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = Path::new(dir).join(file_name(&preference.blockchain, preference.proposal_id, &preference.delegator));
+    fs::write(path, serde_json::to_string(preference)?)?;
+    Ok(())
+}
+
+pub fn remove_preference(dir: &str, blockchain: &str, proposal_id: u64, delegator: &str) -> anyhow::Result<()> {
+    match fs::remove_file(Path::new(dir).join(file_name(blockchain, proposal_id, delegator))) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// All preferences for proposals of the blockchain, unreadable files are skipped.
+pub fn read_preferences(dir: &str, blockchain: &str) -> Vec<VotePreference> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            return Vec::new();
+        }
+    };
+    let mut preferences: Vec<VotePreference> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|data| serde_json::from_str::<VotePreference>(&data).ok())
+        .filter(|preference| preference.blockchain == blockchain)
+        .collect();
+    preferences.sort_by(|a, b| (a.proposal_id, &a.delegator).cmp(&(b.proposal_id, &b.delegator)));
+    preferences
+}
@@ -0,0 +1,205 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Known tokens, contracts and pairs, in the layout of the assets repositories (one JSON file per kind, keyed by network):
+// tokens.json, contracts.json, pairs.json, pairs.dex.json and custom.json for entries maintained by hand.
+// Several sources can be merged, later sources override entries of earlier ones.
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Token {
+    pub symbol: String,
+    /// contract address (cw20) or denom
+    pub token: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub decimals: Option<u32>,
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Contract {
+    pub name: String,
+    #[serde(default)]
+    pub protocol: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DexPair {
+    pub dex: String,
+    /// contract addresses or denoms
+    pub assets: Vec<String>,
+    #[serde(default, rename = "type")]
+    pub pair_type: Option<String>,
+    #[serde(default, rename = "lpToken")]
+    pub lp_token: Option<String>,
+}
+
+/// All maps are keyed by address, custom entries by denom or address.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AssetWhitelist {
+    pub contracts: HashMap<String, Contract>,
+    /// pair address -> the two assets
+    pub pairs: HashMap<String, Vec<String>>,
+    pub dex_pairs: HashMap<String, DexPair>,
+    pub tokens: HashMap<String, Token>,
+    /// native denoms and corrections, take precedence over tokens.json
+    pub custom: HashMap<String, Token>,
+}
+
+/// A missing file is an empty section, a file that can not be parsed is an error.
+fn load_section<T: DeserializeOwned>(dir: &Path, file_name: &str, network: &str) -> anyhow::Result<HashMap<String, T>> {
+    let path = dir.join(file_name);
+    let file = match fs::read_to_string(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(HashMap::new());
+        }
+        Err(err) => {
+            return Err(anyhow::anyhow!("Error: {}: {}", path.display(), err));
+        }
+    };
+    let mut networks: HashMap<String, HashMap<String, T>> = serde_json::from_str(&file)
+        .map_err(|err| anyhow::anyhow!("Error: {}: {}", path.display(), err))?;
+    Ok(networks.remove(network).unwrap_or_default())
+}
+
+/// Loads the whitelist of one network (e.g. "mainnet") from a directory.
+pub fn load_asset_whitelist(path: &str, network: &str) -> anyhow::Result<AssetWhitelist> {
+    let dir = Path::new(path);
+    if !dir.is_dir() {
+        return Err(anyhow::anyhow!("Error: Asset whitelist directory {} not found", path));
+    }
+    Ok(AssetWhitelist {
+        contracts: load_section(dir, "contracts.json", network)?,
+        pairs: load_section(dir, "pairs.json", network)?,
+        dex_pairs: load_section(dir, "pairs.dex.json", network)?,
+        tokens: load_section(dir, "tokens.json", network)?,
+        custom: load_section(dir, "custom.json", network)?,
+    })
+}
+
+/// Loads and merges several directories in order.
+pub fn load_asset_whitelists(paths: &[&str], network: &str) -> anyhow::Result<AssetWhitelist> {
+    let mut whitelist = AssetWhitelist::default();
+    for path in paths {
+        whitelist.merge(load_asset_whitelist(path, network)?);
+    }
+    Ok(whitelist)
+}
+
+impl AssetWhitelist {
+    pub fn merge(&mut self, other: AssetWhitelist) {
+        self.contracts.extend(other.contracts);
+        self.pairs.extend(other.pairs);
+        self.dex_pairs.extend(other.dex_pairs);
+        self.tokens.extend(other.tokens);
+        self.custom.extend(other.custom);
+    }
+
+    /// By denom (e.g. "uatom", "ibc/...") or cw20 contract address.
+    pub fn token(&self, denom_or_contract: &str) -> Option<&Token> {
+        self.custom
+            .get(denom_or_contract)
+            .or_else(|| self.tokens.get(denom_or_contract))
+            .or_else(|| self.tokens.values().find(|t| t.token == denom_or_contract))
+    }
+
+    pub fn contract(&self, address: &str) -> Option<&Contract> {
+        self.contracts.get(address)
+    }
+
+    /// Address of the pair trading the two assets (in either order), DEX pairs first.
+    /// If several pairs trade them, the lowest address is returned so the result does not depend on the map order.
+    pub fn pair_by_assets(&self, a: &str, b: &str) -> Option<&str> {
+        let matches = |assets: &[String]| assets.len() == 2 && assets.iter().any(|x| x == a) && assets.iter().any(|x| x == b);
+        self.dex_pairs
+            .iter()
+            .filter(|(_, pair)| matches(&pair.assets))
+            .map(|(address, _)| address.as_str())
+            .min()
+            .or_else(|| self.pairs.iter().filter(|(_, assets)| matches(assets)).map(|(address, _)| address.as_str()).min())
+    }
+
+    pub fn dex_pair(&self, address: &str) -> Option<&DexPair> {
+        self.dex_pairs.get(address)
+    }
+
+    /// Human readable name for notifications, e.g. "ATOM", "Astroport ATOM-OSMO pair" or "Mars Red Bank".
+    pub fn label(&self, address_or_denom: &str) -> Option<String> {
+        if let Some(token) = self.token(address_or_denom) {
+            return Some(token.symbol.to_owned());
+        }
+        if let Some(contract) = self.contract(address_or_denom) {
+            return Some(match &contract.protocol {
+                Some(protocol) => format!("{} {}", protocol, contract.name),
+                None => contract.name.to_owned(),
+            });
+        }
+        let assets = self
+            .dex_pairs
+            .get(address_or_denom)
+            .map(|pair| (Some(&pair.dex), &pair.assets))
+            .or_else(|| self.pairs.get(address_or_denom).map(|assets| (None, assets)))?;
+        let symbols = assets
+            .1
+            .iter()
+            .map(|asset| self.token(asset).map(|t| t.symbol.to_owned()).unwrap_or(asset.to_owned()))
+            .collect::<Vec<String>>()
+            .join("-");
+        Some(match assets.0 {
+            Some(dex) => format!("{} {} pair", dex, symbols),
+            None => format!("{} pair", symbols),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sources_are_merged_and_looked_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        fs::write(
+            a.join("tokens.json"),
+            r#"{"mainnet": {"osmo1token": {"symbol": "MARS", "token": "osmo1token", "decimals": 6}}}"#,
+        )
+        .unwrap();
+        fs::write(
+            a.join("pairs.dex.json"),
+            r#"{"mainnet": {"osmo1pair": {"dex": "Osmosis", "type": "xyk", "assets": ["uosmo", "osmo1token"], "lpToken": "gamm/pool/1"}}}"#,
+        )
+        .unwrap();
+        fs::write(a.join("contracts.json"), r#"{"mainnet": {"osmo1bank": {"protocol": "Mars", "name": "Red Bank"}}}"#).unwrap();
+        fs::write(b.join("custom.json"), r#"{"mainnet": {"uosmo": {"symbol": "OSMO", "token": "uosmo", "decimals": 6}}}"#).unwrap();
+
+        let whitelist = load_asset_whitelists(&[a.to_str().unwrap(), b.to_str().unwrap()], "mainnet").unwrap();
+        assert_eq!(whitelist.token("uosmo").unwrap().symbol, "OSMO");
+        assert_eq!(whitelist.token("osmo1token").unwrap().decimals, Some(6));
+        assert_eq!(whitelist.pair_by_assets("osmo1token", "uosmo"), Some("osmo1pair"));
+        assert_eq!(whitelist.pair_by_assets("uosmo", "uatom"), None);
+
+        let mut pools = AssetWhitelist::default();
+        for address in ["osmo1pool3", "osmo1pool1", "osmo1pool2"] {
+            pools.pairs.insert(address.to_string(), vec!["uosmo".to_string(), "uatom".to_string()]);
+        }
+        assert_eq!(pools.pair_by_assets("uatom", "uosmo"), Some("osmo1pool1"));
+        assert_eq!(whitelist.label("osmo1pair").unwrap(), "Osmosis OSMO-MARS pair");
+        assert_eq!(whitelist.label("osmo1bank").unwrap(), "Mars Red Bank");
+
+        fs::write(b.join("tokens.json"), r#"{"mainnet": {"osmo1token": {"token": "osmo1token"}}}"#).unwrap();
+        assert!(load_asset_whitelists(&[a.to_str().unwrap(), b.to_str().unwrap()], "mainnet").is_err());
+        assert!(load_asset_whitelist(dir.path().join("missing").to_str().unwrap(), "mainnet").is_err());
+    }
+}
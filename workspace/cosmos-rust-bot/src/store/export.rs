@@ -0,0 +1,102 @@
+use bot_library::shared::migration::{migrate, SETTINGS_MIGRATIONS};
+use bot_library::shared::settings::save_user_settings;
+use bot_library::shared::{load_user_settings, parse_user_settings};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::HistoryStore;
+
+// `cosmos-rust-bot export` and `import`: the settings, the history store and the subscriptions in one JSON file,
+// to back up an instance or move it to another host or data directory.
+// The bot must be stopped, sled locks its databases while they are open.
+
+pub const EXPORT_VERSION: u64 = 1;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StateExport {
+    pub version: u64,
+    pub settings: serde_json::Value,
+    /// tree -> key -> value
+    pub history: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+    /// the subscription store is owned by cosmos-rust-interface, its trees are copied as is (hex encoded).
+    pub subscriptions: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+fn dump_sled(db: &sled::Db) -> anyhow::Result<BTreeMap<String, BTreeMap<String, String>>> {
+    let mut trees = BTreeMap::new();
+    for name in db.tree_names() {
+        let mut values = BTreeMap::new();
+        for (k, v) in db.open_tree(&name)?.iter().filter_map(|x| x.ok()) {
+            values.insert(hex::encode(&k), hex::encode(&v));
+        }
+        trees.insert(hex::encode(&name), values);
+    }
+    Ok(trees)
+}
+
+/// Replaces the content of the database, like HistoryStore::import.
+fn restore_sled(db: &sled::Db, trees: &BTreeMap<String, BTreeMap<String, String>>) -> anyhow::Result<()> {
+    for name in db.tree_names() {
+        db.open_tree(&name)?.clear()?;
+    }
+    for (name, values) in trees {
+        let tree = db.open_tree(hex::decode(name)?)?;
+        for (k, v) in values {
+            tree.insert(hex::decode(k)?, hex::decode(v)?)?;
+        }
+    }
+    db.flush()?;
+    Ok(())
+}
+
+pub fn export_state(history_store: &HistoryStore, subscription_db: &sled::Db, settings_path: &str) -> anyhow::Result<StateExport> {
+    Ok(StateExport {
+        version: EXPORT_VERSION,
+        settings: serde_json::to_value(load_user_settings(settings_path)?)?,
+        history: history_store.export()?,
+        subscriptions: dump_sled(subscription_db)?,
+    })
+}
+
+/// Replaces the settings, the history and the subscriptions of the instance with the export.
+/// The settings are validated (and migrated) before anything is written.
+pub fn import_state(state: StateExport, history_store: &HistoryStore, subscription_db: &sled::Db, settings_path: &str) -> anyhow::Result<()> {
+    if state.version != EXPORT_VERSION {
+        return Err(anyhow::anyhow!("Error: Unsupported export version {} (expected {})", state.version, EXPORT_VERSION));
+    }
+    let mut settings = state.settings;
+    migrate(&mut settings, &SETTINGS_MIGRATIONS)?;
+    let settings = parse_user_settings(settings)?;
+
+    history_store.import(&state.history)?;
+    restore_sled(subscription_db, &state.subscriptions)?;
+    save_user_settings(settings_path, &settings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn import_replaces_the_stored_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings_path = dir.path().join("settings.json").to_string_lossy().to_string();
+        let history_store = HistoryStore::new(sled::Config::new().temporary(true).open().unwrap());
+        let subscription_db = sled::Config::new().temporary(true).open().unwrap();
+        history_store.insert("proposals", "osmosis/1", &"exported").unwrap();
+        subscription_db.insert("subscription", "exported").unwrap();
+        let state = export_state(&history_store, &subscription_db, &settings_path).unwrap();
+
+        history_store.insert("proposals", "osmosis/2", &"added later").unwrap();
+        history_store.insert("params", "osmosis", &"added later").unwrap();
+        subscription_db.insert("registration", "added later").unwrap();
+        let serialized = serde_json::to_string(&state).unwrap();
+
+        import_state(state, &history_store, &subscription_db, &settings_path).unwrap();
+        let state: StateExport = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(history_store.export().unwrap().into_iter().filter(|(_, v)| !v.is_empty()).collect::<BTreeMap<_, _>>(), state.history);
+        assert_eq!(subscription_db.get("subscription").unwrap().unwrap(), "exported");
+        assert!(subscription_db.get("registration").unwrap().is_none());
+    }
+}
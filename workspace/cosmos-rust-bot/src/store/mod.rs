@@ -0,0 +1,220 @@
+pub mod export;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use cosmos_rust_interface::cosmos_rust_package::chrono::Utc;
+
+// Keeps the data the bot queries itself (params, proposals, ..) across refreshes.
+// The TaskMemoryStore only holds the latest result of each task, this store keeps a history.
+
+#[derive(Clone)]
+pub struct HistoryStore {
+    db: sled::Db,
+}
+
+impl HistoryStore {
+    pub fn new(db: sled::Db) -> HistoryStore {
+        HistoryStore { db }
+    }
+
+    fn history_key(key: &str, timestamp: i64) -> String {
+        // zero padded, such that the lexicographic order of sled equals the chronological order.
+        format!("{}/{:020}", key, timestamp)
+    }
+
+    pub fn insert<T: Serialize>(&self, tree: &str, key: &str, value: &T) -> anyhow::Result<()> {
+        self.db.open_tree(tree)?.insert(key.as_bytes(), serde_json::to_vec(value)?)?;
+        Ok(())
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, tree: &str, key: &str) -> anyhow::Result<Option<T>> {
+        match self.db.open_tree(tree)?.get(key.as_bytes())? {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn values<T: DeserializeOwned>(&self, tree: &str) -> Vec<(String, T)> {
+        match self.db.open_tree(tree) {
+            Ok(tree) => tree
+                .iter()
+                .filter_map(|x| x.ok())
+                .filter_map(|(k, v)| {
+                    serde_json::from_slice::<T>(&v)
+                        .ok()
+                        .map(|v| (String::from_utf8_lossy(&k).to_string(), v))
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Removes all keys with the given prefix that are not listed in keep.
+    pub fn retain(&self, tree: &str, prefix: &str, keep: &[String]) -> anyhow::Result<()> {
+        self.retain_matching(tree, prefix, |_| true, keep)
+    }
+
+    /// Removes all keys with the given prefix for which `owned` is true that are not listed in keep.
+    pub fn retain_matching(&self, tree: &str, prefix: &str, owned: impl Fn(&str) -> bool, keep: &[String]) -> anyhow::Result<()> {
+        let tree = self.db.open_tree(tree)?;
+        for k in tree.scan_prefix(prefix.as_bytes()).keys().filter_map(|k| k.ok()) {
+            let key = String::from_utf8_lossy(&k).to_string();
+            if owned(&key) && !keep.contains(&key) {
+                tree.remove(k)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the value to the history of the key, unless it equals the latest value.
+    /// Returns true if the value was added.
+    pub fn push<T: Serialize + DeserializeOwned + PartialEq>(&self, tree: &str, key: &str, value: &T) -> anyhow::Result<bool> {
+        if let Some((_, latest)) = self.latest::<T>(tree, key) {
+            if &latest == value {
+                return Ok(false);
+            }
+        }
+        let history_key = HistoryStore::history_key(key, Utc::now().timestamp());
+        self.insert(tree, &history_key, value)?;
+        Ok(true)
+    }
+
+    /// Like push, but only the latest `max_len` values of the key are kept.
+    pub fn push_capped<T: Serialize + DeserializeOwned + PartialEq>(&self, tree: &str, key: &str, value: &T, max_len: usize) -> anyhow::Result<bool> {
+        let added = self.push(tree, key, value)?;
+        if added {
+            self.truncate_history(tree, key, max_len)?;
+        }
+        Ok(added)
+    }
+
+    /// Removes all but the latest `max_len` values of the key.
+    pub fn truncate_history(&self, tree: &str, key: &str, max_len: usize) -> anyhow::Result<()> {
+        let tree = self.db.open_tree(tree)?;
+        let keys: Vec<sled::IVec> = tree.scan_prefix(format!("{}/", key).as_bytes()).keys().filter_map(|k| k.ok()).collect();
+        for k in keys.iter().take(keys.len().saturating_sub(max_len)) {
+            tree.remove(k)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the latest `len` values of the key, oldest first, without reading the whole history.
+    pub fn tail<T: DeserializeOwned>(&self, tree: &str, key: &str, len: usize) -> Vec<(i64, T)> {
+        let mut tail: Vec<(i64, T)> = match self.db.open_tree(tree) {
+            Ok(tree) => tree
+                .scan_prefix(format!("{}/", key).as_bytes())
+                .rev()
+                .filter_map(|x| x.ok())
+                .filter_map(|(k, v)| {
+                    let k = String::from_utf8_lossy(&k).to_string();
+                    let timestamp = k.rsplit('/').next()?.parse::<i64>().ok()?;
+                    serde_json::from_slice::<T>(&v).ok().map(|v| (timestamp, v))
+                })
+                .take(len)
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        tail.reverse();
+        tail
+    }
+
+    /// Returns the history of the key, oldest first.
+    pub fn history<T: DeserializeOwned>(&self, tree: &str, key: &str) -> Vec<(i64, T)> {
+        match self.db.open_tree(tree) {
+            Ok(tree) => tree
+                .scan_prefix(format!("{}/", key).as_bytes())
+                .filter_map(|x| x.ok())
+                .filter_map(|(k, v)| {
+                    let k = String::from_utf8_lossy(&k).to_string();
+                    let timestamp = k.rsplit('/').next()?.parse::<i64>().ok()?;
+                    serde_json::from_slice::<T>(&v).ok().map(|v| (timestamp, v))
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn latest<T: DeserializeOwned>(&self, tree: &str, key: &str) -> Option<(i64, T)> {
+        let tree = self.db.open_tree(tree).ok()?;
+        let (k, v) = tree.scan_prefix(format!("{}/", key).as_bytes()).last()?.ok()?;
+        let k = String::from_utf8_lossy(&k).to_string();
+        let timestamp = k.rsplit('/').next()?.parse::<i64>().ok()?;
+        serde_json::from_slice::<T>(&v).ok().map(|v| (timestamp, v))
+    }
+
+    /// Returns the keys that have a history in the given tree.
+    pub fn history_keys(&self, tree: &str) -> Vec<String> {
+        let mut keys: Vec<String> = match self.db.open_tree(tree) {
+            Ok(tree) => tree
+                .iter()
+                .keys()
+                .filter_map(|k| k.ok())
+                .filter_map(|k| {
+                    let k = String::from_utf8_lossy(&k).to_string();
+                    k.rsplit_once('/').map(|(key, _)| key.to_string())
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        keys.dedup();
+        keys
+    }
+
+    pub fn flush(&self) -> anyhow::Result<usize> {
+        Ok(self.db.flush()?)
+    }
+
+    pub fn size_on_disk(&self) -> anyhow::Result<u64> {
+        Ok(self.db.size_on_disk()?)
+    }
+
+    /// All trees, tree -> key -> value.
+    pub fn export(&self) -> anyhow::Result<BTreeMap<String, BTreeMap<String, serde_json::Value>>> {
+        let mut trees = BTreeMap::new();
+        for name in self.db.tree_names() {
+            if name == "__sled__default" {
+                continue;
+            }
+            let mut values = BTreeMap::new();
+            for (k, v) in self.db.open_tree(&name)?.iter().filter_map(|x| x.ok()) {
+                values.insert(String::from_utf8_lossy(&k).to_string(), serde_json::from_slice(&v)?);
+            }
+            trees.insert(String::from_utf8_lossy(&name).to_string(), values);
+        }
+        Ok(trees)
+    }
+
+    /// Replaces the content of the store, trees that are not in the export are left empty.
+    pub fn import(&self, trees: &BTreeMap<String, BTreeMap<String, serde_json::Value>>) -> anyhow::Result<()> {
+        for name in self.db.tree_names() {
+            self.db.open_tree(&name)?.clear()?;
+        }
+        for (tree, values) in trees {
+            for (key, value) in values {
+                self.insert(tree, key, value)?;
+            }
+        }
+        self.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn history_is_truncated_and_read_from_the_tail() {
+        let history_store = HistoryStore::new(sled::Config::new().temporary(true).open().unwrap());
+        for timestamp in 1..=5 {
+            history_store.insert("pool", &HistoryStore::history_key("osmosis", timestamp), &timestamp).unwrap();
+        }
+        history_store.insert("pool", &HistoryStore::history_key("osmosis2", 9), &9).unwrap();
+
+        assert_eq!(history_store.tail::<i64>("pool", "osmosis", 2), vec![(4, 4), (5, 5)]);
+        history_store.truncate_history("pool", "osmosis", 3).unwrap();
+        assert_eq!(history_store.history::<i64>("pool", "osmosis"), vec![(3, 3), (4, 4), (5, 5)]);
+        assert_eq!(history_store.history::<i64>("pool", "osmosis2"), vec![(9, 9)]);
+    }
+}